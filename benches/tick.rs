@@ -0,0 +1,47 @@
+#[macro_use]
+extern crate criterion;
+extern crate ca;
+
+use criterion::{BenchmarkId, Criterion};
+
+use ca::gen;
+use ca::nb::{Boundary, Neighborhood};
+use ca::types::Cell;
+use ca::{CA1, CA2};
+
+const SEED: u64 = 42;
+
+fn bench_life(c: &mut Criterion) {
+    let mut group = c.benchmark_group("life");
+    for &size in &[64, 256, 1024] {
+        let cells = gen::random2d_seeded(SEED, size, size, vec![0, 1], None, None, None, None);
+        let mut ca = CA2::new_life(cells, vec![2, 3], vec![3], Boundary::Wrap).unwrap();
+        group.bench_function(BenchmarkId::from_parameter(size), |b| b.iter(|| ca.tick()));
+    }
+    group.finish();
+}
+
+fn bench_cyclic(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cyclic");
+    for &size in &[64, 256, 1024] {
+        let states: Vec<Cell> = (0..14).collect();
+        let cells = gen::random2d_seeded(SEED, size, size, states, None, None, None, None);
+        let nbh = Neighborhood::Moore(1);
+        let mut ca = CA2::new_cyclic(cells, nbh, 1, 14, Boundary::Wrap).unwrap();
+        group.bench_function(BenchmarkId::from_parameter(size), |b| b.iter(|| ca.tick()));
+    }
+    group.finish();
+}
+
+fn bench_rule30(c: &mut Criterion) {
+    let mut group = c.benchmark_group("rule30");
+    for &width in &[256, 4096, 65536] {
+        let cells = gen::random1d_seeded(SEED, width, vec![0, 1], None, None);
+        let mut ca = CA1::new_elementary(cells, 30);
+        group.bench_function(BenchmarkId::from_parameter(width), |b| b.iter(|| ca.tick()));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_life, bench_cyclic, bench_rule30);
+criterion_main!(benches);
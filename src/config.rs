@@ -1,410 +1,496 @@
-use std::str::FromStr;
+use std::cmp;
+use std::fs::File;
+use std::io::Read;
 
 extern crate getopts;
 use getopts::Matches;
 
 extern crate ca;
+use ca::error::CaError;
+use ca::gen::Symmetry;
+use ca::nb::{Boundary, Topology};
+use ca::spec::{InitSpec, RuleSpec};
 use ca::types::Cell;
+use ca::UpdateMode;
 
-const ERR_INVALID_RANDOM: &'static str = "Invalid 'random' parameters!";
-const ERR_NO_STATES: &'static str = "STATES is not set!";
-const ERR_INVALID_STATES: &'static str = "Invalid STATES value!";
-const ERR_NO_POINTS: &'static str = "POINTS is not set!";
-const ERR_INVALID_POINTS: &'static str = "Invalid POINTS value!";
-
-pub enum Point1D {
-    Abs(usize),
-    RelToCenter(i32),
-}
-
-pub enum Point2D {
-    Abs(usize, usize),
-    RelToCenter(i32, i32),
-}
-
-pub enum CAType {
-    CA1 {
-        radius: u8,
-        states: u8,
-        code: Option<String>,
-    },
-    Elementary(u8), // code
-    Cyclic(ca::nb::Neighborhood, u8, u32), // neighborhood, threshold, states
-    Life(Vec<Cell>, Vec<Cell>), // survive, birth
-}
-
-pub enum InitType {
-    Random {
-        states: Vec<Cell>,
-        x1: Option<usize>,
-        x2: Option<usize>,
-        y1: Option<usize>,
-        y2: Option<usize>,
-    },
-    Points1D(Vec<Point1D>),
-    Points2D(Vec<Point2D>),
+// Figure filling a cell in the window.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CellShape {
+    Rect,
+    Circle,
 }
 
+#[derive(Clone)]
 pub struct Config {
-    pub ca_type: CAType,
-    pub init_type: InitType,
+    pub ca_type: RuleSpec,
+    pub init_type: InitSpec,
+    pub tiles: Vec<(RuleSpec, InitSpec)>, // CA shown next to the first one
     pub size: Option<(u32, u32)>,
-    pub cell_width: Option<u8>,
+    pub cell_size: Option<(u8, u8)>, // width, height
     pub delay: Option<u32>,
+    pub fps: Option<u32>,
+    pub seed: Option<u64>,
+    pub record: Option<String>,
+    pub gif: Option<String>,
+    pub frames: Option<u32>,
+    pub ppm_stream: bool,
+    pub history: Option<usize>,
+    pub palette: Option<Vec<(u8, u8, u8)>>,
+    pub bg: Option<(u8, u8, u8)>, // color of state 0
+    pub headless: bool,
+    pub update: UpdateMode,
+    pub growing: bool,
+    pub scrollback: Option<usize>,
+    pub skip: Option<usize>,
+    pub topology: Option<Topology>,
+    pub symmetry: Symmetry,
+    pub age_colors: bool,
+    pub border: bool,
+    pub shape: CellShape,
+    pub ticks: Option<u32>,
+    pub out: Option<String>,
+    pub until_stable: bool,
+    pub max_ticks: Option<u32>,
+    pub report_json: bool,
+    pub dump_rule: bool,
+    pub random_rule: bool,
 }
 
 impl Config {
     pub fn from_matches(matches: &Matches) -> Result<Config, &'static str> {
-        let ca_type = try!(parse_ca_type(&matches.free));
-        let init_type = try!(parse_init_type(matches.opt_str("init"), &ca_type));
+        // several CA separated by + are shown side by side, every one starts from --init
+        let mut tiles = Vec::new();
+        for spec in matches.free.split(|arg| arg == "+") {
+            let ca_type = try!(RuleSpec::from_args(spec).map_err(spec_error));
+            if ca_type.states() as u64 > Cell::max_value() as u64 + 1 {
+                return Err("CA has more states than the small-cells build supports!");
+            }
+            let init_type = match matches.opt_str("init") {
+                Some(s) => try!(s.parse::<InitSpec>().map_err(spec_error)),
+                None => InitSpec::default(),
+            };
+            try!(init_type.check(&ca_type).map_err(spec_error));
+            tiles.push((ca_type, init_type));
+        }
+        let is_1d = tiles[0].0.is_1d();
+        if tiles.iter().any(|&(ref ca_type, _)| ca_type.is_1d() != is_1d) {
+            return Err("CA shown side by side must be all 1D or all 2D!");
+        }
+        // checks of CA type are done for every tile, the rest of options is shared
+        for &(ref ca_type, ref init_type) in &tiles {
+            match (ca_type, init_type) {
+                (&RuleSpec::Wireworld, &InitSpec::Rle(..)) |
+                (&RuleSpec::Wireworld, &InitSpec::Plaintext(..)) |
+                (&RuleSpec::Wireworld, &InitSpec::Csv(..)) => (),
+                (&RuleSpec::Wireworld, _) => {
+                    return Err("Wireworld requires points:rle:FILE, points:cells:FILE or \
+                                csv:FILE init!");
+                }
+                _ => (),
+            }
+        }
         let size = try!(parse_size(matches.opt_str("size")));
-        let cell_width = try!(match matches.opt_str("cell") {
+        let cell_size = try!(parse_cell_size(matches.opt_str("cell")));
+        let delay = try!(match matches.opt_str("delay") {
             Some(s) => {
-                match s.parse::<u8>() {
+                match s.parse::<u32>() {
                     Ok(x) => Ok(Some(x)),
-                    Err(_) => Err("Cell width must be unsigned 8-bit integer!"),
+                    Err(_) => Err("Delay must be unsigned 32-bit integer!"),
                 }
             }
             None => Ok(None),
         });
-        let delay = try!(match matches.opt_str("delay") {
+        // ticks per second are kept as delay between ticks, so the window and GIF get them
+        // the same way
+        let delay = match matches.opt_str("tps") {
+            Some(_) if delay.is_some() => return Err("--tps and --delay are mutually exclusive!"),
+            Some(s) => {
+                match s.parse::<u32>() {
+                    Ok(x) if x > 0 => Some(1000 / x),
+                    _ => return Err("TPS must be positive 32-bit integer!"),
+                }
+            }
+            None => delay,
+        };
+        let fps = match matches.opt_str("fps") {
             Some(s) => {
                 match s.parse::<u32>() {
+                    Ok(x) if x > 0 => Some(x),
+                    _ => return Err("FPS must be positive 32-bit integer!"),
+                }
+            }
+            None => None,
+        };
+        let seed = try!(match matches.opt_str("seed") {
+            Some(s) => {
+                match s.parse::<u64>() {
                     Ok(x) => Ok(Some(x)),
-                    Err(_) => Err("Delay must be unsigned 32-bit integer!"),
+                    Err(_) => Err("Seed must be unsigned 64-bit integer!"),
                 }
             }
             None => Ok(None),
         });
-        Ok(Config {
-            ca_type: ca_type,
-            init_type: init_type,
-            size: size,
-            cell_width: cell_width,
-            delay: delay,
-        })
-    }
-}
-
-fn parse<F>(args: &Vec<String>, idx: usize) -> Result<(F, usize), ()>
-    where F: FromStr
-{
-    if args.len() <= idx {
-        return Err(());
-    }
-    match args[idx].parse::<F>() {
-        Ok(val) => Ok((val, idx + 1)),
-        Err(_) => Err(()),
-    }
-}
-
-fn parse_ca1(args: &Vec<String>, idx: usize) -> Result<(CAType, usize), &'static str> {
-    let (radius, idx) = try!(parse::<u8>(args, idx)
-        .map_err(|_| "RADIUS must be unsigned 8-bit integer!"));
-    let (states, idx) = try!(parse::<u8>(args, idx)
-        .map_err(|_| "STATES must be unsigned 8-bit integer!"));
-    if args.len() < idx {
-        return Err("Specify CODE value!");
-    }
-    let code = if args[idx] == "random" {
-        None
-    } else {
-        Some(args[idx].clone())
-    };
-    Ok((CAType::CA1 {
-        radius: radius,
-        states: states,
-        code: code,
-    },
-        idx + 1))
-}
-
-fn parse_elementary_ca(args: &Vec<String>, idx: usize) -> Result<(CAType, usize), &'static str> {
-    let (code, idx) = try!(parse::<u8>(args, idx)
-        .map_err(|_| "CODE must be unsigned 8-bit integer!"));
-    Ok((CAType::Elementary(code as u8), idx))
-}
-
-fn parse_neighborhood(args: &Vec<String>,
-                      idx: usize)
-                      -> Result<(ca::nb::Neighborhood, usize), &'static str> {
-    if args.len() <= idx {
-        return Err("Expected neighborhood, found end of args!");
-    }
-    match &args[idx][..1] {
-        c @ "m" | c @ "n" => {
-            match (&args[idx][1..]).parse::<u32>() {
-                Ok(range) => {
-                    Ok((match c {
-                        "m" => ca::nb::Neighborhood::Moore(range),
-                        "n" => ca::nb::Neighborhood::VonNeumann(range),
-                        _ => unreachable!(),
-                    },
-                        idx + 1))
+        let frames = try!(match matches.opt_str("frames") {
+            Some(s) => {
+                match s.parse::<u32>() {
+                    Ok(x) => Ok(Some(x)),
+                    Err(_) => Err("Frames count must be unsigned 32-bit integer!"),
                 }
-                Err(_) => Err("Neighborhood range must be unsigned 32-bit integer!"),
             }
+            None => Ok(None),
+        });
+        let history = try!(match matches.opt_str("history") {
+            Some(s) => {
+                match s.parse::<usize>() {
+                    Ok(x) => Ok(Some(x)),
+                    Err(_) => Err("History size must be unsigned integer!"),
+                }
+            }
+            None => Ok(None),
+        });
+        let palette = match matches.opt_str("palette") {
+            Some(path) => Some(try!(parse_palette_file(&path))),
+            None => None,
+        };
+        let bg = match matches.opt_str("bg") {
+            Some(s) => {
+                Some(try!(parse_color(&s)
+                    .map_err(|_| "Background color must be R,G,B with values 0-255!")))
+            }
+            None => None,
+        };
+        let gif = matches.opt_str("gif");
+        if gif.is_some() && size.is_none() {
+            return Err("Specify --size for GIF export!");
         }
-        _ => Err("Neighborhood must start with 'm' or 'n'!"),
-    }
-}
-
-fn parse_cyclic_ca(args: &Vec<String>, idx: usize) -> Result<(CAType, usize), &'static str> {
-    let (nb, idx) = try!(parse_neighborhood(args, idx));
-    let (threshold, idx) = try!(match parse::<u8>(args, idx) {
-        Ok((val, idx)) => Ok((val, idx)),
-        Err(_) => Err("THRESHOLD must be unsigned 8-bit integer!"),
-    });
-    let (states, idx) = try!(match parse::<u32>(args, idx) {
-        Ok((args, idx)) => Ok((args, idx)),
-        Err(_) => Err("STATES must be unsigned 32-bit integer!"),
-    });
-    Ok((CAType::Cyclic(nb, threshold, states), idx))
-}
-
-fn parse_u32_csv(s: &str, sep: char) -> Result<Vec<u32>, ()> {
-    if s == "empty" {
-        return Ok(Vec::new());
-    }
-    let mut ints: Vec<u32> = Vec::new();
-    for part in s.split(sep) {
-        match part.parse::<u32>() {
-            Ok(x) => ints.push(x),
-            Err(_) => return Err(()),
-        }
-    }
-    Ok(ints)
-}
-
-fn parse_life_ca(args: &Vec<String>, idx: usize) -> Result<(CAType, usize), &'static str> {
-    if args.len() <= idx {
-        return Err("SURVIVE is not set!");
-    }
-    let (survive, idx) = try!(match parse_u32_csv(&args[idx], ',') {
-        Ok(survive) => Ok((survive, idx + 1)),
-        Err(_) => Err("Invalid SURVIVE value!"),
-    });
-    if args.len() <= idx {
-        return Err("BIRTH is not set!");
-    }
-    let (birth, idx) = try!(match parse_u32_csv(&args[idx], ',') {
-        Ok(birth) => Ok((birth, idx + 1)),
-        Err(_) => Err("Invalid BIRTH value!"),
-    });
-    Ok((CAType::Life(survive, birth), idx))
-}
-
-fn parse_ca_type(args: &Vec<String>) -> Result<CAType, &'static str> {
-    if args.len() <= 0 {
-        return Err("Specify CA type!");
-    }
-    let (ca_type, idx) = try!(match &*args[0] {
-        "1" => parse_ca1(args, 1),
-        "elementary" => parse_elementary_ca(args, 1),
-        "cyclic" => parse_cyclic_ca(args, 1),
-        "life" => parse_life_ca(args, 1),
-        _ => Err("Unknown CA type!"),
-    });
-    if idx < args.len() {
-        Err("Trailing args!")
-    } else {
-        Ok(ca_type)
-    }
-}
-
-fn parse_init_state(part: &str) -> Result<(u32, u32), ()> {
-    match part.find('*') {
-        None => {
-            match part.parse::<u32>() {
-                Ok(val) => Ok((val, 1)),
-                Err(_) => Err(()),
+        // layers of coupled CA are blended into colors that aren't in the GIF palette
+        for &(ref ca_type, _) in &tiles {
+            if let RuleSpec::Coupled(..) = *ca_type {
+                if gif.is_some() {
+                    return Err("--gif is not supported for coupled CA!");
+                }
             }
         }
-        Some(pos) => {
-            match part[..pos].parse::<u32>() {
-                Ok(val) => {
-                    match part[pos + 1..].parse::<u32>() {
-                        Ok(count) => Ok((val, count)),
-                        Err(_) => Err(()),
-                    }
+        let ppm_stream = matches.opt_present("ppm-stream");
+        if ppm_stream && size.is_none() {
+            return Err("Specify --size for PPM stream!");
+        }
+        let ticks = try!(match matches.opt_str("ticks") {
+            Some(s) => {
+                match s.parse::<u32>() {
+                    Ok(x) => Ok(Some(x)),
+                    Err(_) => Err("Ticks count must be unsigned 32-bit integer!"),
                 }
-                Err(_) => Err(()),
+            }
+            None => Ok(None),
+        });
+        let update = if matches.opt_present("async") {
+            try!(parse_async_order(matches.opt_str("async")))
+        } else {
+            UpdateMode::Synchronous
+        };
+        let async_update = update != UpdateMode::Synchronous;
+        for &(ref ca_type, _) in &tiles {
+            match *ca_type {
+                RuleSpec::Ant(..) if async_update => {
+                    return Err("--async is not supported for ant!");
+                }
+                RuleSpec::Coupled(..) if async_update => {
+                    return Err("--async is not supported for coupled CA!");
+                }
+                _ if async_update && is_1d => return Err("--async is supported only for 2D CA!"),
+                _ => (),
             }
         }
-    }
-}
-
-fn parse_init_random(s: &str, ca_type: &CAType) -> Result<InitType, &'static str> {
-    if s == "" {
-        return Err(ERR_NO_STATES);
-    }
-    let parts: Vec<&str> = s.split(':').collect();
-    if parts.len() > 2 {
-        return Err(ERR_INVALID_RANDOM);
-    }
-
-    let states = if parts[0] == "uniform" {
-        match *ca_type {
-            CAType::Cyclic(_, _, states) => (0..states).collect(),
-            _ => vec![0, 1],
+        let scrollback = try!(match matches.opt_str("scrollback") {
+            Some(s) => {
+                match s.parse::<usize>() {
+                    Ok(x) => Ok(Some(x)),
+                    Err(_) => Err("Scrollback must be unsigned integer!"),
+                }
+            }
+            None => Ok(None),
+        });
+        let skip = try!(match matches.opt_str("skip") {
+            Some(s) => {
+                match s.parse::<usize>() {
+                    Ok(x) => Ok(Some(x)),
+                    Err(_) => Err("Skip count must be unsigned integer!"),
+                }
+            }
+            None => Ok(None),
+        });
+        let growing = matches.opt_present("growing");
+        if growing && tiles.len() > 1 {
+            return Err("--growing can't be used with several CA!");
         }
-    } else {
-        let mut states = Vec::new();
-        for part in s.split(',') {
-            let (state, count) = try!(parse_init_state(part).map_err(|_| ERR_INVALID_STATES));
-            for _ in 0..count {
-                states.push(state);
+        for &(ref ca_type, _) in &tiles {
+            match *ca_type {
+                // such rules are emulated by alternating two rules every tick of the whole world
+                RuleSpec::Life(ref survive, ref birth) if birth.contains(&0) &&
+                                                        !survive.contains(&8) => {
+                    if growing || async_update {
+                        return Err("--growing and --async don't support life with B0 and \
+                                    without S8!");
+                    }
+                }
+                RuleSpec::Life(..) => (),
+                _ if growing => return Err("--growing is supported only for life!"),
+                _ => (),
             }
         }
-        states
-    };
-
-    let (x1, x2, y1, y2) = if parts.len() == 1 {
-        (None, None, None, None)
-    } else {
-        let parts: Vec<&str> = parts[1].split(',').collect();
-        let x1 = Some(try!(parts[0]
-            .parse::<usize>()
-            .map_err(|_| "random: invalid X1 value!")));
-        let x2 = if parts.len() < 2 {
-            None
-        } else {
-            Some(try!(parts[1]
-                .parse::<usize>()
-                .map_err(|_| "random: invalid X2 value!")))
+        let topology = match matches.opt_str("topology") {
+            Some(s) => Some(try!(parse_topology(&s))),
+            None => None,
         };
-        let y1 = if parts.len() < 3 {
-            None
-        } else {
-            Some(try!(parts[2]
-                .parse::<usize>()
-                .map_err(|_| "random: invalid Y1 value!")))
+        if topology.is_some() {
+            for &(ref ca_type, _) in &tiles {
+                match *ca_type {
+                    RuleSpec::Ant(..) => return Err("--topology is not supported for ant!"),
+                    _ if is_1d => return Err("--topology is supported only for 2D CA!"),
+                    _ if growing => return Err("--topology can't be used with --growing!"),
+                    // emulation of B0 without S8 needs all 8 neighbors of every cell
+                    RuleSpec::Life(ref survive, ref birth) if birth.contains(&0) &&
+                                                            !survive.contains(&8) &&
+                                                            topology.map_or(false, |t| {
+                        t.x == Boundary::Skip || t.y == Boundary::Skip
+                    }) => {
+                        return Err("Life with B0 and without S8 needs a topology without \
+                                    edges!")
+                    }
+                    _ => (),
+                }
+            }
+        }
+        let symmetry = match matches.opt_str("symmetry") {
+            Some(s) => try!(parse_symmetry(&s)),
+            None => Symmetry::default(),
         };
-        let y2 = if parts.len() < 4 {
-            None
-        } else {
-            Some(try!(parts[3]
-                .parse::<usize>()
-                .map_err(|_| "random: invalid Y2 value!")))
+        if symmetry != Symmetry::None && is_1d {
+            return Err("--symmetry is supported only for 2D CA!");
+        }
+        let shape = match matches.opt_str("shape") {
+            Some(s) => try!(parse_shape(&s)),
+            None => CellShape::Rect,
         };
-        (x1, x2, y1, y2)
-    };
-
-    match *ca_type {
-        CAType::Elementary(..) if y1.is_some() || y2.is_some() => {
-            return Err("random: Y1 and Y2 values are disabled for 1D CA!");
+        if shape != CellShape::Rect && is_1d {
+            return Err("--shape is supported only for 2D CA!");
+        }
+        let age_colors = matches.opt_present("age");
+        if age_colors {
+            for &(ref ca_type, _) in &tiles {
+                match *ca_type {
+                    RuleSpec::Ant(..) => return Err("--age is not supported for ant!"),
+                    RuleSpec::Coupled(..) => return Err("--age is not supported for coupled CA!"),
+                    _ if is_1d => return Err("--age is supported only for 2D CA!"),
+                    _ if growing => return Err("--age can't be used with --growing!"),
+                    _ => (),
+                }
+            }
+        }
+        let headless = matches.opt_present("headless");
+        let out = matches.opt_str("out");
+        if headless && (size.is_none() || out.is_none()) {
+            return Err("Specify --size and --out for headless mode!");
         }
-        _ => (),
+        let until_stable = matches.opt_present("until-stable");
+        if until_stable && !headless && gif.is_none() && !ppm_stream {
+            return Err("--until-stable requires --headless, --gif or --ppm-stream!");
+        }
+        if until_stable && is_1d {
+            return Err("--until-stable is supported only for 2D CA!");
+        }
+        let max_ticks = try!(match matches.opt_str("max-ticks") {
+            Some(s) => {
+                match s.parse::<u32>() {
+                    Ok(x) => Ok(Some(x)),
+                    Err(_) => Err("Max ticks count must be unsigned 32-bit integer!"),
+                }
+            }
+            None => Ok(None),
+        });
+        if max_ticks.is_some() && !until_stable {
+            return Err("--max-ticks requires --until-stable!");
+        }
+        let report_json = matches.opt_present("report-json");
+        if report_json && !headless {
+            return Err("--report-json requires --headless!");
+        }
+        let dump_rule = matches.opt_present("dump-rule");
+        if dump_rule && tiles.len() > 1 {
+            return Err("--dump-rule can't be used with several CA!");
+        }
+        let random_rule = matches.opt_present("random-rule");
+        for &(ref ca_type, _) in &tiles {
+            match *ca_type {
+                RuleSpec::CA1 { .. } |
+                RuleSpec::Elementary(..) => (),
+                _ if dump_rule => {
+                    return Err("--dump-rule is supported only for general and elementary 1D CA!")
+                }
+                _ if random_rule => {
+                    return Err("--random-rule is supported only for general and elementary 1D \
+                                CA!")
+                }
+                _ => (),
+            }
+        }
+        let (ca_type, init_type) = tiles.remove(0);
+        Ok(Config {
+            ca_type: ca_type,
+            init_type: init_type,
+            tiles: tiles,
+            size: size,
+            cell_size: cell_size,
+            delay: delay,
+            fps: fps,
+            seed: seed,
+            record: matches.opt_str("record"),
+            gif: gif,
+            frames: frames,
+            ppm_stream: ppm_stream,
+            history: history,
+            palette: palette,
+            bg: bg,
+            headless: headless,
+            update: update,
+            growing: growing,
+            scrollback: scrollback,
+            skip: skip,
+            topology: topology,
+            symmetry: symmetry,
+            age_colors: age_colors,
+            border: !matches.opt_present("no-border"),
+            shape: shape,
+            ticks: ticks,
+            out: out,
+            until_stable: until_stable,
+            max_ticks: max_ticks,
+            report_json: report_json,
+            dump_rule: dump_rule,
+            random_rule: random_rule,
+        })
     }
 
-    Ok(InitType::Random {
-        states: states,
-        x1: x1,
-        x2: x2,
-        y1: y1,
-        y2: y2,
-    })
+    // Most states of the shown CA.
+    pub fn states(&self) -> u32 {
+        self.tiles
+            .iter()
+            .map(|&(ref ca_type, _)| ca_type.states())
+            .fold(self.ca_type.states(), cmp::max)
+    }
 }
 
-fn is_rel_to_center_head(s: &str) -> (bool, bool) {
-    (s.starts_with("c+"), s.starts_with("c-"))
+// Spec errors carry the messages of the command line.
+fn spec_error(err: CaError) -> &'static str {
+    match err {
+        CaError::InvalidSpec(msg) => msg,
+        _ => "Invalid CA parameters!",
+    }
 }
 
-fn parse_point1d(s: &str) -> Result<Point1D, ()> {
-    if s == "c" {
-        return Ok(Point1D::RelToCenter(0));
+fn parse_async_order(option_val: Option<String>) -> Result<UpdateMode, &'static str> {
+    match option_val.as_ref().map(|s| s.as_str()) {
+        None | Some("random") => Ok(UpdateMode::AsyncRandom),
+        Some("sweep") => Ok(UpdateMode::AsyncSweep),
+        Some("shuffle") => Ok(UpdateMode::AsyncShuffle),
+        _ => Err("Async ORDER must be random, sweep or shuffle!"),
     }
-    let (cplus, cminus) = is_rel_to_center_head(s);
-    if cplus || cminus {
-        let shift = try!(s[2..].parse::<u16>().map_err(|_| ()));
-        Ok(Point1D::RelToCenter((shift as i32) *
-                                (if cplus {
-            1
-        } else {
-            -1
-        })))
-    } else {
-        let idx = try!(s.parse::<usize>().map_err(|_| ()));
-        Ok(Point1D::Abs(idx))
+}
+
+fn parse_shape(s: &str) -> Result<CellShape, &'static str> {
+    match s {
+        "rect" => Ok(CellShape::Rect),
+        "circle" => Ok(CellShape::Circle),
+        _ => Err("Shape must be rect or circle!"),
     }
 }
 
-fn parse_points1d(s: &str) -> Result<InitType, ()> {
-    let mut points: Vec<Point1D> = Vec::new();
-    for part in s.split(';') {
-        let p = try!(parse_point1d(part));
-        points.push(p);
+fn parse_symmetry(s: &str) -> Result<Symmetry, &'static str> {
+    match s {
+        "none" => Ok(Symmetry::None),
+        "horizontal" => Ok(Symmetry::Horizontal),
+        "vertical" => Ok(Symmetry::Vertical),
+        "both" => Ok(Symmetry::Both),
+        "rot4" => Ok(Symmetry::Rotational4),
+        _ => Err("Symmetry must be none, horizontal, vertical, both or rot4!"),
     }
-    Ok(InitType::Points1D(points))
 }
 
-fn parse_point2d(s: &str) -> Result<Point2D, ()> {
-    if s == "c" {
-        return Ok(Point2D::RelToCenter(0, 0));
+// Edges are walls (cells beyond them are skipped) on axes that don't wrap, mirror reflects
+// cells beyond every edge back into the world. helix is a torus with rows shifted by SHIFT
+// (default: 1) at the left and right edges.
+fn parse_topology(s: &str) -> Result<Topology, &'static str> {
+    if s == "helix" || s.starts_with("helix:") {
+        let shift = try!(s.splitn(2, ':')
+            .nth(1)
+            .map_or(Ok(1), |shift| shift.parse::<i64>())
+            .map_err(|_| "Helix shift must be 64-bit integer!"));
+        return Ok(Topology::from((Boundary::Twist { shift: shift }, Boundary::Wrap)));
     }
-    let (cplus, cminus) = is_rel_to_center_head(s);
-    if cplus || cminus {
-        let shifts: Vec<&str> = s[2..].split(',').collect();
-        if shifts.len() != 2 {
-            return Err(());
+    let (x, y) = match s {
+        "torus" => (Boundary::Wrap, Boundary::Wrap),
+        "cylinder-x" => (Boundary::Wrap, Boundary::Skip),
+        "cylinder-y" => (Boundary::Skip, Boundary::Wrap),
+        "plane" => (Boundary::Skip, Boundary::Skip),
+        "mirror" => (Boundary::Reflect, Boundary::Reflect),
+        _ => {
+            return Err("Topology must be torus, cylinder-x, cylinder-y, plane, mirror or \
+                        helix[:SHIFT]!")
         }
-        let xshift = try!(shifts[0].parse::<u16>().map_err(|_| ()));
-        let yshift = try!(shifts[1].parse::<u16>().map_err(|_| ()));
-        let sgn: i32 = if cplus {
-            1
-        } else {
-            -1
-        };
-        let xshift = (xshift as i32) * sgn;
-        let yshift = (yshift as i32) * sgn;
-        Ok(Point2D::RelToCenter(xshift, yshift))
-    } else {
-        let coords: Vec<&str> = s.split(',').collect();
-        if coords.len() != 2 {
-            return Err(());
-        }
-        let x = try!(coords[0].parse::<usize>().map_err(|_| ()));
-        let y = try!(coords[1].parse::<usize>().map_err(|_| ()));
-        Ok(Point2D::Abs(x, y))
-    }
+    };
+    Ok(Topology { x: x, y: y })
 }
 
-fn parse_points2d(s: &str) -> Result<InitType, ()> {
-    let mut points: Vec<Point2D> = Vec::new();
-    for part in s.split(';') {
-        let p = try!(parse_point2d(part));
-        points.push(p);
+// R,G,B
+fn parse_color(s: &str) -> Result<(u8, u8, u8), ()> {
+    let rgb: Vec<u8> = try!(s.split(',')
+        .map(|c| c.trim().parse::<u8>())
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|_| ()));
+    if rgb.len() != 3 {
+        return Err(());
     }
-    Ok(InitType::Points2D(points))
+    Ok((rgb[0], rgb[1], rgb[2]))
 }
 
-fn parse_init_points(s: &str, ca_type: &CAType) -> Result<InitType, &'static str> {
-    if s == "" {
-        return Err(ERR_NO_POINTS);
+// One R,G,B triple per line, empty lines and # comments are skipped.
+fn parse_palette(s: &str) -> Result<Vec<(u8, u8, u8)>, &'static str> {
+    let mut palette = Vec::new();
+    for line in ca::gen::content_lines(s) {
+        let color = try!(parse_color(line)
+            .map_err(|_| "Invalid palette color, must be R,G,B with values 0-255!"));
+        palette.push(color);
     }
-    (match *ca_type {
-            CAType::Elementary(..) => parse_points1d(s),
-            _ => parse_points2d(s),
-        })
-        .map_err(|_| ERR_INVALID_POINTS)
+    Ok(palette)
 }
 
-fn parse_init_type(option_value: Option<String>,
-                   ca_type: &CAType)
-                   -> Result<InitType, &'static str> {
-    static RANDOM_PREFIX: &'static str = "random:";
-    static POINTS_PREFIX: &'static str = "points:";
-    match option_value {
-        None => parse_init_type(Some(format!("{}uniform", RANDOM_PREFIX)), ca_type),
+fn parse_palette_file(path: &str) -> Result<Vec<(u8, u8, u8)>, &'static str> {
+    let mut s = String::new();
+    try!(File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut s))
+        .map_err(|_| "Failed to read palette file!"));
+    parse_palette(&s)
+}
+
+// CELL_WIDTH or CELL_WIDTHxCELL_HEIGHT
+fn parse_cell_size(option_val: Option<String>) -> Result<Option<(u8, u8)>, &'static str> {
+    static ERR_INVALID_CELL: &'static str = "Cell width and height must be unsigned 8-bit \
+                                             integers!";
+    match option_val {
         Some(s) => {
-            if s.starts_with(RANDOM_PREFIX) {
-                parse_init_random(&s[RANDOM_PREFIX.len()..], ca_type)
-            } else if s.starts_with("points:") {
-                parse_init_points(&s[POINTS_PREFIX.len()..], ca_type)
-            } else {
-                Err("Unknown initialization type!")
+            let (w, h) = match s.find('x') {
+                Some(xpos) => (&s[..xpos], &s[xpos + 1..]),
+                None => (&s[..], &s[..]),
+            };
+            let w = try!(w.parse::<u8>().map_err(|_| ERR_INVALID_CELL));
+            let h = try!(h.parse::<u8>().map_err(|_| ERR_INVALID_CELL));
+            if w == 0 || h == 0 {
+                return Err(ERR_INVALID_CELL);
             }
+            Ok(Some((w, h)))
         }
+        None => Ok(None),
     }
 }
 
@@ -0,0 +1,84 @@
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum CaError {
+    ZeroRadius,
+    InvalidStates,
+    InvalidBoundaryState,
+    ParamsTooBig,
+    CodeLengthMismatch,
+    InvalidCodeDigit(char, usize), // digit, base
+    InvalidTurn(char),
+    TooFewTurns,
+    InvalidCellState(usize), // states count
+    AntOutsideWorld,
+    WeightsLengthMismatch(usize), // neighborhood size
+    MissingDimensions,
+    MissingInit,
+    MissingRule,
+    NoStates,
+    EmptyGrid,
+    JaggedGrid(usize), // row
+    InvalidNeighborhood,
+    InvalidRulestring,
+    LayerSizeMismatch,
+    PreviousSizeMismatch,
+    InvalidSpec(&'static str), // message
+}
+
+impl fmt::Display for CaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CaError::ZeroRadius => write!(f, "radius < 1!"),
+            CaError::InvalidStates => write!(f, "states not in range 2-36!"),
+            CaError::InvalidBoundaryState => {
+                write!(f, "boundary state must be less than states!")
+            }
+            CaError::ParamsTooBig => write!(f, "states.pow(radius*2+1) must fit in usize!"),
+            CaError::CodeLengthMismatch => {
+                write!(f, "code must contain digit for every neighborhood!")
+            }
+            CaError::InvalidCodeDigit(c, base) => {
+                write!(f, "{} is not a digit in base {}!", c, base)
+            }
+            CaError::InvalidTurn(c) => {
+                write!(f, "Invalid turn '{}', must be one of R, L, N, U!", c)
+            }
+            CaError::TooFewTurns => write!(f, "Turns string must have at least 2 turns!"),
+            CaError::InvalidCellState(states) => {
+                write!(f, "Cell states must be less than {}!", states)
+            }
+            CaError::AntOutsideWorld => write!(f, "Ant is outside of the world!"),
+            CaError::WeightsLengthMismatch(size) => {
+                write!(f, "weights must contain weight for every of {} neighbors!", size)
+            }
+            CaError::MissingDimensions => write!(f, "dimensions are not set!"),
+            CaError::MissingInit => write!(f, "initial cells are not set!"),
+            CaError::MissingRule => write!(f, "rule is not set!"),
+            CaError::NoStates => write!(f, "list of states to choose from is empty!"),
+            CaError::EmptyGrid => write!(f, "grid has no cells!"),
+            CaError::JaggedGrid(row) => {
+                write!(f, "row {} of grid has different length than the first one!", row)
+            }
+            CaError::InvalidNeighborhood => {
+                write!(f, "neighborhood must be m, n or h followed by range, e.g. m1!")
+            }
+            CaError::InvalidRulestring => write!(f, "rulestring must look like B3/S23!"),
+            CaError::LayerSizeMismatch => write!(f, "layers must have the same size!"),
+            CaError::InvalidSpec(msg) => write!(f, "{}", msg),
+            CaError::PreviousSizeMismatch => {
+                write!(f, "previous generation must have as many cells as the current one!")
+            }
+        }
+    }
+}
+
+impl Error for CaError {}
+
+// Lets code with String errors use try! on CaError results.
+impl From<CaError> for String {
+    fn from(err: CaError) -> String {
+        err.to_string()
+    }
+}
@@ -0,0 +1,134 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+extern crate gif;
+extern crate png;
+
+// RGB pixels, 3 bytes per pixel, row by row.
+pub struct Frame {
+    pub w: u32,
+    pub h: u32,
+    pub pixels: Vec<u8>,
+}
+
+pub fn write_png(path: &PathBuf, frame: &Frame) -> Result<(), String> {
+    let file = try!(File::create(path)
+        .map_err(|e| format!("Failed to create {}: {}", path.display(), e)));
+    let mut encoder = png::Encoder::new(BufWriter::new(file), frame.w, frame.h);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = try!(encoder.write_header().map_err(|e| e.to_string()));
+    writer.write_image_data(&frame.pixels).map_err(|e| e.to_string())
+}
+
+// Writes frames as DIR/frame_00001.png, DIR/frame_00002.png, ...
+pub struct FrameRecorder {
+    dir: PathBuf,
+    frames: u32,
+}
+
+impl FrameRecorder {
+    pub fn new(dir: &str) -> Result<FrameRecorder, String> {
+        try!(fs::create_dir_all(dir).map_err(|e| format!("Failed to create {}: {}", dir, e)));
+        Ok(FrameRecorder {
+            dir: PathBuf::from(dir),
+            frames: 0,
+        })
+    }
+
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    pub fn record(&mut self, frame: &Frame) -> Result<(), String> {
+        self.frames += 1;
+        let path = self.dir.join(format!("frame_{:05}.png", self.frames));
+        write_png(&path, frame)
+    }
+}
+
+// Binary PPM frames written one after another, e.g. to stdout for ffmpeg -f image2pipe.
+pub struct PpmStream<W: Write> {
+    out: W,
+}
+
+impl<W: Write> PpmStream<W> {
+    pub fn new(out: W) -> PpmStream<W> {
+        PpmStream { out: out }
+    }
+
+    // Every frame is flushed, so the reader gets it at once.
+    pub fn record(&mut self, frame: &Frame) -> Result<(), String> {
+        try!(write!(self.out, "P6\n{} {}\n255\n", frame.w, frame.h).map_err(|e| e.to_string()));
+        try!(self.out.write_all(&frame.pixels).map_err(|e| e.to_string()));
+        self.out.flush().map_err(|e| e.to_string())
+    }
+}
+
+// Animated GIF with global palette made of palette colors, so every frame color must be
+// one of them.
+pub struct GifRecorder {
+    encoder: gif::Encoder<BufWriter<File>>,
+    color_idx: HashMap<(u8, u8, u8), u8>,
+    delay: u16,
+}
+
+impl GifRecorder {
+    pub fn new(path: &str,
+               w: u32,
+               h: u32,
+               palette: &Vec<(u8, u8, u8)>,
+               delay_ms: u32)
+               -> Result<GifRecorder, String> {
+        if palette.len() > 256 {
+            return Err(String::from("GIF palette can't have more than 256 colors!"));
+        }
+        if w > 65535 || h > 65535 {
+            return Err(String::from("GIF size can't exceed 65535x65535!"));
+        }
+        let mut color_idx = HashMap::new();
+        let mut global_palette: Vec<u8> = Vec::with_capacity(palette.len() * 3);
+        for (i, &(r, g, b)) in palette.iter().enumerate() {
+            color_idx.entry((r, g, b)).or_insert(i as u8);
+            global_palette.push(r);
+            global_palette.push(g);
+            global_palette.push(b);
+        }
+        let file = try!(File::create(path)
+            .map_err(|e| format!("Failed to create {}: {}", path, e)));
+        let mut encoder =
+            try!(gif::Encoder::new(BufWriter::new(file), w as u16, h as u16, &global_palette)
+                .map_err(|e| e.to_string()));
+        try!(encoder.set_repeat(gif::Repeat::Infinite).map_err(|e| e.to_string()));
+        // GIF delay is measured in hundredths of a second
+        let delay = (delay_ms / 10) as u16;
+        Ok(GifRecorder {
+            encoder: encoder,
+            color_idx: color_idx,
+            delay: if delay == 0 {
+                1
+            } else {
+                delay
+            },
+        })
+    }
+
+    pub fn record(&mut self, frame: &Frame) -> Result<(), String> {
+        let mut indexes: Vec<u8> = Vec::with_capacity(frame.pixels.len() / 3);
+        for rgb in frame.pixels.chunks(3) {
+            let idx = try!(self.color_idx
+                .get(&(rgb[0], rgb[1], rgb[2]))
+                .ok_or("Frame color is missing from GIF palette!"));
+            indexes.push(*idx);
+        }
+        let mut gif_frame = gif::Frame::default();
+        gif_frame.width = frame.w as u16;
+        gif_frame.height = frame.h as u16;
+        gif_frame.delay = self.delay;
+        gif_frame.buffer = Cow::Owned(indexes);
+        self.encoder.write_frame(&gif_frame).map_err(|e| e.to_string())
+    }
+}
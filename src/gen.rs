@@ -1,11 +1,50 @@
+use std::cmp;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
 extern crate rand;
-use rand::Rng;
+use rand::{Rng, SeedableRng, StdRng};
+
+use image::{self, GrayImage};
+use image::imageops::{self, FilterType};
 
 use types::Cell;
 
+pub fn seeded_rng(seed: u64) -> StdRng {
+    let seed: &[usize] = &[seed as usize, (seed >> 32) as usize];
+    SeedableRng::from_seed(seed)
+}
+
+// Seed for seeded_rng() picked at random, so a random run can be reported and repeated.
+pub fn random_seed() -> u64 {
+    rand::thread_rng().gen()
+}
+
 pub fn random1d(w: usize, states: Vec<Cell>, i1: Option<usize>, i2: Option<usize>) -> Vec<Cell> {
-    let mut rng = rand::thread_rng();
+    random1d_with_rng(&mut rand::thread_rng(), w, states, i1, i2)
+}
+
+pub fn random1d_seeded(seed: u64,
+                       w: usize,
+                       states: Vec<Cell>,
+                       i1: Option<usize>,
+                       i2: Option<usize>)
+                       -> Vec<Cell> {
+    random1d_with_rng(&mut seeded_rng(seed), w, states, i1, i2)
+}
+
+pub fn random1d_with_rng<R: Rng>(rng: &mut R,
+                                 w: usize,
+                                 states: Vec<Cell>,
+                                 i1: Option<usize>,
+                                 i2: Option<usize>)
+                                 -> Vec<Cell> {
     let mut cells: Vec<Cell> = vec![0; w];
+    // nothing to choose from, cells are left 0
+    if states.is_empty() {
+        return cells;
+    }
     let min_idx = match i1 {
         None => 0,
         Some(i) => i,
@@ -34,8 +73,35 @@ pub fn random2d(w: usize,
                 y1: Option<usize>,
                 y2: Option<usize>)
                 -> Vec<Vec<Cell>> {
-    let mut rng = rand::thread_rng();
+    random2d_with_rng(&mut rand::thread_rng(), w, h, states, x1, x2, y1, y2)
+}
+
+pub fn random2d_seeded(seed: u64,
+                       w: usize,
+                       h: usize,
+                       states: Vec<Cell>,
+                       x1: Option<usize>,
+                       x2: Option<usize>,
+                       y1: Option<usize>,
+                       y2: Option<usize>)
+                       -> Vec<Vec<Cell>> {
+    random2d_with_rng(&mut seeded_rng(seed), w, h, states, x1, x2, y1, y2)
+}
+
+pub fn random2d_with_rng<R: Rng>(rng: &mut R,
+                                 w: usize,
+                                 h: usize,
+                                 states: Vec<Cell>,
+                                 x1: Option<usize>,
+                                 x2: Option<usize>,
+                                 y1: Option<usize>,
+                                 y2: Option<usize>)
+                                 -> Vec<Vec<Cell>> {
     let mut cells: Vec<Vec<Cell>> = vec![vec![0; w]; h];
+    // nothing to choose from, cells are left 0
+    if states.is_empty() {
+        return cells;
+    }
     let min_x = match x1 {
         None => 0,
         Some(x) => x,
@@ -72,19 +138,646 @@ pub fn random2d(w: usize,
     cells
 }
 
+// Every cell is 1 with probability density, 0 otherwise.
+pub fn random2d_density<R: Rng>(w: usize, h: usize, density: f64, rng: &mut R) -> Vec<Vec<Cell>> {
+    (0..h)
+        .map(|_| {
+            (0..w)
+                .map(|_| if rng.gen::<f64>() < density { 1 } else { 0 })
+                .collect()
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Symmetry {
+    None,
+    // left half mirrored to the right one
+    Horizontal,
+    // top half mirrored to the bottom one
+    Vertical,
+    Both,
+    // rotations by 90 degrees about the center, only the centered square of side min(w, h)
+    // can have this symmetry, so cells outside of it are set to 0
+    Rotational4,
+}
+
+impl Default for Symmetry {
+    fn default() -> Symmetry {
+        Symmetry::None
+    }
+}
+
+// Makes cells symmetric about the grid center: every cell gets the state of the first (in row
+// by row order) cell of its orbit, so random soup keeps its top-left part.
+pub fn symmetrize(cells: &mut Vec<Vec<Cell>>, symmetry: Symmetry) {
+    let h = cells.len();
+    if h == 0 {
+        return;
+    }
+    let w = cells[0].len();
+    let (n, row0, col0) = (cmp::min(w, h), (h - cmp::min(w, h)) / 2, (w - cmp::min(w, h)) / 2);
+    for row in 0..h {
+        for col in 0..w {
+            let (mirror_row, mirror_col) = (h - 1 - row, w - 1 - col);
+            let orbit = match symmetry {
+                Symmetry::None => vec![(row, col)],
+                Symmetry::Horizontal => vec![(row, col), (row, mirror_col)],
+                Symmetry::Vertical => vec![(row, col), (mirror_row, col)],
+                Symmetry::Both => {
+                    vec![(row, col), (row, mirror_col), (mirror_row, col),
+                         (mirror_row, mirror_col)]
+                }
+                Symmetry::Rotational4 => {
+                    if row < row0 || row >= row0 + n || col < col0 || col >= col0 + n {
+                        cells[row][col] = 0;
+                        continue;
+                    }
+                    // coordinates inside of the square
+                    let (r, c) = (row - row0, col - col0);
+                    vec![(r, c), (c, n - 1 - r), (n - 1 - r, n - 1 - c), (n - 1 - c, r)]
+                        .into_iter()
+                        .map(|(r, c)| (r + row0, c + col0))
+                        .collect()
+                }
+            };
+            let (src_row, src_col) = orbit.into_iter().min().unwrap();
+            cells[row][col] = cells[src_row][src_col];
+        }
+    }
+}
+
 pub fn points1d(w: usize, indexes: Vec<usize>) -> Vec<Cell> {
+    points1d_with_state(w, indexes.into_iter().map(|i| (i, 1)).collect())
+}
+
+// (index, state) pairs
+pub fn points1d_with_state(w: usize, points: Vec<(usize, Cell)>) -> Vec<Cell> {
     let mut cells: Vec<Cell> = vec![0; w];
-    for i in indexes {
-        cells[i] = 1;
+    for (i, state) in points {
+        cells[i] = state;
     }
     cells
 }
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Align {
+    Center,
+    Right,
+}
+
+// Row of width w containing given states aligned to its center or right edge, states that
+// don't fit are dropped from both sides or from the left.
+pub fn aligned1d(w: usize, states: &[Cell], align: Align) -> Vec<Cell> {
+    let len = states.len() as i64;
+    let left = match align {
+        Align::Center => (w as i64 - len) / 2,
+        Align::Right => w as i64 - len,
+    };
+    let points = states.iter()
+        .enumerate()
+        .map(|(i, &state)| (left + i as i64, state))
+        .filter(|&(i, _)| i >= 0 && i < (w as i64))
+        .map(|(i, state)| (i as usize, state))
+        .collect();
+    points1d_with_state(w, points)
+}
+
 pub fn points2d(w: usize, h: usize, coords: Vec<(usize, usize)>) -> Vec<Vec<Cell>> {
+    points2d_with_state(w, h, coords.into_iter().map(|coord| (coord, 1)).collect())
+}
+
+// ((x, y), state) pairs
+pub fn points2d_with_state(w: usize,
+                           h: usize,
+                           points: Vec<((usize, usize), Cell)>)
+                           -> Vec<Vec<Cell>> {
+    let mut cells: Vec<Vec<Cell>> = vec![vec![0; w]; h];
+    for ((x, y), state) in points {
+        cells[y][x] = state;
+    }
+    cells
+}
+
+// Grid of width w and height h with cells at coords set to 1, coords are (row, col) unlike
+// points2d().
+pub fn area_with_points(w: usize, h: usize, coords: Vec<(usize, usize)>) -> Vec<Vec<Cell>> {
+    let mut cells: Vec<Vec<Cell>> = vec![vec![0; w]; h];
+    for (row, col) in coords {
+        cells[row][col] = 1;
+    }
+    cells
+}
+
+fn read_file<P: AsRef<Path>>(path: P) -> Result<String, String> {
+    let mut file = try!(File::open(path).map_err(|e| e.to_string()));
+    let mut s = String::new();
+    try!(file.read_to_string(&mut s).map_err(|e| e.to_string()));
+    Ok(s)
+}
+
+// Copies pattern into cells with its top left cell at (row, col), pattern cells falling
+// outside of the world are dropped.
+pub fn stamp(cells: &mut Vec<Vec<Cell>>, pattern: &Vec<Vec<Cell>>, row: usize, col: usize) {
+    for (prow, pattern_row) in pattern.iter().enumerate() {
+        if let Some(cells_row) = cells.get_mut(row + prow) {
+            for (pcol, &cell) in pattern_row.iter().enumerate() {
+                if let Some(c) = cells_row.get_mut(col + pcol) {
+                    *c = cell;
+                }
+            }
+        }
+    }
+}
+
+// Places pattern into w x h world so that center of pattern is at offset (x, y).
+// Cells falling outside of the world are dropped with a warning.
+pub fn place_pattern(pattern: &Vec<Vec<Cell>>,
+                     w: usize,
+                     h: usize,
+                     offset: (usize, usize))
+                     -> Vec<Vec<Cell>> {
     let mut cells: Vec<Vec<Cell>> = vec![vec![0; w]; h];
-    for coord in coords {
-        let (x, y) = coord;
-        cells[y][x] = 1;
+    let ph = pattern.len() as i64;
+    let pw = pattern.iter().map(|row| row.len()).max().unwrap_or(0) as i64;
+    let top = (offset.1 as i64) - ph / 2;
+    let left = (offset.0 as i64) - pw / 2;
+    let mut clipped = false;
+    for (prow, pattern_row) in pattern.iter().enumerate() {
+        for (pcol, &cell) in pattern_row.iter().enumerate() {
+            let row = top + prow as i64;
+            let col = left + pcol as i64;
+            if row < 0 || col < 0 || row >= (h as i64) || col >= (w as i64) {
+                clipped = clipped || cell != 0;
+                continue;
+            }
+            cells[row as usize][col as usize] = cell;
+        }
+    }
+    if clipped {
+        let _ = writeln!(io::stderr(), "Warning: pattern does not fit into world, clipped.");
     }
     cells
 }
+
+// Trimmed non-blank lines of text file with comments starting with '#' cut off, e.g. of a
+// palette or RLE file.
+pub fn content_lines<'a>(s: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+    s.lines()
+        .map(|line| line.split('#').next().unwrap().trim())
+        .filter(|line| !line.is_empty())
+}
+
+pub fn parse_rle(s: &str) -> Result<Vec<Vec<Cell>>, String> {
+    static ERR_INVALID_HEADER: &'static str = "rle: invalid header!";
+    static ERR_TOO_BIG: &'static str = "rle: pattern is bigger than 65536x65536 cells!";
+    // keeps a broken or malicious file from allocating all memory
+    const MAX_SIDE: usize = 65536;
+
+    let mut lines = content_lines(s);
+    let header = try!(lines.next().ok_or("rle: missing header!"));
+    let mut pw: usize = 0;
+    let mut ph: usize = 0;
+    for part in header.split(',') {
+        let kv: Vec<&str> = part.split('=').map(|x| x.trim()).collect();
+        if kv.len() != 2 {
+            return Err(String::from(ERR_INVALID_HEADER));
+        }
+        match kv[0] {
+            "x" => pw = try!(kv[1].parse::<usize>().map_err(|_| ERR_INVALID_HEADER)),
+            "y" => ph = try!(kv[1].parse::<usize>().map_err(|_| ERR_INVALID_HEADER)),
+            _ => (),
+        }
+    }
+    if pw > MAX_SIDE || ph > MAX_SIDE {
+        return Err(String::from(ERR_TOO_BIG));
+    }
+
+    let mut pattern: Vec<Vec<Cell>> = vec![Vec::new()];
+    let mut count: usize = 0;
+    // first letter of two-letter state tag: pA-pX are states 25-48, qA-qX are 49-72, etc.
+    let mut prefix: Option<u32> = None;
+    'body: for line in lines {
+        for c in line.chars() {
+            if c.is_whitespace() {
+                continue;
+            }
+            if let Some(d) = c.to_digit(10) {
+                count = try!(count.checked_mul(10)
+                    .and_then(|count| count.checked_add(d as usize))
+                    .ok_or(ERR_TOO_BIG));
+                continue;
+            }
+            if prefix.is_none() && c >= 'p' && c <= 'y' {
+                prefix = Some((c as u32) - ('p' as u32) + 1);
+                continue;
+            }
+            let n = if count == 0 {
+                1
+            } else {
+                count
+            };
+            count = 0;
+            let is_multistate = c >= 'A' && c <= 'X';
+            if prefix.is_some() && !is_multistate {
+                return Err(format!("rle: unexpected '{}' in state tag!", c));
+            }
+            let state = match c {
+                '!' => break 'body,
+                '$' => {
+                    if pattern.len() + n > MAX_SIDE {
+                        return Err(String::from(ERR_TOO_BIG));
+                    }
+                    for _ in 0..n {
+                        pattern.push(Vec::new());
+                    }
+                    continue;
+                }
+                'b' | '.' => 0,
+                _ if is_multistate => {
+                    let state = prefix.unwrap_or(0) * 24 + (c as u32) - ('A' as u32) + 1;
+                    if state > Cell::max_value() as u32 {
+                        return Err(format!("rle: state {} doesn't fit into cell!", state));
+                    }
+                    state as Cell
+                }
+                c if c.is_alphabetic() => 1,
+                c => return Err(format!("rle: unexpected '{}'!", c)),
+            };
+            prefix = None;
+            let row = pattern.last_mut().unwrap();
+            if row.len() + n > MAX_SIDE {
+                return Err(String::from(ERR_TOO_BIG));
+            }
+            for _ in 0..n {
+                row.push(state);
+            }
+        }
+    }
+
+    let pw = pattern.iter().map(|row| row.len()).fold(pw, |a, b| if a > b { a } else { b });
+    while pattern.len() < ph {
+        pattern.push(Vec::new());
+    }
+    for row in pattern.iter_mut() {
+        row.resize(pw, 0);
+    }
+    Ok(pattern)
+}
+
+// Tag of the state in RLE body, multistate tags cover states up to 264.
+fn rle_tag(state: Cell, multistate: bool) -> String {
+    match (state, multistate) {
+        (0, false) => String::from("b"),
+        (_, false) => String::from("o"),
+        (0, true) => String::from("."),
+        (_, true) => {
+            let prefix = (state - 1) / 24;
+            let letter = (('A' as Cell) + (state - 1) % 24) as u8 as char;
+            if prefix == 0 {
+                letter.to_string()
+            } else {
+                format!("{}{}", (('p' as Cell) + prefix - 1) as u8 as char, letter)
+            }
+        }
+    }
+}
+
+// Encodes bounding box of non-zero cells of w x h grid in RLE format. Grids with states
+// above 1 use multistate tags (., A, B, ..., pA, ...), states above 264 have no tags.
+pub fn to_rle(w: usize, h: usize, cells: &[Cell]) -> Result<String, String> {
+    if let Some(&state) = cells.iter().find(|&&cell| cell as u32 > 264) {
+        return Err(format!("rle: state {} is above 264, the last one RLE can encode!", state));
+    }
+    let is_live = |row: usize, col: usize| cells[row * w + col] != 0;
+    let rows: Vec<usize> = (0..h).filter(|&row| (0..w).any(|col| is_live(row, col))).collect();
+    let cols: Vec<usize> = (0..w).filter(|&col| (0..h).any(|row| is_live(row, col))).collect();
+    if rows.is_empty() {
+        return Ok(String::from("x = 0, y = 0\n!\n"));
+    }
+    let (row1, row2) = (rows[0], rows[rows.len() - 1]);
+    let (col1, col2) = (cols[0], cols[cols.len() - 1]);
+    let multistate = cells.iter().any(|&cell| cell > 1);
+
+    let mut tokens: Vec<String> = Vec::new();
+    let mut pending_rows = 0;
+    let run = |n: usize, tag: String| if n == 1 { tag } else { format!("{}{}", n, tag) };
+    for row in row1..(row2 + 1) {
+        // trailing dead cells are omitted
+        let last = match (col1..(col2 + 1)).rev().find(|&col| is_live(row, col)) {
+            Some(col) => col,
+            None => {
+                pending_rows += 1;
+                continue;
+            }
+        };
+        if pending_rows > 0 {
+            tokens.push(run(pending_rows, String::from("$")));
+        }
+        pending_rows = 1;
+        let mut col = col1;
+        while col <= last {
+            let state = cells[row * w + col];
+            let mut n = 1;
+            while col + n <= last && cells[row * w + col + n] == state {
+                n += 1;
+            }
+            tokens.push(run(n, rle_tag(state, multistate)));
+            col += n;
+        }
+    }
+    tokens.push(String::from("!"));
+
+    let mut rle = format!("x = {}, y = {}\n", col2 - col1 + 1, row2 - row1 + 1);
+    let mut line_len = 0;
+    for token in tokens {
+        // lines of RLE files should not exceed 70 characters
+        if line_len + token.len() > 70 {
+            rle.push('\n');
+            line_len = 0;
+        }
+        line_len += token.len();
+        rle.push_str(&token);
+    }
+    rle.push('\n');
+    Ok(rle)
+}
+
+pub fn from_rle<P: AsRef<Path>>(path: P,
+                                w: usize,
+                                h: usize,
+                                offset: (usize, usize))
+                                -> Result<Vec<Vec<Cell>>, String> {
+    let s = try!(read_file(path));
+    let pattern = try!(parse_rle(&s));
+    Ok(place_pattern(&pattern, w, h, offset))
+}
+
+pub fn parse_plaintext(s: &str) -> Result<Vec<Vec<Cell>>, String> {
+    let mut pattern: Vec<Vec<Cell>> = Vec::new();
+    // blank lines are rows of dead cells, so only comment lines are skipped
+    for line in s.lines().filter(|line| !line.starts_with('!') && !line.starts_with('#')) {
+        let mut row: Vec<Cell> = Vec::new();
+        for c in line.trim_end().chars() {
+            match c {
+                '.' => row.push(0),
+                'O' => row.push(1),
+                c => return Err(format!("cells: unexpected '{}'!", c)),
+            }
+        }
+        pattern.push(row);
+    }
+    while pattern.last().map_or(false, |row| row.is_empty()) {
+        pattern.pop();
+    }
+    let pw = pattern.iter().map(|row| row.len()).max().unwrap_or(0);
+    for row in pattern.iter_mut() {
+        row.resize(pw, 0);
+    }
+    Ok(pattern)
+}
+
+pub fn from_plaintext<P: AsRef<Path>>(path: P,
+                                      w: usize,
+                                      h: usize,
+                                      offset: (usize, usize))
+                                      -> Result<Vec<Vec<Cell>>, String> {
+    let s = try!(read_file(path));
+    let pattern = try!(parse_plaintext(&s));
+    Ok(place_pattern(&pattern, w, h, offset))
+}
+
+// Rows of comma-separated states, one row per line, e.g. exported from a spreadsheet. All rows
+// must have the same length, blank lines and comments are skipped like by content_lines.
+pub fn parse_csv(s: &str) -> Result<Vec<Vec<Cell>>, String> {
+    let mut grid: Vec<Vec<Cell>> = Vec::new();
+    for line in content_lines(s) {
+        let row = try!(line.split(',')
+            .map(|part| part.trim().parse::<Cell>())
+            .collect::<Result<Vec<Cell>, _>>()
+            .map_err(|_| format!("csv: invalid state in row {}!", grid.len() + 1)));
+        if grid.first().map_or(false, |first| first.len() != row.len()) {
+            return Err(format!("csv: row {} has {} cells, but the first one has {}!",
+                               grid.len() + 1,
+                               row.len(),
+                               grid[0].len()));
+        }
+        grid.push(row);
+    }
+    if grid.is_empty() {
+        return Err(String::from("csv: file has no rows!"));
+    }
+    Ok(grid)
+}
+
+pub fn from_csv<P: AsRef<Path>>(path: P) -> Result<Vec<Vec<Cell>>, String> {
+    let s = try!(read_file(path));
+    parse_csv(&s)
+}
+
+// Image is scaled to fit the w x h world keeping its aspect ratio and centered in it, the rest
+// of the world is in state 0. Pixel brightness is split into states equal levels, black is 0.
+pub fn from_image<P: AsRef<Path>>(path: P,
+                                  w: usize,
+                                  h: usize,
+                                  states: u32)
+                                  -> Result<Vec<Vec<Cell>>, String> {
+    let img = try!(image::open(path).map_err(|e| e.to_string()));
+    Ok(quantize_image(&img.to_luma8(), w, h, states))
+}
+
+pub fn quantize_image(img: &GrayImage, w: usize, h: usize, states: u32) -> Vec<Vec<Cell>> {
+    let mut cells = vec![vec![0; w]; h];
+    let (img_w, img_h) = (img.width() as usize, img.height() as usize);
+    if img_w == 0 || img_h == 0 || w == 0 || h == 0 {
+        return cells;
+    }
+    let (fit_w, fit_h) = if img_w * h > img_h * w {
+        (w, cmp::max(1, img_h * w / img_w))
+    } else {
+        (cmp::max(1, img_w * h / img_h), h)
+    };
+    let resized = imageops::resize(img, fit_w as u32, fit_h as u32, FilterType::Triangle);
+    let (col0, row0) = ((w - fit_w) / 2, (h - fit_h) / 2);
+    for (x, y, pixel) in resized.enumerate_pixels() {
+        let level = (pixel[0] as u32) * states / 256;
+        cells[row0 + y as usize][col0 + x as usize] = level as Cell;
+    }
+    cells
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_points_with_state() {
+        assert_eq!(points1d_with_state(4, vec![(0, 2), (3, 1)]), vec![2, 0, 0, 1]);
+        let cells = points2d_with_state(3, 2, vec![((2, 0), 5), ((0, 1), 3)]);
+        assert_eq!(cells, vec![vec![0, 0, 5], vec![3, 0, 0]]);
+    }
+
+    #[test]
+    fn test_area_with_points() {
+        let cells = area_with_points(3, 3, vec![(0, 0), (1, 1), (2, 2)]);
+        assert_eq!(cells, vec![vec![1, 0, 0], vec![0, 1, 0], vec![0, 0, 1]]);
+        let cells = area_with_points(3, 2, vec![(1, 2)]);
+        assert_eq!(cells, vec![vec![0, 0, 0], vec![0, 0, 1]]);
+    }
+
+    #[test]
+    fn test_quantize_image() {
+        let img = GrayImage::from_raw(2, 1, vec![0, 255]).unwrap();
+        let cells = quantize_image(&img, 4, 4, 2);
+        assert_eq!(cells,
+                   vec![vec![0, 0, 0, 0], vec![0, 0, 1, 1], vec![0, 0, 1, 1], vec![0, 0, 0, 0]]);
+        let cells = quantize_image(&img, 2, 1, 4);
+        assert_eq!(cells, vec![vec![0, 3]]);
+    }
+
+    #[test]
+    fn test_symmetrize() {
+        let random = vec![vec![1, 2, 3], vec![4, 5, 6]];
+        let mut cells = random.clone();
+        symmetrize(&mut cells, Symmetry::Horizontal);
+        assert_eq!(cells, vec![vec![1, 2, 1], vec![4, 5, 4]]);
+        let mut cells = random.clone();
+        symmetrize(&mut cells, Symmetry::Vertical);
+        assert_eq!(cells, vec![vec![1, 2, 3], vec![1, 2, 3]]);
+        let mut cells = random.clone();
+        symmetrize(&mut cells, Symmetry::Both);
+        assert_eq!(cells, vec![vec![1, 2, 1], vec![1, 2, 1]]);
+
+        let mut cells = vec![vec![1, 2, 3, 4], vec![5, 6, 7, 8], vec![9, 10, 11, 12]];
+        symmetrize(&mut cells, Symmetry::Rotational4);
+        assert_eq!(cells, vec![vec![1, 2, 1, 0], vec![2, 6, 2, 0], vec![1, 2, 1, 0]]);
+    }
+
+    #[test]
+    fn test_aligned1d() {
+        assert_eq!(aligned1d(7, &[1, 0, 2], Align::Center), vec![0, 0, 1, 0, 2, 0, 0]);
+        assert_eq!(aligned1d(6, &[1, 0, 2], Align::Right), vec![0, 0, 0, 1, 0, 2]);
+        assert_eq!(aligned1d(2, &[1, 2, 3, 4], Align::Center), vec![2, 3]);
+        assert_eq!(aligned1d(2, &[1, 2, 3], Align::Right), vec![2, 3]);
+    }
+
+    #[test]
+    fn test_stamp() {
+        let mut cells = vec![vec![0; 4]; 3];
+        let pattern = vec![vec![1, 2], vec![3, 4]];
+        stamp(&mut cells, &pattern, 1, 1);
+        stamp(&mut cells, &pattern, 2, 3);
+        assert_eq!(cells, vec![vec![0, 0, 0, 0], vec![0, 1, 2, 0], vec![0, 3, 4, 1]]);
+    }
+
+    #[test]
+    fn test_parse_rle() {
+        let glider = "#N Glider\nx = 3, y = 3, rule = B3/S23\nbob$2bo$3o!";
+        assert_eq!(parse_rle(glider).unwrap(),
+                   vec![vec![0, 1, 0], vec![0, 0, 1], vec![1, 1, 1]]);
+        let blank_rows = "x = 2, y = 4\no3$bo!";
+        assert_eq!(parse_rle(blank_rows).unwrap(),
+                   vec![vec![1, 0], vec![0, 0], vec![0, 0], vec![0, 1]]);
+        assert!(parse_rle("x = 1, y = 1\no?!").is_err());
+        let multistate = "x = 4, y = 2, rule = WireWorld\n.A2C$pAqX!";
+        assert_eq!(parse_rle(multistate).unwrap(),
+                   vec![vec![0, 1, 3, 3], vec![25, 72, 0, 0]]);
+        for rle in &["x = 1, y = 99999999999\no!",
+                     "x = 1, y = 1\n99999999999999999999999o!",
+                     "x = 1, y = 1\n70000o!",
+                     "x = 1, y = 1\no70000$o!"] {
+            assert!(parse_rle(rle).is_err());
+        }
+    }
+
+    #[test]
+    fn test_to_rle() {
+        let glider = vec![vec![0, 0, 0, 0], vec![0, 0, 1, 0], vec![0, 0, 0, 1], vec![0, 1, 1, 1]];
+        assert_eq!(to_rle(4, 4, &glider.concat()).unwrap(), "x = 3, y = 3\nbo$2bo$3o!\n");
+        let blank_rows = vec![vec![1, 0], vec![0, 0], vec![0, 0], vec![0, 1]];
+        let rle = to_rle(2, 4, &blank_rows.concat()).unwrap();
+        assert_eq!(rle, "x = 2, y = 4\no3$bo!\n");
+        assert_eq!(parse_rle(&rle).unwrap(), blank_rows);
+        let multistate = vec![vec![0, 1, 3, 3], vec![25, 72, 0, 0]];
+        let rle = to_rle(4, 2, &multistate.concat()).unwrap();
+        assert_eq!(rle, "x = 4, y = 2\n.A2C$pAqX!\n");
+        assert_eq!(parse_rle(&rle).unwrap(), multistate);
+        let long_row = vec![1, 0].repeat(40);
+        assert!(to_rle(80, 1, &long_row).unwrap().lines().all(|line| line.len() <= 70));
+        assert_eq!(to_rle(2, 2, &[0, 0, 0, 0]).unwrap(), "x = 0, y = 0\n!\n");
+        // 264 is the last state with a tag, if cells can hold it
+        let last = cmp::min(264, Cell::max_value() as u32);
+        let cells = vec![last as Cell];
+        assert_eq!(parse_rle(&to_rle(1, 1, &cells).unwrap()).unwrap(), vec![cells]);
+        if last == 264 {
+            assert!(to_rle(1, 1, &[(last + 1) as Cell]).is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse_plaintext() {
+        let glider = "!Name: Glider\n.O\n..O\nOOO\n";
+        assert_eq!(parse_plaintext(glider).unwrap(),
+                   vec![vec![0, 1, 0], vec![0, 0, 1], vec![1, 1, 1]]);
+        assert_eq!(parse_plaintext("O\n\n.O").unwrap(),
+                   vec![vec![1, 0], vec![0, 0], vec![0, 1]]);
+        assert!(parse_plaintext("O*").is_err());
+        let commented = "# blinker\n!Name: Blinker\nOOO  \n";
+        assert_eq!(parse_plaintext(commented).unwrap(), vec![vec![1, 1, 1]]);
+    }
+
+    #[test]
+    fn test_content_lines() {
+        let palette = "# dead\n0,0,0\n\n  255,0,0  # alive\n\t\n#\n";
+        assert_eq!(content_lines(palette).collect::<Vec<_>>(), vec!["0,0,0", "255,0,0"]);
+        let rle = "#C comment\n\nx = 2, y = 1\n  \n# another one\n2o!\n";
+        assert_eq!(parse_rle(rle).unwrap(), vec![vec![1, 1]]);
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let csv = "# wire\n0, 3,3\n1,2,0\n\n";
+        assert_eq!(parse_csv(csv).unwrap(), vec![vec![0, 3, 3], vec![1, 2, 0]]);
+        assert!(parse_csv("0,1\n1").is_err());
+        assert!(parse_csv("0,,1").is_err());
+        assert!(parse_csv("# nothing\n").is_err());
+    }
+
+    #[test]
+    fn test_place_pattern() {
+        let pattern = vec![vec![1, 1], vec![0, 1]];
+        assert_eq!(place_pattern(&pattern, 4, 3, (2, 1)),
+                   vec![vec![0, 1, 1, 0], vec![0, 0, 1, 0], vec![0, 0, 0, 0]]);
+        assert_eq!(place_pattern(&pattern, 2, 2, (0, 0)),
+                   vec![vec![1, 0], vec![0, 0]]);
+    }
+
+    #[test]
+    fn test_random_seeded() {
+        let states = vec![0, 1, 2, 3];
+        assert_eq!(random1d_seeded(42, 100, states.clone(), None, None),
+                   random1d_seeded(42, 100, states.clone(), None, None));
+        assert_eq!(random2d_seeded(42, 30, 20, states.clone(), None, None, None, None),
+                   random2d_seeded(42, 30, 20, states.clone(), None, None, None, None));
+        assert!(random2d_seeded(1, 30, 20, states.clone(), None, None, None, None) !=
+                random2d_seeded(2, 30, 20, states, None, None, None, None));
+    }
+
+    #[test]
+    fn test_random_no_states() {
+        assert_eq!(random1d(5, Vec::new(), None, None), vec![0; 5]);
+        assert_eq!(random2d(3, 2, Vec::new(), None, None, None, None), vec![vec![0; 3]; 2]);
+    }
+
+    #[test]
+    fn test_random2d_density() {
+        let mut rng = seeded_rng(42);
+        let alive = |cells: Vec<Vec<Cell>>| -> usize {
+            cells.iter().map(|row| row.iter().filter(|&&c| c == 1).count()).sum()
+        };
+        assert_eq!(alive(random2d_density(10, 10, 0.0, &mut rng)), 0);
+        assert_eq!(alive(random2d_density(10, 10, 1.0, &mut rng)), 100);
+        let density = alive(random2d_density(200, 100, 0.3, &mut rng)) as f64 / 20000.0;
+        assert!(density > 0.28 && density < 0.32);
+    }
+}
@@ -1,16 +1,54 @@
 use std::char;
+use std::cmp;
+use std::collections::{HashMap, VecDeque};
+use std::mem;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
 
+extern crate flate2;
+extern crate image;
 extern crate rand;
+use rand::{Rng, StdRng};
 use rand::distributions::{Range, IndependentSample};
 
+#[cfg(feature = "parallel")]
+extern crate rayon;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+pub mod error;
 pub mod gen;
 pub mod nb;
+pub mod patterns;
+pub mod snapshot;
+pub mod spec;
 pub mod types;
 
 use types::Cell;
+use nb::{Boundary, Topology};
+use error::CaError;
 
 // (cells, width, index) -> new_state
-pub type CA1Rule = Fn(&Vec<Cell>, usize, usize) -> Cell;
+// Rule may keep state between calls, it's called for cells in order of their indexes.
+#[cfg(not(feature = "parallel"))]
+pub type CA1Rule = FnMut(&Vec<Cell>, usize, usize) -> Cell;
+// With 'parallel' feature cells are computed on several threads sharing the rule.
+#[cfg(feature = "parallel")]
+pub type CA1Rule = Fn(&Vec<Cell>, usize, usize) -> Cell + Send + Sync;
+
+// Stateless rule that can be shared between many automata, e.g. an ensemble run on several
+// threads, see CA1::new_shared(). Any Fn(cells, width, index) closure is a Rule1.
+pub trait Rule1: Send + Sync {
+    fn apply(&self, cells: &Vec<Cell>, width: usize, idx: usize) -> Cell;
+}
+
+impl<F> Rule1 for F
+    where F: Fn(&Vec<Cell>, usize, usize) -> Cell + Send + Sync
+{
+    fn apply(&self, cells: &Vec<Cell>, width: usize, idx: usize) -> Cell {
+        self(cells, width, idx)
+    }
+}
 
 fn get_random_ca1_code(len: usize, base: usize) -> String {
     let base = base as u32;
@@ -22,74 +60,425 @@ fn get_random_ca1_code(len: usize, base: usize) -> String {
     code
 }
 
-pub fn get_ca1_rule(radius: u8, states: u8, code: Option<String>) -> Result<Box<CA1Rule>, String> {
-    static ERR_ZERO_RADIUS: &'static str = "radius < 1!";
-    static ERR_INVALID_STATES: &'static str = "states not in range 2-36!";
-    static ERR_TOO_BIG_PARAMS: &'static str = "states.pow(radius*2+1) must fit in usize!";
-    static ERR_INVALID_CODE_LEN: &'static str = "code must contain digit for every neighborhood!";
+fn ca1_cell(cells: &Vec<Cell>, width: usize, idx: i64, boundary: Boundary) -> Cell {
+    match nb::bound_idx(idx, width, boundary) {
+        Some(i) => cells[i],
+        None => {
+            match boundary {
+                Boundary::Constant(c) => c,
+                // A 1D neighborhood has fixed width, so skipped cells count as dead ones.
+                _ => 0,
+            }
+        }
+    }
+}
 
+fn check_ca1_params(radius: u8, states: u8, boundary: Boundary) -> Result<(), CaError> {
     if radius < 1 {
-        return Err(String::from(ERR_ZERO_RADIUS));
+        return Err(CaError::ZeroRadius);
     }
     if states < 2 || states > 36 {
-        return Err(String::from(ERR_INVALID_STATES));
+        return Err(CaError::InvalidStates);
     }
-
-    let radius = radius as usize;
-    let nb_width = try!(radius.checked_mul(2)
-        .ok_or(ERR_TOO_BIG_PARAMS)
-        .and_then(|x| {
-            x.checked_add(1)
-                .ok_or(ERR_TOO_BIG_PARAMS)
-        }));
-
-    let states = states as usize;
-    let mut neighborhoods = states;
-    for _ in 1..nb_width {
-        neighborhoods = try!(neighborhoods.checked_mul(states).ok_or(ERR_TOO_BIG_PARAMS));
+    match boundary {
+        Boundary::Constant(c) if c >= (states as Cell) => Err(CaError::InvalidBoundaryState),
+        _ => Ok(()),
     }
+}
+
+// Far-right digit of code is the new state for index 0.
+fn parse_ca1_code(code: Option<String>,
+                  len: usize,
+                  states: usize)
+                  -> Result<Vec<Cell>, CaError> {
     let code = match code {
         Some(s) => s,
-        None => get_random_ca1_code(neighborhoods, states),
+        None => get_random_ca1_code(len, states),
     };
-    if neighborhoods != code.len() {
-        return Err(String::from(ERR_INVALID_CODE_LEN));
+    if len != code.len() {
+        return Err(CaError::CodeLengthMismatch);
     }
-    let mut rules: Vec<Cell> = vec![0; neighborhoods];
+    let mut rules: Vec<Cell> = vec![0; len];
     for (i, c) in code.chars().rev().enumerate() {
         let new_state = try!(c.to_digit(states as u32)
-            .ok_or(format!("{} is not a digit in base {}!", c, states)));
-        rules[i] = new_state;
+            .ok_or(CaError::InvalidCodeDigit(c, states)));
+        rules[i] = new_state as Cell;
     }
+    Ok(rules)
+}
 
-    let radius = radius as i64;
-    Ok(Box::new(move |cells, width, idx| {
+pub fn get_ca1_rule(radius: u8,
+                    states: u8,
+                    code: Option<String>,
+                    boundary: Boundary)
+                    -> Result<Box<CA1Rule>, CaError> {
+    get_ca1_asymmetric_rule(radius, radius, states, code, boundary)
+}
+
+// Neighborhood of cell idx is idx-radius_left..idx+radius_right, so information flows in one
+// direction faster than in the other one. One of the radii may be 0.
+pub fn get_ca1_asymmetric_rule(radius_left: u8,
+                               radius_right: u8,
+                               states: u8,
+                               code: Option<String>,
+                               boundary: Boundary)
+                               -> Result<Box<CA1Rule>, CaError> {
+    let rule = try!(ca1_asymmetric_rule(radius_left, radius_right, states, code, boundary));
+    Ok(Box::new(rule))
+}
+
+pub fn get_shared_ca1_rule(radius: u8,
+                           states: u8,
+                           code: Option<String>,
+                           boundary: Boundary)
+                           -> Result<Arc<Rule1>, CaError> {
+    let rule = try!(ca1_asymmetric_rule(radius, radius, states, code, boundary));
+    Ok(Arc::new(rule))
+}
+
+fn ca1_asymmetric_rule(radius_left: u8,
+                       radius_right: u8,
+                       states: u8,
+                       code: Option<String>,
+                       boundary: Boundary)
+                       -> Result<impl Fn(&Vec<Cell>, usize, usize) -> Cell + Send + Sync,
+                                 CaError> {
+    try!(check_ca1_params(cmp::max(radius_left, radius_right), states, boundary));
+    let rules = try!(ca1_rules(radius_left, radius_right, states, code));
+
+    let states = states as usize;
+    let (radius_left, radius_right) = (radius_left as i64, radius_right as i64);
+    Ok(move |cells: &Vec<Cell>, width, idx| {
         let idx = idx as i64;
-        let idx_begin = idx - radius;
-        let idx_end = idx + radius + 1;
+        let idx_begin = idx - radius_left;
+        let idx_end = idx + radius_right + 1;
         let mut nb_code: usize = 0;
         for i in idx_begin..idx_end {
-            let i = nb::wrap_idx(i, width) as usize;
-            let state = cells[i] as usize;
+            let state = ca1_cell(cells, width, i, boundary) as usize;
             nb_code = nb_code * states + state;
         }
         rules[nb_code]
-    }))
+    })
+}
+
+// Count of neighborhoods of general 1D CA, it's the count of digits of its code.
+fn ca1_neighborhoods(radius_left: u8, radius_right: u8, states: u8) -> Result<usize, CaError> {
+    let nb_width = (radius_left as usize) + (radius_right as usize) + 1;
+    let states = states as usize;
+    let mut neighborhoods = states;
+    for _ in 1..nb_width {
+        neighborhoods = try!(neighborhoods.checked_mul(states).ok_or(CaError::ParamsTooBig));
+    }
+    Ok(neighborhoods)
+}
+
+// New state for every neighborhood code, see ca1_asymmetric_rule().
+fn ca1_rules(radius_left: u8,
+             radius_right: u8,
+             states: u8,
+             code: Option<String>)
+             -> Result<Vec<Cell>, CaError> {
+    let neighborhoods = try!(ca1_neighborhoods(radius_left, radius_right, states));
+    parse_ca1_code(code, neighborhoods, states as usize)
+}
+
+// Code of get_ca1_asymmetric_rule() chosen at random, the rule picked when code is None.
+pub fn random_ca1_code(radius_left: u8, radius_right: u8, states: u8) -> Result<String, CaError> {
+    try!(check_ca1_params(cmp::max(radius_left, radius_right), states, Boundary::Skip));
+    let neighborhoods = try!(ca1_neighborhoods(radius_left, radius_right, states));
+    Ok(get_random_ca1_code(neighborhoods, states as usize))
+}
+
+// (neighborhood, new state) pairs of the rule of get_ca1_asymmetric_rule() in the order of
+// digits of code, i.e. from the neighborhood with all cells in the last state to the one with
+// all cells in state 0.
+pub fn ca1_rule_table(radius_left: u8,
+                      radius_right: u8,
+                      states: u8,
+                      code: Option<String>)
+                      -> Result<Vec<(Vec<Cell>, Cell)>, CaError> {
+    // the table doesn't depend on boundary
+    try!(check_ca1_params(cmp::max(radius_left, radius_right), states, Boundary::Skip));
+    let rules = try!(ca1_rules(radius_left, radius_right, states, code));
+    let nb_width = (radius_left as usize) + (radius_right as usize) + 1;
+    let table = (0..rules.len())
+        .rev()
+        .map(|nb_code| {
+            let mut neighborhood = vec![0; nb_width];
+            let mut rest = nb_code;
+            for cell in neighborhood.iter_mut().rev() {
+                *cell = (rest % (states as usize)) as Cell;
+                rest /= states as usize;
+            }
+            (neighborhood, rules[nb_code])
+        })
+        .collect();
+    Ok(table)
+}
+
+// Code has a digit for every possible sum of states in the neighborhood.
+pub fn get_ca1_totalistic_rule(radius: u8,
+                               states: u8,
+                               code: Option<String>,
+                               boundary: Boundary)
+                               -> Result<Box<CA1Rule>, CaError> {
+    let rule = try!(ca1_totalistic_rule(radius, states, code, boundary));
+    Ok(Box::new(rule))
+}
+
+pub fn get_shared_ca1_totalistic_rule(radius: u8,
+                                      states: u8,
+                                      code: Option<String>,
+                                      boundary: Boundary)
+                                      -> Result<Arc<Rule1>, CaError> {
+    let rule = try!(ca1_totalistic_rule(radius, states, code, boundary));
+    Ok(Arc::new(rule))
+}
+
+fn ca1_totalistic_rule(radius: u8,
+                       states: u8,
+                       code: Option<String>,
+                       boundary: Boundary)
+                       -> Result<impl Fn(&Vec<Cell>, usize, usize) -> Cell + Send + Sync,
+                                 CaError> {
+    try!(check_ca1_params(radius, states, boundary));
+
+    let nb_width = 2 * (radius as usize) + 1;
+    let max_sum = ((states as usize) - 1) * nb_width;
+    let rules = try!(parse_ca1_code(code, max_sum + 1, states as usize));
+
+    let radius = radius as i64;
+    Ok(move |cells: &Vec<Cell>, width, idx| {
+        let idx = idx as i64;
+        let mut sum: usize = 0;
+        for i in (idx - radius)..(idx + radius + 1) {
+            sum += ca1_cell(cells, width, i, boundary) as usize;
+        }
+        rules[sum]
+    })
+}
+
+// Code has a digit for every (center state, sum of outer states) pair; digit for pair
+// (center, sum) has index center * (max_sum + 1) + sum counting from the right.
+pub fn get_ca1_outer_totalistic_rule(radius: u8,
+                                     states: u8,
+                                     code: Option<String>,
+                                     boundary: Boundary)
+                                     -> Result<Box<CA1Rule>, CaError> {
+    let rule = try!(ca1_outer_totalistic_rule(radius, states, code, boundary));
+    Ok(Box::new(rule))
+}
+
+pub fn get_shared_ca1_outer_totalistic_rule(radius: u8,
+                                            states: u8,
+                                            code: Option<String>,
+                                            boundary: Boundary)
+                                            -> Result<Arc<Rule1>, CaError> {
+    let rule = try!(ca1_outer_totalistic_rule(radius, states, code, boundary));
+    Ok(Arc::new(rule))
+}
+
+fn ca1_outer_totalistic_rule(radius: u8,
+                             states: u8,
+                             code: Option<String>,
+                             boundary: Boundary)
+                             -> Result<impl Fn(&Vec<Cell>, usize, usize) -> Cell + Send + Sync,
+                                       CaError> {
+    try!(check_ca1_params(radius, states, boundary));
+
+    let max_sum = ((states as usize) - 1) * 2 * (radius as usize);
+    let len = (states as usize) * (max_sum + 1);
+    let rules = try!(parse_ca1_code(code, len, states as usize));
+
+    let radius = radius as i64;
+    Ok(move |cells: &Vec<Cell>, width, idx| {
+        let center = cells[idx] as usize;
+        let idx = idx as i64;
+        let mut sum: usize = 0;
+        for i in (idx - radius)..(idx + radius + 1) {
+            if i != idx {
+                sum += ca1_cell(cells, width, i, boundary) as usize;
+            }
+        }
+        rules[center * (max_sum + 1) + sum]
+    })
 }
 
 pub fn get_elementary_rule(code: u8) -> Box<CA1Rule> {
-    get_ca1_rule(1, 2, Some(format!("{:0>8b}", code))).unwrap()
+    get_ca1_rule(1, 2, Some(format!("{:0>8b}", code)), Boundary::default()).unwrap()
+}
+
+pub fn get_shared_elementary_rule(code: u8) -> Arc<Rule1> {
+    get_shared_ca1_rule(1, 2, Some(format!("{:0>8b}", code)), Boundary::default()).unwrap()
+}
+
+// Count of cells in every state, index is state.
+fn population<'a, I>(cells: I) -> Vec<usize>
+    where I: Iterator<Item = &'a Cell>
+{
+    let mut counts: Vec<usize> = Vec::new();
+    for &cell in cells {
+        let state = cell as usize;
+        if state >= counts.len() {
+            counts.resize(state + 1, 0);
+        }
+        counts[state] += 1;
+    }
+    counts
+}
+
+fn density(population: Vec<usize>, state: Cell) -> f64 {
+    let total: usize = population.iter().sum();
+    match population.get(state as usize) {
+        Some(&count) if total > 0 => (count as f64) / (total as f64),
+        _ => 0.0,
+    }
+}
+
+// 64-bit FNV-1a hash of cells.
+fn fnv_hash(cells: &[Cell]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &cell in cells {
+        for i in 0..4 {
+            hash ^= ((cell as u32 >> (8 * i)) & 0xff) as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
+
+// Shape of a group of cells: width and height of its bounding box and hash of the cells in it.
+type ShapeKey = (usize, usize, u64);
+
+// Groups of live cells of w x h grid joined when they are at most 2 cells apart, since parts
+// of spaceships often are, as (top, left, shape) of their bounding boxes. Groups don't wrap
+// around the edges.
+fn cell_groups(cells: &[Cell], w: usize, h: usize) -> Vec<(usize, usize, ShapeKey)> {
+    let mut grouped = vec![false; cells.len()];
+    let mut groups = Vec::new();
+    for start in 0..cells.len() {
+        if cells[start] == 0 || grouped[start] {
+            continue;
+        }
+        grouped[start] = true;
+        let mut members = vec![start];
+        let mut i = 0;
+        while i < members.len() {
+            let (row, col) = (members[i] / w, members[i] % w);
+            for r in row.saturating_sub(2)..cmp::min(row + 3, h) {
+                for c in col.saturating_sub(2)..cmp::min(col + 3, w) {
+                    let idx = r * w + c;
+                    if cells[idx] != 0 && !grouped[idx] {
+                        grouped[idx] = true;
+                        members.push(idx);
+                    }
+                }
+            }
+            i += 1;
+        }
+        let top = members.iter().map(|&idx| idx / w).min().unwrap();
+        let bottom = members.iter().map(|&idx| idx / w).max().unwrap();
+        let left = members.iter().map(|&idx| idx % w).min().unwrap();
+        let right = members.iter().map(|&idx| idx % w).max().unwrap();
+        let (gw, gh) = (right - left + 1, bottom - top + 1);
+        let mut shape = vec![0; gw * gh];
+        for &idx in &members {
+            shape[(idx / w - top) * gw + idx % w - left] = cells[idx];
+        }
+        groups.push((top, left, (gw, gh, fnv_hash(&shape))));
+    }
+    groups
+}
+
+// Row by row grid w x h copied into grid new_w x new_h, cells outside of it are default.
+fn resized<T: Copy + Default>(cells: &[T], w: usize, h: usize, new_w: usize, new_h: usize)
+                              -> Vec<T> {
+    let mut result = vec![T::default(); new_w * new_h];
+    let len = cmp::min(w, new_w);
+    for row in 0..cmp::min(h, new_h) {
+        result[row * new_w..row * new_w + len].copy_from_slice(&cells[row * w..row * w + len]);
+    }
+    result
+}
+
+// Keeps up to capacity previous generations, the oldest ones are dropped first.
+struct History<T> {
+    capacity: usize,
+    generations: VecDeque<T>,
+}
+
+impl<T> History<T> {
+    fn new(capacity: usize) -> History<T> {
+        History {
+            capacity: capacity,
+            generations: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    fn push(&mut self, generation: T) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.generations.len() == self.capacity {
+            self.generations.pop_front();
+        }
+        self.generations.push_back(generation);
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        self.generations.pop_back()
+    }
+
+    fn clear(&mut self) {
+        self.generations.clear();
+    }
+}
+
+// Rule owned by a single CA1 or shared with other ones.
+enum Ca1RuleRef {
+    Owned(Box<CA1Rule>),
+    Shared(Arc<Rule1>),
+}
+
+impl Ca1RuleRef {
+    #[cfg(not(feature = "parallel"))]
+    fn apply(&mut self, cells: &Vec<Cell>, width: usize, idx: usize) -> Cell {
+        match *self {
+            Ca1RuleRef::Owned(ref mut rule) => rule(cells, width, idx),
+            Ca1RuleRef::Shared(ref rule) => rule.apply(cells, width, idx),
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    fn apply(&self, cells: &Vec<Cell>, width: usize, idx: usize) -> Cell {
+        match *self {
+            Ca1RuleRef::Owned(ref rule) => rule(cells, width, idx),
+            Ca1RuleRef::Shared(ref rule) => rule.apply(cells, width, idx),
+        }
+    }
 }
 
 pub struct CA1 {
     pub w: usize,
     pub cells: Vec<Cell>,
     future: Vec<Cell>,
-    rule: Box<CA1Rule>,
+    rule: Ca1RuleRef,
+    history: History<Vec<Cell>>,
+    // previous generation of second-order CA
+    previous: Option<Vec<Cell>>,
 }
 
 impl CA1 {
     pub fn new(cells: Vec<Cell>, rule: Box<CA1Rule>) -> CA1 {
+        CA1::with_rule_ref(cells, Ca1RuleRef::Owned(rule))
+    }
+
+    // Same rule may run many automata at once, see get_shared_ca1_rule().
+    pub fn new_shared(cells: Vec<Cell>, rule: Arc<Rule1>) -> CA1 {
+        CA1::with_rule_ref(cells, Ca1RuleRef::Shared(rule))
+    }
+
+    fn with_rule_ref(cells: Vec<Cell>, rule: Ca1RuleRef) -> CA1 {
         let w = cells.len();
         let future = cells.to_vec();
         CA1 {
@@ -97,15 +486,67 @@ impl CA1 {
             cells: cells,
             future: future,
             rule: rule,
+            history: History::new(0),
+            previous: None,
+        }
+    }
+
+    // Remember up to capacity previous generations for step_back().
+    pub fn with_history(mut self, capacity: usize) -> CA1 {
+        self.history = History::new(capacity);
+        self
+    }
+
+    // Starts over from cells with another rule, remembered generations are dropped.
+    pub fn restart(&mut self, cells: Vec<Cell>, rule: Box<CA1Rule>) {
+        self.w = cells.len();
+        self.future = cells.to_vec();
+        self.cells = cells;
+        self.rule = Ca1RuleRef::Owned(rule);
+        self.history = History::new(self.history.capacity);
+        if self.previous.is_some() {
+            self.previous = Some(vec![0; self.w]);
         }
     }
 
     pub fn new_ca1(cells: Vec<Cell>,
                    radius: u8,
                    states: u8,
-                   code: Option<String>)
-                   -> Result<CA1, String> {
-        let rule = try!(get_ca1_rule(radius, states, code));
+                   code: Option<String>,
+                   boundary: Boundary)
+                   -> Result<CA1, CaError> {
+        let rule = try!(get_ca1_rule(radius, states, code, boundary));
+        Ok(CA1::new(cells, rule))
+    }
+
+    // radius is (left, right)
+    pub fn new_ca1_asymmetric(cells: Vec<Cell>,
+                              radius: (u8, u8),
+                              states: u8,
+                              code: Option<String>,
+                              boundary: Boundary)
+                              -> Result<CA1, CaError> {
+        let rule = try!(get_ca1_asymmetric_rule(radius.0, radius.1, states, code, boundary));
+        Ok(CA1::new(cells, rule))
+    }
+
+    pub fn new_ca1_totalistic(cells: Vec<Cell>,
+                              radius: u8,
+                              states: u8,
+                              code: Option<String>,
+                              boundary: Boundary)
+                              -> Result<CA1, CaError> {
+        let rule = try!(get_ca1_totalistic_rule(radius, states, code, boundary));
+        Ok(CA1::new(cells, rule))
+    }
+
+    pub fn new_ca1_outer_totalistic(cells: Vec<Cell>,
+                                    radius: u8,
+                                    states: u8,
+                                    code: Option<String>,
+                                    boundary: Boundary)
+                                    -> Result<CA1, CaError> {
+        let rule = try!(get_ca1_outer_totalistic_rule(radius, states, code, boundary));
         Ok(CA1::new(cells, rule))
     }
 
@@ -114,68 +555,385 @@ impl CA1 {
         CA1::new(cells, rule)
     }
 
+    // Second-order elementary CA: new state is rule(neighborhood) XOR previous state, so it
+    // can be run backward exactly with reverse_tick().
+    pub fn new_reversible(cells: Vec<Cell>,
+                          previous: Vec<Cell>,
+                          code: u8)
+                          -> Result<CA1, CaError> {
+        if cells.len() != previous.len() {
+            return Err(CaError::PreviousSizeMismatch);
+        }
+        let mut ca = CA1::new_elementary(cells, code);
+        ca.previous = Some(previous);
+        Ok(ca)
+    }
+
+    pub fn is_reversible(&self) -> bool {
+        self.previous.is_some()
+    }
+
+    pub fn previous(&self) -> Option<&[Cell]> {
+        self.previous.as_ref().map(|p| &p[..])
+    }
+
+    /// Iterates over cells as (row, col, state) of a grid with a single row.
+    ///
+    /// ```
+    /// use ca::CA1;
+    ///
+    /// let ca = CA1::new_elementary(vec![0, 1, 1, 0], 30);
+    /// let live: Vec<usize> = ca.iter_cells()
+    ///     .filter(|&(_, _, state)| state == 1)
+    ///     .map(|(_, col, _)| col)
+    ///     .collect();
+    /// assert_eq!(live, vec![1, 2]);
+    /// ```
+    pub fn iter_cells<'a>(&'a self) -> impl Iterator<Item = (usize, usize, Cell)> + 'a {
+        self.cells.iter().enumerate().map(|(col, &cell)| (0, col, cell))
+    }
+
+    pub fn population(&self) -> Vec<usize> {
+        population(self.cells.iter())
+    }
+
+    pub fn density(&self, state: Cell) -> f64 {
+        density(self.population(), state)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        snapshot::save(path, self.w, 1, &self.cells)
+    }
+
+    // Replaces cells with the ones saved by save(), the rule is kept.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<(), String> {
+        let (w, h, cells) = try!(snapshot::load(path));
+        if w != self.w || h != 1 {
+            return Err(format!("snapshot size must be {}x1!", self.w));
+        }
+        self.cells = cells;
+        Ok(())
+    }
+
+    // Runs n generations.
+    pub fn tick_n(&mut self, n: usize) {
+        for _ in 0..n {
+            self.tick();
+        }
+    }
+
     pub fn tick(&mut self) {
+        if let Some(ref mut previous) = self.previous {
+            for idx in 0..self.w {
+                self.future[idx] = self.rule.apply(&self.cells, self.w, idx) ^ previous[idx];
+            }
+            // (previous, cells) -> (cells, future)
+            mem::swap(previous, &mut self.cells);
+            mem::swap(&mut self.cells, &mut self.future);
+            return;
+        }
+        if self.history.capacity > 0 {
+            self.history.push(self.cells.to_vec());
+        }
+        self.compute_future();
+        mem::swap(&mut self.cells, &mut self.future);
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn compute_future(&mut self) {
+        self.compute_future_sequential();
+    }
+
+    #[cfg(feature = "parallel")]
+    fn compute_future(&mut self) {
+        self.compute_future_parallel();
+    }
+
+    // kept with 'parallel' feature to test parallel version against
+    #[cfg_attr(feature = "parallel", allow(dead_code))]
+    fn compute_future_sequential(&mut self) {
         for idx in 0..self.w {
-            self.future[idx] = (self.rule)(&self.cells, self.w, idx);
+            self.future[idx] = self.rule.apply(&self.cells, self.w, idx);
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    fn compute_future_parallel(&mut self) {
+        let w = self.w;
+        let cells = &self.cells;
+        let rule = &self.rule;
+        self.future.par_iter_mut().enumerate().for_each(|(idx, cell)| {
+            *cell = rule.apply(cells, w, idx);
+        });
+    }
+
+    // Runs second-order CA one generation backward, returns false if CA isn't reversible.
+    pub fn reverse_tick(&mut self) -> bool {
+        match self.previous {
+            Some(ref mut previous) => {
+                for idx in 0..self.w {
+                    self.future[idx] = self.rule.apply(previous, self.w, idx) ^ self.cells[idx];
+                }
+                // (previous, cells) -> (future, previous)
+                mem::swap(previous, &mut self.cells);
+                mem::swap(previous, &mut self.future);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Restores previous generation, returns false if history is empty. Reversible CA is
+    // just run backward.
+    pub fn step_back(&mut self) -> bool {
+        if self.is_reversible() {
+            return self.reverse_tick();
+        }
+        match self.history.pop() {
+            Some(cells) => {
+                self.cells = cells;
+                true
+            }
+            None => false,
         }
-        self.cells.copy_from_slice(&self.future);
     }
 }
 
 // (cells, width, height, row, col) -> new_state
-pub type CA2Rule = Fn(&Vec<Vec<Cell>>, usize, usize, usize, usize) -> Cell;
+// cells are stored row by row, cell (row, col) has index row * width + col.
+// Rule may keep state between calls, cells are visited row by row.
+#[cfg(not(feature = "parallel"))]
+pub type CA2Rule = FnMut(&[Cell], usize, usize, usize, usize) -> Cell;
+// With 'parallel' feature rows are computed on several threads sharing the rule.
+#[cfg(feature = "parallel")]
+pub type CA2Rule = Fn(&[Cell], usize, usize, usize, usize) -> Cell + Send + Sync;
+
+pub fn get_life_rule<B: Into<Topology>>(survive: Vec<Cell>,
+                                        birth: Vec<Cell>,
+                                        boundary: B)
+                                        -> Box<CA2Rule> {
+    get_life_rule_for_state(survive, birth, 1, boundary)
+}
+
+// Count of the 8 neighbors of (row, col) in state on a torus of at least 3x3 cells, the hot
+// path of life rules.
+fn count_wrapped_moore(cells: &[Cell],
+                       w: usize,
+                       h: usize,
+                       row: usize,
+                       col: usize,
+                       state: Cell)
+                       -> Cell {
+    let up = ((row + h - 1) % h) * w;
+    let down = ((row + 1) % h) * w;
+    let mid = row * w;
+    let left = (col + w - 1) % w;
+    let right = (col + 1) % w;
+    let neighbors = [cells[up + left],
+                     cells[up + col],
+                     cells[up + right],
+                     cells[mid + left],
+                     cells[mid + right],
+                     cells[down + left],
+                     cells[down + col],
+                     cells[down + right]];
+    neighbors.iter().filter(|&&nb| nb == state).count() as Cell
+}
 
-pub fn get_life_rule(survive: Vec<Cell>, birth: Vec<Cell>) -> Box<CA2Rule> {
+// Life-like rule where live_state is the live state: only neighbors in it are counted, dead
+// cells (0) are born into it. Cells in other states are left as they are.
+pub fn get_life_rule_for_state<B: Into<Topology>>(survive: Vec<Cell>,
+                                                  birth: Vec<Cell>,
+                                                  live_state: Cell,
+                                                  boundary: B)
+                                                  -> Box<CA2Rule> {
+    let boundary: Topology = boundary.into();
+    let wrapped = boundary == Topology::from(Boundary::Wrap);
     Box::new(move |cells, w, h, row, col| {
-        let mut live = 0;
-        for nb in nb::MooreNeighborhoodIterator::new(cells, w, h, row, col, 1) {
-            if nb == 1 {
-                live += 1;
-            }
-        }
-        match cells[row][col] {
+        let live = if wrapped && w >= 3 && h >= 3 {
+            count_wrapped_moore(cells, w, h, row, col, live_state)
+        } else {
+            nb::MooreNeighborhoodIterator::new(cells, w, h, row, col, 1, boundary)
+                .filter(|&nb| nb == live_state)
+                .count() as Cell
+        };
+        match cells[row * w + col] {
             0 => {
                 if birth.contains(&live) {
-                    1
+                    live_state
                 } else {
                     0
                 }
             }
-            _ => {
+            cell if cell == live_state => {
                 if survive.contains(&live) {
-                    1
+                    live_state
                 } else {
                     0
                 }
             }
+            cell => cell,
+        }
+    })
+}
+
+// Rules for (even, odd) generations of life-like rule with B0 and without S8 that keep dead
+// background dead: cells of odd generations are stored inverted. Even rule inverts its
+// result, odd rule inverts its input, i.e. a cell with n stored live neighbors has 8 - n ones.
+// This holds only when every cell has 8 neighbors, so edges must wrap or reflect.
+pub fn get_b0_life_rules<B: Into<Topology>>(survive: Vec<Cell>,
+                                            birth: Vec<Cell>,
+                                            boundary: B)
+                                            -> (Box<CA2Rule>, Box<CA2Rule>) {
+    let boundary: Topology = boundary.into();
+    let complement = |counts: &Vec<Cell>| (0..9).filter(|n| !counts.contains(n)).collect();
+    let mirror = |counts: &Vec<Cell>| {
+        counts.iter().filter(|&&n| n <= 8).map(|&n| 8 - n).collect()
+    };
+    let even = get_life_rule(complement(&survive), complement(&birth), boundary);
+    let odd = get_life_rule(mirror(&birth), mirror(&survive), boundary);
+    (even, odd)
+}
+
+// Life-like rule where birth and survival happen with probabilities p_birth and p_survive
+// when the count of live neighbors allows them. The rng is shared by all cells, so the rule
+// stays usable from several threads. CA with this rule must be made
+// with_nondeterministic_rule(), otherwise it may be taken for frozen.
+pub fn get_probabilistic_life_rule<R, B>(survive: Vec<Cell>,
+                                         birth: Vec<Cell>,
+                                         p_survive: f64,
+                                         p_birth: f64,
+                                         rng: R,
+                                         boundary: B)
+                                         -> Box<CA2Rule>
+    where R: Rng + Send + 'static,
+          B: Into<Topology>
+{
+    let boundary: Topology = boundary.into();
+    let rng = Mutex::new(rng);
+    Box::new(move |cells, w, h, row, col| {
+        let live = nb::MooreNeighborhoodIterator::new(cells, w, h, row, col, 1, boundary)
+            .filter(|&nb| nb == 1)
+            .count() as Cell;
+        let (allowed, p) = match cells[row * w + col] {
+            0 => (birth.contains(&live), p_birth),
+            _ => (survive.contains(&live), p_survive),
+        };
+        if allowed && rng.lock().unwrap().next_f64() < p {
+            1
+        } else {
+            0
+        }
+    })
+}
+
+// Every neighbor adds its weight multiplied by its state to the weighted sum. Dead cell is
+// born if the sum is in birth, live cell survives if the sum is in survive. Weights are given
+// in the order of nbh.offsets().
+pub fn get_weighted_rule<B: Into<Topology>>(weights: Vec<i64>,
+                                            birth: Vec<i64>,
+                                            survive: Vec<i64>,
+                                            nbh: nb::Neighborhood,
+                                            boundary: B)
+                                            -> Result<Box<CA2Rule>, CaError> {
+    let boundary: Topology = boundary.into();
+    if weights.len() != nbh.size() {
+        return Err(CaError::WeightsLengthMismatch(nbh.size()));
+    }
+    let offsets = nbh.offsets();
+    Ok(Box::new(move |cells, w, h, row, col| {
+        let (row_sgn, col_sgn) = (row as i64, col as i64);
+        let mut sum: i64 = 0;
+        for (&(drow, dcol), &weight) in offsets.iter().zip(weights.iter()) {
+            if let Some(nb) = nb::cell_at(cells, w, h, row_sgn + drow, col_sgn + dcol, boundary) {
+                sum += weight * (nb as i64);
+            }
+        }
+        let table = match cells[row * w + col] {
+            0 => &birth,
+            _ => &survive,
+        };
+        if table.contains(&sum) {
+            1
+        } else {
+            0
+        }
+    }))
+}
+
+// Larger than Life: dead cell is born if count of live cells in its Moore neighborhood of
+// given range is in [birth_lo, birth_hi], live cell survives if it's in [survive_lo, survive_hi].
+pub fn get_ltl_rule<B: Into<Topology>>(range: u32,
+                                       birth_lo: u32,
+                                       birth_hi: u32,
+                                       survive_lo: u32,
+                                       survive_hi: u32,
+                                       boundary: B)
+                                       -> Box<CA2Rule> {
+    let boundary: Topology = boundary.into();
+    Box::new(move |cells, w, h, row, col| {
+        let live = nb::MooreNeighborhoodIterator::new(cells, w, h, row, col, range, boundary)
+            .filter(|&nb| nb == 1)
+            .count() as u32;
+        let (lo, hi) = match cells[row * w + col] {
+            0 => (birth_lo, birth_hi),
+            _ => (survive_lo, survive_hi),
+        };
+        if live >= lo && live <= hi {
+            1
+        } else {
+            0
         }
     })
 }
 
-pub fn get_cyclic_rule(nbh: nb::Neighborhood, threshold: u8, states: u32) -> Box<CA2Rule> {
+pub fn get_cyclic_rule<B: Into<Topology>>(nbh: nb::Neighborhood,
+                                          threshold: u8,
+                                          states: u32,
+                                          boundary: B)
+                                          -> Box<CA2Rule> {
+    get_cyclic_rule_with_successor(nbh, threshold, move |cell| {
+        ((cell as u32 + 1) % states) as Cell
+    }, boundary)
+}
+
+// Cyclic rule where state of a cell is replaced by successor(state) instead of the next one,
+// e.g. a permutation of states.
+pub fn get_cyclic_rule_with_successor<B, F>(nbh: nb::Neighborhood,
+                                            threshold: u8,
+                                            successor: F,
+                                            boundary: B)
+                                            -> Box<CA2Rule>
+    where B: Into<Topology>,
+          F: Fn(Cell) -> Cell + Send + Sync + 'static
+{
+    let boundary: Topology = boundary.into();
     Box::new(move |cells, w, h, row, col| {
-        let cell = cells[row][col];
-        let next = (cell + 1) % states;
-        let mut cnt_next = 0;
-        match nbh {
+        let cell = cells[row * w + col];
+        let next = successor(cell);
+        let cnt_next = match nbh {
             nb::Neighborhood::Moore(range) => {
-                let it = nb::MooreNeighborhoodIterator::new(cells, w, h, row, col, range);
-                for nb in it {
-                    if nb == next {
-                        cnt_next += 1;
-                    }
-                }
+                nb::MooreNeighborhoodIterator::new(cells, w, h, row, col, range, boundary)
+                    .filter(|&nb| nb == next)
+                    .count()
             }
             nb::Neighborhood::VonNeumann(range) => {
-                let it = nb::VonNeumannNeighborhoodIterator::new(cells, w, h, row, col, range);
-                for nb in it {
-                    if nb == next {
-                        cnt_next += 1;
-                    }
-                }
+                nb::VonNeumannNeighborhoodIterator::new(cells, w, h, row, col, range, boundary)
+                    .filter(|&nb| nb == next)
+                    .count()
+            }
+            nb::Neighborhood::Hex(range) => {
+                nb::HexNeighborhoodIterator::new(cells, w, h, row, col, range, boundary)
+                    .filter(|&nb| nb == next)
+                    .count()
+            }
+            nb::Neighborhood::Custom(ref offsets) => {
+                nb::CustomNeighborhoodIterator::new(cells, w, h, row, col, offsets, boundary)
+                    .filter(|&nb| nb == next)
+                    .count()
             }
         };
-        if cnt_next >= threshold {
+        if cnt_next >= (threshold as usize) {
             next
         } else {
             cell
@@ -183,50 +941,1973 @@ pub fn get_cyclic_rule(nbh: nb::Neighborhood, threshold: u8, states: u32) -> Box
     })
 }
 
-pub struct CA2 {
-    pub w: usize,
-    pub h: usize,
-    pub cells: Vec<Vec<Cell>>,
-    future: Vec<Vec<Cell>>,
-    rule: Box<CA2Rule>,
-}
-
-impl CA2 {
-    pub fn new(cells: Vec<Vec<Cell>>, rule: Box<CA2Rule>) -> CA2 {
-        let h = cells.len();
-        let w = cells[0].len();
-        let future = cells.to_vec();
-        CA2 {
-            w: w,
-            h: h,
-            cells: cells,
-            future: future,
-            rule: rule,
+// 2D analog of get_ca1_outer_totalistic_rule: code has a digit for every (center state, sum of
+// neighbor states) pair, digit for pair (center, sum) has index center * (max_sum + 1) + sum
+// counting from the right. Neighbors skipped by the boundary add nothing to the sum.
+pub fn get_ca2_totalistic_rule<B: Into<Topology>>(nbh: nb::Neighborhood,
+                                                  states: u8,
+                                                  code: Option<String>,
+                                                  boundary: B)
+                                                  -> Result<Box<CA2Rule>, CaError> {
+    let boundary: Topology = boundary.into();
+    if states < 2 || states > 36 {
+        return Err(CaError::InvalidStates);
+    }
+    for &axis in &[boundary.x, boundary.y] {
+        if let Boundary::Constant(c) = axis {
+            if c >= (states as Cell) {
+                return Err(CaError::InvalidBoundaryState);
+            }
         }
     }
 
-    pub fn new_life(cells: Vec<Vec<Cell>>, survive: Vec<Cell>, birth: Vec<Cell>) -> CA2 {
-        let rule = get_life_rule(survive, birth);
-        CA2::new(cells, rule)
-    }
+    let max_sum = try!(((states as usize) - 1)
+        .checked_mul(nbh.size())
+        .ok_or(CaError::ParamsTooBig));
+    let len = try!((max_sum + 1).checked_mul(states as usize).ok_or(CaError::ParamsTooBig));
+    let rules = try!(parse_ca1_code(code, len, states as usize));
 
-    pub fn new_cyclic(cells: Vec<Vec<Cell>>,
-                      nbh: nb::Neighborhood,
-                      threshold: u8,
-                      states: u32)
-                      -> CA2 {
-        let rule = get_cyclic_rule(nbh, threshold, states);
-        CA2::new(cells, rule)
-    }
+    let offsets = nbh.offsets();
+    Ok(Box::new(move |cells, w, h, row, col| {
+        let center = cells[row * w + col] as usize;
+        let sum: usize =
+            nb::CustomNeighborhoodIterator::new(cells, w, h, row, col, &offsets, boundary)
+                .map(|nb| nb as usize)
+                .sum();
+        rules[center * (max_sum + 1) + sum]
+    }))
+}
 
-    pub fn tick(&mut self) {
-        for row in 0..self.h {
-            for col in 0..self.w {
-                self.future[row][col] = (self.rule)(&self.cells, self.w, self.h, row, col);
-            }
-        }
-        for row in 0..self.h {
-            self.cells[row].copy_from_slice(&self.future[row]);
+// States: 0 - dead, 1 - alive, 2..states-1 - dying. Only alive cells are counted as live
+// neighbors; alive cell that doesn't survive and dying cells move to the next state.
+pub fn get_generations_rule<B: Into<Topology>>(survive: Vec<Cell>,
+                                               birth: Vec<Cell>,
+                                               states: u32,
+                                               boundary: B)
+                                               -> Box<CA2Rule> {
+    let boundary: Topology = boundary.into();
+    Box::new(move |cells, w, h, row, col| {
+        let cell = cells[row * w + col];
+        if cell > 1 {
+            return ((cell as u32 + 1) % states) as Cell;
         }
+        let live = nb::MooreNeighborhoodIterator::new(cells, w, h, row, col, 1, boundary)
+            .filter(|&nb| nb == 1)
+            .count() as Cell;
+        match cell {
+            0 => {
+                if birth.contains(&live) {
+                    1
+                } else {
+                    0
+                }
+            }
+            _ => {
+                if survive.contains(&live) {
+                    1
+                } else {
+                    (2 % states) as Cell
+                }
+            }
+        }
+    })
+}
+
+// States: 0 - off, 1 - on, 2 - dying.
+pub fn get_brians_brain_rule<B: Into<Topology>>(boundary: B) -> Box<CA2Rule> {
+    let boundary: Topology = boundary.into();
+    Box::new(move |cells, w, h, row, col| {
+        match cells[row * w + col] {
+            0 => {
+                let on = nb::MooreNeighborhoodIterator::new(cells, w, h, row, col, 1, boundary)
+                    .filter(|&nb| nb == 1)
+                    .count();
+                if on == 2 {
+                    1
+                } else {
+                    0
+                }
+            }
+            1 => 2,
+            _ => 0,
+        }
+    })
+}
+
+// States: 0 - empty, 1 - electron head, 2 - electron tail, 3 - conductor.
+pub fn get_wireworld_rule<B: Into<Topology>>(boundary: B) -> Box<CA2Rule> {
+    let boundary: Topology = boundary.into();
+    Box::new(move |cells, w, h, row, col| {
+        match cells[row * w + col] {
+            1 => 2,
+            2 => 3,
+            3 => {
+                let heads = nb::MooreNeighborhoodIterator::new(cells, w, h, row, col, 1, boundary)
+                    .filter(|&nb| nb == 1)
+                    .count();
+                if heads == 1 || heads == 2 {
+                    1
+                } else {
+                    3
+                }
+            }
+            _ => 0,
+        }
+    })
+}
+
+// Asynchronous modes update one cell in place per tick, they differ in the order of cells.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum UpdateMode {
+    // all cells at once
+    Synchronous,
+    // randomly chosen cell
+    AsyncRandom,
+    // cells row by row
+    AsyncSweep,
+    // every cell once per w * h ticks, in random order that changes every such sweep
+    AsyncShuffle,
+}
+
+impl Default for UpdateMode {
+    fn default() -> UpdateMode {
+        UpdateMode::Synchronous
+    }
+}
+
+impl UpdateMode {
+    // Whether the order of cells depends on the rng, see CA2::with_update_rng().
+    pub fn is_random(&self) -> bool {
+        *self == UpdateMode::AsyncRandom || *self == UpdateMode::AsyncShuffle
+    }
+}
+
+// Picks cells for asynchronous update modes.
+struct CellOrder {
+    // index of the next cell of the sweep in shuffled (AsyncShuffle) or in cells (AsyncSweep)
+    pos: usize,
+    shuffled: Vec<usize>,
+    rng: StdRng,
+}
+
+impl CellOrder {
+    fn new(rng: StdRng) -> CellOrder {
+        CellOrder {
+            pos: 0,
+            shuffled: Vec::new(),
+            rng: rng,
+        }
+    }
+
+    // Index of the next cell to update out of len ones.
+    fn next(&mut self, update: UpdateMode, len: usize) -> usize {
+        match update {
+            UpdateMode::Synchronous => unreachable!(),
+            UpdateMode::AsyncRandom => self.rng.gen_range(0, len),
+            UpdateMode::AsyncSweep => {
+                let idx = self.pos % len;
+                self.pos = idx + 1;
+                idx
+            }
+            UpdateMode::AsyncShuffle => {
+                if self.pos >= self.shuffled.len() || self.shuffled.len() != len {
+                    self.shuffled = (0..len).collect();
+                    self.rng.shuffle(&mut self.shuffled);
+                    self.pos = 0;
+                }
+                self.pos += 1;
+                self.shuffled[self.pos - 1]
+            }
+        }
+    }
+}
+
+pub struct CA2 {
+    pub w: usize,
+    pub h: usize,
+    // row by row, see index()
+    pub cells: Vec<Cell>,
+    future: Vec<Cell>,
+    rule: Box<CA2Rule>,
+    // rule swapped with rule after every synchronous tick, see with_alternating_rule()
+    alternate: Option<Box<CA2Rule>>,
+    // whether the same cells always get the same next generation, see is_frozen()
+    deterministic: bool,
+    history: History<Vec<Cell>>,
+    update: UpdateMode,
+    order: CellOrder,
+    // count of cells changed by the last tick, None before the first one
+    changed: Option<usize>,
+    // generations every live (non-zero) cell has been alive for, see with_ages()
+    ages: Option<Vec<u32>>,
+}
+
+// (width, height) of a non-empty grid with rows of equal non-zero length.
+fn grid_size(cells: &[Vec<Cell>]) -> Result<(usize, usize), CaError> {
+    let w = try!(cells.first().map(|row| row.len()).ok_or(CaError::EmptyGrid));
+    if w == 0 {
+        return Err(CaError::EmptyGrid);
+    }
+    if let Some(row) = cells.iter().position(|row| row.len() != w) {
+        return Err(CaError::JaggedGrid(row));
+    }
+    Ok((w, cells.len()))
+}
+
+impl CA2 {
+    // Cells must be a non-empty grid with rows of equal non-zero length.
+    pub fn new(cells: Vec<Vec<Cell>>, rule: Box<CA2Rule>) -> Result<CA2, CaError> {
+        let (w, h) = try!(grid_size(&cells));
+        let cells = cells.concat();
+        let future = cells.to_vec();
+        Ok(CA2 {
+            w: w,
+            h: h,
+            cells: cells,
+            future: future,
+            rule: rule,
+            alternate: None,
+            deterministic: true,
+            history: History::new(0),
+            update: UpdateMode::default(),
+            order: CellOrder::new(gen::seeded_rng(gen::random_seed())),
+            changed: None,
+            ages: None,
+        })
+    }
+
+    // Remember up to capacity previous generations for step_back().
+    pub fn with_history(mut self, capacity: usize) -> CA2 {
+        self.history = History::new(capacity);
+        self
+    }
+
+    // Makes rule apply to every other generation starting from the second one.
+    pub fn with_alternating_rule(mut self, rule: Box<CA2Rule>) -> CA2 {
+        self.alternate = Some(rule);
+        self
+    }
+
+    // Rule may give different results for the same cells, e.g. get_probabilistic_life_rule().
+    pub fn with_nondeterministic_rule(mut self) -> CA2 {
+        self.deterministic = false;
+        self
+    }
+
+    fn swap_rules(&mut self) {
+        if let Some(ref mut alternate) = self.alternate {
+            mem::swap(&mut self.rule, alternate);
+        }
+    }
+
+    pub fn with_update(mut self, update: UpdateMode) -> CA2 {
+        self.update = update;
+        self
+    }
+
+    // Rng picking cells for random update modes, e.g. gen::seeded_rng() for repeatable runs.
+    pub fn with_update_rng(mut self, rng: StdRng) -> CA2 {
+        self.order = CellOrder::new(rng);
+        self
+    }
+
+    // Track age of live cells: it's incremented every tick a cell stays non-zero and reset
+    // when it dies. Cells live at the start have age 0.
+    pub fn with_ages(mut self) -> CA2 {
+        self.ages = Some(vec![0; self.cells.len()]);
+        self
+    }
+
+    // None if ages are not tracked.
+    pub fn age(&self, row: usize, col: usize) -> Option<u32> {
+        let idx = self.index(row, col);
+        self.ages.as_ref().map(|ages| ages[idx])
+    }
+
+    fn update_age(&mut self, idx: usize, old: Cell) {
+        if let Some(ref mut ages) = self.ages {
+            ages[idx] = if self.cells[idx] != 0 && old != 0 {
+                ages[idx] + 1
+            } else {
+                0
+            };
+        }
+    }
+
+    // Rules with B0 but without S8 turn the infinite background on and off every generation,
+    // they are emulated by showing every other generation inverted, so the background stays
+    // dead, see get_b0_life_rules().
+    pub fn new_life<B: Into<Topology>>(cells: Vec<Vec<Cell>>,
+                                       survive: Vec<Cell>,
+                                       birth: Vec<Cell>,
+                                       boundary: B)
+                                       -> Result<CA2, CaError> {
+        if birth.contains(&0) && !survive.contains(&8) {
+            let (even, odd) = get_b0_life_rules(survive, birth, boundary);
+            return Ok(try!(CA2::new(cells, even)).with_alternating_rule(odd));
+        }
+        let rule = get_life_rule(survive, birth, boundary);
+        CA2::new(cells, rule)
+    }
+
+    pub fn new_life_for_state<B: Into<Topology>>(cells: Vec<Vec<Cell>>,
+                                                 survive: Vec<Cell>,
+                                                 birth: Vec<Cell>,
+                                                 live_state: Cell,
+                                                 boundary: B)
+                                                 -> Result<CA2, CaError> {
+        CA2::new(cells, get_life_rule_for_state(survive, birth, live_state, boundary))
+    }
+
+    pub fn new_cyclic<B: Into<Topology>>(cells: Vec<Vec<Cell>>,
+                                         nbh: nb::Neighborhood,
+                                         threshold: u8,
+                                         states: u32,
+                                         boundary: B)
+                                         -> Result<CA2, CaError> {
+        let rule = get_cyclic_rule(nbh, threshold, states, boundary);
+        CA2::new(cells, rule)
+    }
+
+    // successor[state] is the state that follows state.
+    pub fn new_cyclic_with_successors<B: Into<Topology>>(cells: Vec<Vec<Cell>>,
+                                                         nbh: nb::Neighborhood,
+                                                         threshold: u8,
+                                                         successors: Vec<Cell>,
+                                                         boundary: B)
+                                                         -> Result<CA2, CaError> {
+        let successor = move |cell: Cell| successors[cell as usize];
+        let rule = get_cyclic_rule_with_successor(nbh, threshold, successor, boundary);
+        CA2::new(cells, rule)
+    }
+
+    pub fn new_ca2_totalistic<B: Into<Topology>>(cells: Vec<Vec<Cell>>,
+                                                 nbh: nb::Neighborhood,
+                                                 states: u8,
+                                                 code: Option<String>,
+                                                 boundary: B)
+                                                 -> Result<CA2, CaError> {
+        let rule = try!(get_ca2_totalistic_rule(nbh, states, code, boundary));
+        // rule looks up cell state in the table, so bigger states would be out of it
+        if cells.iter().any(|row| row.iter().any(|&cell| cell as usize >= states as usize)) {
+            return Err(CaError::InvalidCellState(states as usize));
+        }
+        CA2::new(cells, rule)
+    }
+
+    pub fn new_generations<B: Into<Topology>>(cells: Vec<Vec<Cell>>,
+                                              survive: Vec<Cell>,
+                                              birth: Vec<Cell>,
+                                              states: u32,
+                                              boundary: B)
+                                              -> Result<CA2, CaError> {
+        CA2::new(cells, get_generations_rule(survive, birth, states, boundary))
+    }
+
+    pub fn new_ltl<B: Into<Topology>>(cells: Vec<Vec<Cell>>,
+                                      range: u32,
+                                      birth: (u32, u32),
+                                      survive: (u32, u32),
+                                      boundary: B)
+                                      -> Result<CA2, CaError> {
+        let rule = get_ltl_rule(range, birth.0, birth.1, survive.0, survive.1, boundary);
+        CA2::new(cells, rule)
+    }
+
+    pub fn new_brians_brain<B: Into<Topology>>(cells: Vec<Vec<Cell>>,
+                                               boundary: B)
+                                               -> Result<CA2, CaError> {
+        CA2::new(cells, get_brians_brain_rule(boundary))
+    }
+
+    pub fn new_wireworld<B: Into<Topology>>(cells: Vec<Vec<Cell>>,
+                                            boundary: B)
+                                            -> Result<CA2, CaError> {
+        CA2::new(cells, get_wireworld_rule(boundary))
+    }
+
+    pub fn index(&self, row: usize, col: usize) -> usize {
+        row * self.w + col
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> Cell {
+        self.cells[self.index(row, col)]
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, cell: Cell) {
+        let idx = self.index(row, col);
+        self.cells[idx] = cell;
+        if let Some(ref mut ages) = self.ages {
+            ages[idx] = 0;
+        }
+    }
+
+    // Grows or shrinks the world keeping cells of the overlapping top left part, new cells are
+    // in state 0. Generations in history have the old size, so it's dropped.
+    pub fn resize(&mut self, w: usize, h: usize) {
+        self.cells = resized(&self.cells, self.w, self.h, w, h);
+        if let Some(ref mut ages) = self.ages {
+            *ages = resized(ages, self.w, self.h, w, h);
+        }
+        self.future = self.cells.to_vec();
+        self.w = w;
+        self.h = h;
+        self.history.clear();
+        self.changed = None;
+    }
+
+    pub fn row(&self, row: usize) -> &[Cell] {
+        &self.cells[row * self.w..(row + 1) * self.w]
+    }
+
+    /// States of neighbors of cell (row, col) in the order of `nbh.offsets()`. The world wraps
+    /// around like a torus whatever boundary the rule uses.
+    ///
+    /// ```
+    /// use ca::CA2;
+    /// use ca::nb::{Boundary, Neighborhood};
+    ///
+    /// let cells = vec![vec![1, 1, 0, 0], vec![0, 1, 0, 0], vec![0, 0, 0, 1]];
+    /// let ca = CA2::new_life(cells, vec![2, 3], vec![3], Boundary::Wrap).unwrap();
+    /// let neighbors = ca.neighbors_of(1, 1, &Neighborhood::Moore(1));
+    /// assert_eq!(neighbors.len(), 8);
+    /// assert_eq!(neighbors.iter().filter(|&&state| state == 1).count(), 2);
+    /// ```
+    pub fn neighbors_of(&self, row: usize, col: usize, nbh: &nb::Neighborhood) -> Vec<Cell> {
+        let (cells, w, h) = (&self.cells[..], self.w, self.h);
+        let boundary = Topology::default();
+        match *nbh {
+            nb::Neighborhood::Moore(range) => {
+                nb::MooreNeighborhoodIterator::new(cells, w, h, row, col, range, boundary)
+                    .collect()
+            }
+            nb::Neighborhood::VonNeumann(range) => {
+                nb::VonNeumannNeighborhoodIterator::new(cells, w, h, row, col, range, boundary)
+                    .collect()
+            }
+            nb::Neighborhood::Hex(range) => {
+                nb::HexNeighborhoodIterator::new(cells, w, h, row, col, range, boundary).collect()
+            }
+            nb::Neighborhood::Custom(ref offsets) => {
+                nb::CustomNeighborhoodIterator::new(cells, w, h, row, col, offsets, boundary)
+                    .collect()
+            }
+        }
+    }
+
+    /// Iterates over cells row by row as (row, col, state).
+    ///
+    /// ```
+    /// use ca::CA2;
+    /// use ca::nb::Boundary;
+    ///
+    /// let cells = vec![vec![0, 1, 0], vec![1, 1, 0]];
+    /// let ca = CA2::new_life(cells, vec![2, 3], vec![3], Boundary::Wrap).unwrap();
+    /// let live = ca.iter_cells().filter(|&(_, _, state)| state == 1).count();
+    /// assert_eq!(live, 3);
+    /// ```
+    pub fn iter_cells<'a>(&'a self) -> impl Iterator<Item = (usize, usize, Cell)> + 'a {
+        let w = self.w;
+        self.cells.iter().enumerate().map(move |(idx, &cell)| (idx / w, idx % w, cell))
+    }
+
+    pub fn population(&self) -> Vec<usize> {
+        population(self.cells.iter())
+    }
+
+    pub fn density(&self, state: Cell) -> f64 {
+        density(self.population(), state)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        snapshot::save(path, self.w, self.h, &self.cells)
+    }
+
+    // Current pattern trimmed to its bounding box in RLE format.
+    pub fn to_rle(&self) -> Result<String, String> {
+        gen::to_rle(self.w, self.h, &self.cells)
+    }
+
+    // Same as save(), but the snapshot is gzip-compressed.
+    pub fn save_compressed<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        snapshot::save_compressed(path, self.w, self.h, &self.cells)
+    }
+
+    // Replaces cells with the ones saved by save(), the rule is kept.
+    pub fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<(), String> {
+        let snapshot = try!(snapshot::load(path));
+        self.set_cells(snapshot)
+    }
+
+    pub fn load_compressed<P: AsRef<Path>>(&mut self, path: P) -> Result<(), String> {
+        let snapshot = try!(snapshot::load_compressed(path));
+        self.set_cells(snapshot)
+    }
+
+    fn set_cells(&mut self, (w, h, cells): (usize, usize, Vec<Cell>)) -> Result<(), String> {
+        if w != self.w || h != self.h {
+            return Err(format!("snapshot size must be {}x{}!", self.w, self.h));
+        }
+        self.cells = cells;
+        if let Some(ref mut ages) = self.ages {
+            *ages = vec![0; w * h];
+        }
+        Ok(())
+    }
+
+    // Runs n generations.
+    pub fn tick_n(&mut self, n: usize) {
+        for _ in 0..n {
+            self.tick();
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.tick_changes(None);
+    }
+
+    // Same as tick(), but appends (row, col) of every cell changed by it to changed, so
+    // renderers can redraw only them. Coordinates from several ticks can be accumulated.
+    pub fn tick_tracked(&mut self, changed: &mut Vec<(usize, usize)>) {
+        self.tick_changes(Some(changed));
+    }
+
+    fn tick_changes(&mut self, mut changed_cells: Option<&mut Vec<(usize, usize)>>) {
+        if self.history.capacity > 0 {
+            self.history.push(self.cells.to_vec());
+        }
+        let w = self.w;
+        match self.update {
+            UpdateMode::Synchronous => {
+                self.compute_future();
+                mem::swap(&mut self.cells, &mut self.future);
+                // future holds the previous generation now
+                let mut changed = 0;
+                for idx in 0..self.cells.len() {
+                    if self.cells[idx] != self.future[idx] {
+                        changed += 1;
+                        if let Some(ref mut changed_cells) = changed_cells {
+                            changed_cells.push((idx / w, idx % w));
+                        }
+                    }
+                }
+                self.changed = Some(changed);
+                if self.ages.is_some() {
+                    for idx in 0..self.cells.len() {
+                        let old = self.future[idx];
+                        self.update_age(idx, old);
+                    }
+                }
+                self.swap_rules();
+            }
+            update => {
+                let h = self.h;
+                let idx = self.order.next(update, w * h);
+                let cell = (self.rule)(&self.cells, w, h, idx / w, idx % w);
+                if cell != self.cells[idx] {
+                    self.changed = Some(1);
+                    if let Some(changed_cells) = changed_cells {
+                        changed_cells.push((idx / w, idx % w));
+                    }
+                } else {
+                    self.changed = Some(0);
+                }
+                let old = mem::replace(&mut self.cells[idx], cell);
+                self.update_age(idx, old);
+            }
+        }
+    }
+
+    // Count of cells changed by the last tick.
+    pub fn changed_count(&self) -> usize {
+        self.changed.unwrap_or(0)
+    }
+
+    // Share of cells changed by the last tick.
+    pub fn activity(&self) -> f64 {
+        (self.changed_count() as f64) / (self.cells.len() as f64)
+    }
+
+    // Whether the last tick changed nothing, so the world won't change anymore. It's known
+    // only for a single deterministic rule updating all cells at once: random rules and
+    // orders or the other one of alternating rules may change cells on the next tick.
+    pub fn is_frozen(&self) -> bool {
+        self.deterministic && self.alternate.is_none() &&
+        self.update == UpdateMode::Synchronous && self.changed == Some(0)
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn compute_future(&mut self) {
+        self.compute_future_sequential();
+    }
+
+    #[cfg(feature = "parallel")]
+    fn compute_future(&mut self) {
+        self.compute_future_parallel();
+    }
+
+    // kept with 'parallel' feature to test parallel version against
+    #[cfg_attr(feature = "parallel", allow(dead_code))]
+    fn compute_future_sequential(&mut self) {
+        let (w, h) = (self.w, self.h);
+        for (idx, cell) in self.future.iter_mut().enumerate() {
+            *cell = (self.rule)(&self.cells, w, h, idx / w, idx % w);
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    fn compute_future_parallel(&mut self) {
+        let (w, h) = (self.w, self.h);
+        let cells = &self.cells;
+        let rule = &self.rule;
+        self.future.par_chunks_mut(w).enumerate().for_each(|(row, future_row)| {
+            for (col, cell) in future_row.iter_mut().enumerate() {
+                *cell = rule(cells, w, h, row, col);
+            }
+        });
+    }
+
+    // Restores previous generation, returns false if history is empty.
+    pub fn step_back(&mut self) -> bool {
+        match self.history.pop() {
+            Some(cells) => {
+                self.cells = cells;
+                if self.update == UpdateMode::Synchronous {
+                    self.swap_rules();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Hash of current generation, equal generations have equal hashes.
+    pub fn state_hash(&self) -> u64 {
+        fnv_hash(&self.cells)
+    }
+
+    // Ticks up to max_period times until some generation repeats, returns the period of the
+    // cycle (1 for still life) or None if nothing repeated. Generations are compared by hash.
+    pub fn detect_period(&mut self, max_period: usize) -> Option<usize> {
+        let mut seen: HashMap<u64, usize> = HashMap::new();
+        seen.insert(fnv_hash(&self.cells), 0);
+        for step in 1..(max_period + 1) {
+            self.tick();
+            if let Some(first) = seen.insert(fnv_hash(&self.cells), step) {
+                return Some(step - first);
+            }
+        }
+        None
+    }
+
+    // Ticks up to max_period times looking for groups of live cells (see cell_groups) that
+    // reappear moved, like gliders. Returns (dx, dy, period) of every translation found, dx
+    // and dy are cells to the right and down moved per period. Only shifts by less than period
+    // cells along both axes count, so that distant copies of the same shape aren't taken for
+    // one moving group. Spaceship crossing a wrapped edge is noticed again once it is whole.
+    pub fn find_translating_patterns(&mut self, max_period: usize) -> Vec<(i64, i64, usize)> {
+        let mut found = Vec::new();
+        let mut generations = vec![cell_groups(&self.cells, self.w, self.h)];
+        for _ in 0..max_period {
+            self.tick();
+            let groups = cell_groups(&self.cells, self.w, self.h);
+            for &(top, left, shape) in &groups {
+                // the latest generation with the same shape nearby gives the period
+                for (age, generation) in generations.iter().rev().enumerate() {
+                    let period = age + 1;
+                    let shift = generation.iter()
+                        .filter(|group| group.2 == shape)
+                        .map(|group| (left as i64 - group.1 as i64, top as i64 - group.0 as i64))
+                        .filter(|&(dx, dy)| cmp::max(dx.abs(), dy.abs()) < period as i64)
+                        .min_by_key(|&(dx, dy)| cmp::max(dx.abs(), dy.abs()));
+                    if let Some((dx, dy)) = shift {
+                        if (dx, dy) != (0, 0) && !found.contains(&(dx, dy, period)) {
+                            found.push((dx, dy, period));
+                        }
+                        break;
+                    }
+                }
+            }
+            generations.push(groups);
+        }
+        found
+    }
+}
+
+// Remembers hashes of up to max_period last generations to notice a cycle while CA is run by
+// somebody else, unlike CA2::detect_period() which ticks CA itself.
+pub struct PeriodTracker {
+    max_period: usize,
+    hashes: VecDeque<u64>,
+}
+
+impl PeriodTracker {
+    pub fn new(max_period: usize) -> PeriodTracker {
+        PeriodTracker {
+            max_period: max_period,
+            hashes: VecDeque::with_capacity(max_period),
+        }
+    }
+
+    // Adds hash of the next generation, returns period of the cycle if the generation repeats
+    // one of the remembered ones.
+    pub fn push(&mut self, hash: u64) -> Option<usize> {
+        let period = self.hashes.iter().rev().position(|&h| h == hash).map(|i| i + 1);
+        if self.hashes.len() == self.max_period {
+            self.hashes.pop_front();
+        }
+        if self.max_period > 0 {
+            self.hashes.push_back(hash);
+        }
+        period
+    }
+}
+
+enum Ca2Init {
+    Random(Vec<Cell>, u64), // states, seed
+    Cells(Vec<Vec<Cell>>),
+}
+
+enum Ca2BuilderRule {
+    Life(Vec<Cell>, Vec<Cell>), // survive, birth
+    Custom(Box<CA2Rule>),
+}
+
+// Builds CA2 step by step:
+// Ca2Builder::new().dimensions(w, h).random(vec![0, 1], seed).life(survive, birth).build()
+pub struct Ca2Builder {
+    dimensions: Option<(usize, usize)>,
+    init: Option<Ca2Init>,
+    rule: Option<Ca2BuilderRule>,
+    topology: Topology,
+    history: usize,
+}
+
+impl Ca2Builder {
+    pub fn new() -> Ca2Builder {
+        Ca2Builder {
+            dimensions: None,
+            init: None,
+            rule: None,
+            topology: Topology::default(),
+            history: 0,
+        }
+    }
+
+    pub fn dimensions(mut self, w: usize, h: usize) -> Ca2Builder {
+        self.dimensions = Some((w, h));
+        self
+    }
+
+    // Every cell gets one of states, see gen::random2d().
+    pub fn random(mut self, states: Vec<Cell>, seed: u64) -> Ca2Builder {
+        self.init = Some(Ca2Init::Random(states, seed));
+        self
+    }
+
+    // Initial cells, they set dimensions too.
+    pub fn cells(mut self, cells: Vec<Vec<Cell>>) -> Ca2Builder {
+        self.init = Some(Ca2Init::Cells(cells));
+        self
+    }
+
+    pub fn life(mut self, survive: Vec<Cell>, birth: Vec<Cell>) -> Ca2Builder {
+        self.rule = Some(Ca2BuilderRule::Life(survive, birth));
+        self
+    }
+
+    pub fn rule(mut self, rule: Box<CA2Rule>) -> Ca2Builder {
+        self.rule = Some(Ca2BuilderRule::Custom(rule));
+        self
+    }
+
+    // Boundary or (x, y) boundaries, see nb::Topology.
+    pub fn boundary<B: Into<Topology>>(mut self, boundary: B) -> Ca2Builder {
+        self.topology = boundary.into();
+        self
+    }
+
+    pub fn history(mut self, capacity: usize) -> Ca2Builder {
+        self.history = capacity;
+        self
+    }
+
+    pub fn build(self) -> Result<CA2, CaError> {
+        let cells = match self.init {
+            Some(Ca2Init::Random(states, seed)) => {
+                let (w, h) = try!(self.dimensions.ok_or(CaError::MissingDimensions));
+                if states.is_empty() {
+                    return Err(CaError::NoStates);
+                }
+                gen::random2d_seeded(seed, w, h, states, None, None, None, None)
+            }
+            Some(Ca2Init::Cells(cells)) => cells,
+            None => return Err(CaError::MissingInit),
+        };
+        let ca = match self.rule {
+            Some(Ca2BuilderRule::Life(survive, birth)) => {
+                CA2::new_life(cells, survive, birth, self.topology)
+            }
+            Some(Ca2BuilderRule::Custom(rule)) => CA2::new(cells, rule),
+            None => return Err(CaError::MissingRule),
+        };
+        Ok(try!(ca).with_history(self.history))
+    }
+}
+
+// (layers, width, height, row, col, layer) -> new state of cell (row, col) of layer, every
+// layer is stored like CA2 cells. Layers are computed one after another, cell by cell.
+pub type CoupledRule = FnMut(&[Vec<Cell>; 2], usize, usize, usize, usize, usize) -> Cell;
+
+// Life in both layers of CoupledCA2, but a dead cell can't be born where the other layer has
+// a live one, so layers compete for space.
+pub fn get_coupled_life_rule<B: Into<Topology>>(survive: Vec<Cell>,
+                                                birth: Vec<Cell>,
+                                                boundary: B)
+                                                -> Box<CoupledRule> {
+    let boundary: Topology = boundary.into();
+    Box::new(move |layers, w, h, row, col, layer| {
+        let cells = &layers[layer];
+        let live = nb::MooreNeighborhoodIterator::new(cells, w, h, row, col, 1, boundary)
+            .filter(|&nb| nb == 1)
+            .count() as Cell;
+        let idx = row * w + col;
+        let alive = if cells[idx] == 1 {
+            survive.contains(&live)
+        } else {
+            birth.contains(&live) && layers[1 - layer][idx] == 0
+        };
+        if alive {
+            1
+        } else {
+            0
+        }
+    })
+}
+
+// Two grids of the same size whose rule reads both of them. Both layers of the next generation
+// are computed from the current one.
+pub struct CoupledCA2 {
+    pub w: usize,
+    pub h: usize,
+    // row by row like CA2 cells
+    pub layers: [Vec<Cell>; 2],
+    future: [Vec<Cell>; 2],
+    rule: Box<CoupledRule>,
+}
+
+impl CoupledCA2 {
+    // Layers must be grids of the same size, see CA2::new().
+    pub fn new(layer0: Vec<Vec<Cell>>,
+               layer1: Vec<Vec<Cell>>,
+               rule: Box<CoupledRule>)
+               -> Result<CoupledCA2, CaError> {
+        let (w, h) = try!(grid_size(&layer0));
+        if try!(grid_size(&layer1)) != (w, h) {
+            return Err(CaError::LayerSizeMismatch);
+        }
+        let layers = [layer0.concat(), layer1.concat()];
+        let future = layers.clone();
+        Ok(CoupledCA2 {
+            w: w,
+            h: h,
+            layers: layers,
+            future: future,
+            rule: rule,
+        })
+    }
+
+    pub fn new_coupled_life<B: Into<Topology>>(layer0: Vec<Vec<Cell>>,
+                                               layer1: Vec<Vec<Cell>>,
+                                               survive: Vec<Cell>,
+                                               birth: Vec<Cell>,
+                                               boundary: B)
+                                               -> Result<CoupledCA2, CaError> {
+        let rule = get_coupled_life_rule(survive, birth, boundary);
+        CoupledCA2::new(layer0, layer1, rule)
+    }
+
+    pub fn tick(&mut self) {
+        let (w, h) = (self.w, self.h);
+        for layer in 0..2 {
+            for idx in 0..w * h {
+                self.future[layer][idx] = (self.rule)(&self.layers, w, h, idx / w, idx % w, layer);
+            }
+        }
+        mem::swap(&mut self.layers, &mut self.future);
+    }
+
+    pub fn get(&self, layer: usize, row: usize, col: usize) -> Cell {
+        self.layers[layer][row * self.w + col]
+    }
+
+    pub fn set(&mut self, layer: usize, row: usize, col: usize, cell: Cell) {
+        self.layers[layer][row * self.w + col] = cell;
+    }
+
+    pub fn row(&self, layer: usize, row: usize) -> &[Cell] {
+        &self.layers[layer][row * self.w..(row + 1) * self.w]
+    }
+
+    // Hash of both layers, see CA2::state_hash().
+    pub fn state_hash(&self) -> u64 {
+        fnv_hash(&self.layers[0]) ^ fnv_hash(&self.layers[1]).rotate_left(1)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Heading {
+    Up,
+    Right,
+    Down,
+    Left,
+}
+
+impl Heading {
+    fn turn(self, turn: Turn) -> Heading {
+        let headings = [Heading::Up, Heading::Right, Heading::Down, Heading::Left];
+        let shift = match turn {
+            Turn::None => 0,
+            Turn::Right => 1,
+            Turn::UTurn => 2,
+            Turn::Left => 3,
+        };
+        headings[(self as usize + shift) % 4]
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Turn {
+    None,
+    Right,
+    UTurn,
+    Left,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Ant {
+    pub row: usize,
+    pub col: usize,
+    pub heading: Heading,
+}
+
+impl Ant {
+    pub fn new(row: usize, col: usize) -> Ant {
+        Ant {
+            row: row,
+            col: col,
+            heading: Heading::Up,
+        }
+    }
+}
+
+// Langton's ant generalized to turmites: on a cell in state i an ant turns according to
+// i-th letter of turns string (R - right, L - left, N - no turn, U - u-turn), switches the
+// cell to the next state and moves forward. "RL" is the classic ant. The world is wrapped.
+pub struct LangtonsAnt {
+    pub w: usize,
+    pub h: usize,
+    // row by row as in CA2
+    pub cells: Vec<Cell>,
+    pub ants: Vec<Ant>,
+    turns: Vec<Turn>,
+}
+
+impl LangtonsAnt {
+    pub fn new(cells: Vec<Vec<Cell>>,
+               ants: Vec<Ant>,
+               turns: &str)
+               -> Result<LangtonsAnt, CaError> {
+        let mut parsed_turns: Vec<Turn> = Vec::new();
+        for c in turns.chars() {
+            parsed_turns.push(match c.to_ascii_uppercase() {
+                'N' => Turn::None,
+                'R' => Turn::Right,
+                'U' => Turn::UTurn,
+                'L' => Turn::Left,
+                _ => return Err(CaError::InvalidTurn(c)),
+            });
+        }
+        if parsed_turns.len() < 2 {
+            return Err(CaError::TooFewTurns);
+        }
+        let (w, h) = try!(grid_size(&cells));
+        let cells = cells.concat();
+        if cells.iter().any(|&cell| cell as usize >= parsed_turns.len()) {
+            return Err(CaError::InvalidCellState(parsed_turns.len()));
+        }
+        if ants.iter().any(|ant| ant.row >= h || ant.col >= w) {
+            return Err(CaError::AntOutsideWorld);
+        }
+        Ok(LangtonsAnt {
+            w: w,
+            h: h,
+            cells: cells,
+            ants: ants,
+            turns: parsed_turns,
+        })
+    }
+
+    pub fn states(&self) -> usize {
+        self.turns.len()
+    }
+
+    pub fn row(&self, row: usize) -> &[Cell] {
+        &self.cells[row * self.w..(row + 1) * self.w]
+    }
+
+    // Saves cells only, ants are not part of the snapshot.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        snapshot::save(path, self.w, self.h, &self.cells)
+    }
+
+    // Ants move one after another, so they see cells already changed by previous ones.
+    pub fn tick(&mut self) {
+        let (w, h) = (self.w, self.h);
+        for ant in self.ants.iter_mut() {
+            let idx = ant.row * w + ant.col;
+            let state = self.cells[idx] as usize;
+            ant.heading = ant.heading.turn(self.turns[state]);
+            self.cells[idx] = ((state + 1) % self.turns.len()) as Cell;
+            match ant.heading {
+                Heading::Up => ant.row = (ant.row + h - 1) % h,
+                Heading::Right => ant.col = (ant.col + 1) % w,
+                Heading::Down => ant.row = (ant.row + 1) % h,
+                Heading::Left => ant.col = (ant.col + w - 1) % w,
+            }
+        }
+    }
+}
+
+// Life-like CA on an unbounded plane. Only live cells are stored, so patterns are free to
+// grow and gliders fly away instead of wrapping around.
+pub struct GrowingLife {
+    // (row, col) of live cells, coordinates may become negative
+    pub cells: HashMap<(i64, i64), Cell>,
+    survive: Vec<Cell>,
+    birth: Vec<Cell>,
+}
+
+impl GrowingLife {
+    pub fn new(cells: Vec<Vec<Cell>>, survive: Vec<Cell>, birth: Vec<Cell>) -> GrowingLife {
+        let mut live = HashMap::new();
+        for (row, cells_row) in cells.iter().enumerate() {
+            for (col, &cell) in cells_row.iter().enumerate() {
+                if cell != 0 {
+                    live.insert((row as i64, col as i64), 1);
+                }
+            }
+        }
+        GrowingLife {
+            cells: live,
+            survive: survive,
+            birth: birth,
+        }
+    }
+
+    pub fn get(&self, row: i64, col: i64) -> Cell {
+        *self.cells.get(&(row, col)).unwrap_or(&0)
+    }
+
+    pub fn population(&self) -> usize {
+        self.cells.len()
+    }
+
+    // (min_row, min_col, max_row, max_col) of live cells, None if there are no live cells.
+    pub fn bounding_box(&self) -> Option<(i64, i64, i64, i64)> {
+        let mut coords = self.cells.keys();
+        let &(row, col) = match coords.next() {
+            Some(c) => c,
+            None => return None,
+        };
+        Some(coords.fold((row, col, row, col), |(r1, c1, r2, c2), &(row, col)| {
+            (cmp::min(r1, row), cmp::min(c1, col), cmp::max(r2, row), cmp::max(c2, col))
+        }))
+    }
+
+    // (width, height, cells) of the bounding box.
+    pub fn grid(&self) -> (usize, usize, Vec<Cell>) {
+        match self.bounding_box() {
+            Some((r1, c1, r2, c2)) => {
+                let w = (c2 - c1 + 1) as usize;
+                let h = (r2 - r1 + 1) as usize;
+                let mut cells = vec![0; w * h];
+                for &(row, col) in self.cells.keys() {
+                    cells[((row - r1) as usize) * w + (col - c1) as usize] = 1;
+                }
+                (w, h, cells)
+            }
+            None => (0, 0, Vec::new()),
+        }
+    }
+
+    // Saves the bounding box of live cells.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let (w, h, cells) = self.grid();
+        snapshot::save(path, w, h, &cells)
+    }
+
+    // Only live cells and their neighbors can change, so only they are examined.
+    pub fn tick(&mut self) {
+        let mut counts: HashMap<(i64, i64), Cell> = HashMap::new();
+        for &(row, col) in self.cells.keys() {
+            for drow in -1..2 {
+                for dcol in -1..2 {
+                    if drow != 0 || dcol != 0 {
+                        *counts.entry((row + drow, col + dcol)).or_insert(0) += 1;
+                    }
+                }
+            }
+        }
+        let mut future = HashMap::new();
+        for (&coords, &count) in counts.iter() {
+            let rule = if self.cells.contains_key(&coords) {
+                &self.survive
+            } else {
+                &self.birth
+            };
+            if rule.contains(&count) {
+                future.insert(coords, 1);
+            }
+        }
+        // live cells without live neighbors are not counted above
+        if self.survive.contains(&0) {
+            for &coords in self.cells.keys() {
+                if !counts.contains_key(&coords) {
+                    future.insert(coords, 1);
+                }
+            }
+        }
+        self.cells = future;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gen;
+    use nb::Boundary;
+
+    #[test]
+    fn test_ca1_constant_boundary() {
+        // rule 90: new state is left XOR right
+        let code = Some(String::from("01011010"));
+        let rule = get_ca1_rule(1, 2, code.clone(), Boundary::Wrap).unwrap();
+        let mut ca = CA1::new(vec![1, 0, 0, 0], rule);
+        ca.tick();
+        assert_eq!(ca.cells, vec![0, 1, 0, 1]);
+
+        let rule = get_ca1_rule(1, 2, code.clone(), Boundary::Constant(0)).unwrap();
+        let mut ca = CA1::new(vec![1, 0, 0, 0], rule);
+        ca.tick();
+        assert_eq!(ca.cells, vec![0, 1, 0, 0]);
+
+        let rule = get_ca1_rule(1, 2, code, Boundary::Reflect).unwrap();
+        let mut ca = CA1::new(vec![1, 0, 0, 0], rule);
+        ca.tick();
+        assert_eq!(ca.cells, vec![0, 1, 0, 0]);
+    }
+
+    #[test]
+    fn test_ca1_totalistic_rule() {
+        // new state is 1 iff exactly one cell in the neighborhood is alive
+        let code = Some(String::from("0010"));
+        let rule = get_ca1_totalistic_rule(1, 2, code, Boundary::Constant(0)).unwrap();
+        let mut ca = CA1::new(vec![0, 0, 1, 0, 0], rule);
+        ca.tick();
+        assert_eq!(ca.cells, vec![0, 1, 1, 1, 0]);
+        ca.tick();
+        assert_eq!(ca.cells, vec![1, 0, 0, 0, 1]);
+
+        assert!(get_ca1_totalistic_rule(1, 3, Some(String::from("0010")), Boundary::Wrap)
+            .is_err());
+    }
+
+    #[test]
+    fn test_ca1_outer_totalistic_rule() {
+        // rule 150 (center XOR left XOR right) as outer totalistic code:
+        // index = center * 3 + outer_sum
+        let code = Some(String::from("101010"));
+        let rule = get_ca1_outer_totalistic_rule(1, 2, code, Boundary::Wrap).unwrap();
+        let mut ca = CA1::new(vec![0, 0, 1, 1, 0], rule);
+        ca.tick();
+        assert_eq!(ca.cells, vec![0, 1, 0, 0, 1]);
+
+        let code = Some(String::from("10101"));
+        assert!(get_ca1_outer_totalistic_rule(1, 2, code, Boundary::Wrap).is_err());
+    }
+
+    #[test]
+    fn test_stateful_rule() {
+        let calls = Arc::new(Mutex::new(0));
+        let counter = calls.clone();
+        let rule = Box::new(move |_: &Vec<Cell>, _, _| {
+            let mut calls = counter.lock().unwrap();
+            *calls += 1;
+            *calls % 2
+        });
+        let mut ca = CA1::new(vec![0; 4], rule);
+        ca.tick();
+        assert_eq!(*calls.lock().unwrap(), 4);
+        assert_eq!(ca.cells.iter().filter(|&&cell| cell == 1).count(), 2);
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    #[test]
+    fn test_fnmut_rule() {
+        let mut calls = 0;
+        let rule = Box::new(move |_: &Vec<Cell>, _, _| {
+            calls += 1;
+            calls % 2
+        });
+        let mut ca = CA1::new(vec![0; 4], rule);
+        ca.tick();
+        // cells are visited in order of their indexes
+        assert_eq!(ca.cells, vec![1, 0, 1, 0]);
+    }
+
+    #[test]
+    fn test_ca1_asymmetric_rule() {
+        // every cell takes the state of its right neighbor, so the pattern moves left
+        let mut ca = CA1::new_ca1_asymmetric(vec![0, 0, 1, 1, 0],
+                                             (0, 1),
+                                             2,
+                                             Some(String::from("1010")),
+                                             Boundary::Wrap)
+            .unwrap();
+        ca.tick();
+        assert_eq!(ca.cells, vec![0, 1, 1, 0, 0]);
+        assert_eq!(get_ca1_asymmetric_rule(0, 1, 2, Some(String::from("10")), Boundary::Wrap)
+                       .err(),
+                   Some(CaError::CodeLengthMismatch));
+        assert!(get_ca1_asymmetric_rule(0, 0, 2, None, Boundary::Wrap).is_err());
+    }
+
+    #[test]
+    fn test_shared_rule() {
+        let rule = get_shared_elementary_rule(90);
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let rule = rule.clone();
+                std::thread::spawn(move || {
+                    let mut cells = vec![0; 8];
+                    cells[i] = 1;
+                    let mut ca = CA1::new_shared(cells, rule);
+                    ca.tick();
+                    ca.cells
+                })
+            })
+            .collect();
+        for (i, handle) in handles.into_iter().enumerate() {
+            let mut expected = CA1::new_elementary(vec![0; 8], 90);
+            expected.cells[i] = 1;
+            expected.tick();
+            assert_eq!(handle.join().unwrap(), expected.cells);
+        }
+    }
+
+    #[test]
+    fn test_ca1_restart() {
+        let mut ca = CA1::new_elementary(vec![0, 0, 1, 0, 0], 90).with_history(2);
+        ca.tick();
+        ca.restart(vec![0, 0, 1, 0, 0], get_elementary_rule(4));
+        assert!(!ca.step_back());
+        ca.tick();
+        assert_eq!(ca.cells, vec![0, 0, 1, 0, 0]);
+    }
+
+    #[test]
+    fn test_reversible() {
+        let cells = gen::points1d(8, vec![3, 4]);
+        let previous = gen::points1d(8, vec![0]);
+        let mut ca = CA1::new_reversible(cells.clone(), previous.clone(), 90).unwrap();
+        ca.tick();
+        assert_eq!(ca.previous(), Some(&cells[..]));
+        assert_eq!(ca.cells, vec![1, 0, 1, 1, 1, 1, 0, 0]);
+        for _ in 0..20 {
+            ca.tick();
+        }
+        for _ in 0..21 {
+            assert!(ca.reverse_tick());
+        }
+        assert_eq!(ca.cells, cells);
+        assert_eq!(ca.previous(), Some(&previous[..]));
+
+        assert!(!CA1::new_elementary(cells.clone(), 90).reverse_tick());
+        assert_eq!(CA1::new_reversible(cells, vec![0; 7], 90).err(),
+                   Some(CaError::PreviousSizeMismatch));
+    }
+
+    #[test]
+    fn test_population() {
+        let cells = gen::points2d(4, 2, vec![(0, 0), (1, 1), (3, 1)]);
+        let ca = CA2::new_life(cells, vec![2, 3], vec![3], Boundary::Wrap).unwrap();
+        assert_eq!(ca.population(), vec![5, 3]);
+        assert_eq!(ca.density(1), 0.375);
+        assert_eq!(ca.density(2), 0.0);
+
+        let ca = CA1::new_elementary(gen::points1d(4, vec![1]), 30);
+        assert_eq!(ca.population(), vec![3, 1]);
+        assert_eq!(ca.density(0), 0.75);
+    }
+
+    #[test]
+    fn test_life_for_state() {
+        // blinker of 2s next to a cell in state 3 that doesn't take part in life
+        let cells = vec![vec![0, 0, 0, 0, 0],
+                         vec![0, 0, 0, 0, 0],
+                         vec![0, 2, 2, 2, 3],
+                         vec![0, 0, 0, 0, 0],
+                         vec![0, 0, 0, 0, 0]];
+        let mut ca = CA2::new_life_for_state(cells, vec![2, 3], vec![3], 2, Boundary::Wrap)
+            .unwrap();
+        ca.tick();
+        assert_eq!(ca.cells,
+                   vec![vec![0, 0, 0, 0, 0],
+                        vec![0, 0, 2, 0, 0],
+                        vec![0, 0, 2, 0, 3],
+                        vec![0, 0, 2, 0, 0],
+                        vec![0, 0, 0, 0, 0]]
+                       .concat());
+    }
+
+    #[test]
+    fn test_probabilistic_life() {
+        let cells = gen::points2d(5, 5, vec![(1, 2), (2, 2), (3, 2)]);
+        let mut life = CA2::new_life(cells.clone(), vec![2, 3], vec![3], Boundary::Wrap).unwrap();
+        let rule = get_probabilistic_life_rule(vec![2, 3],
+                                               vec![3],
+                                               1.0,
+                                               1.0,
+                                               gen::seeded_rng(1),
+                                               Boundary::Wrap);
+        let mut ca = CA2::new(cells.clone(), rule).unwrap();
+        life.tick();
+        ca.tick();
+        assert_eq!(ca.cells, life.cells);
+
+        // nothing is born, only the center of the blinker survives
+        let rule = get_probabilistic_life_rule(vec![2, 3],
+                                               vec![3],
+                                               1.0,
+                                               0.0,
+                                               gen::seeded_rng(1),
+                                               Boundary::Wrap);
+        let mut ca = CA2::new(cells, rule).unwrap();
+        ca.tick();
+        assert_eq!(ca.cells, gen::points2d(5, 5, vec![(2, 2)]).concat());
+    }
+
+    #[test]
+    fn test_weighted_rule() {
+        // Life as weighted rule
+        let cells = gen::points2d(5, 5, vec![(1, 2), (2, 2), (3, 2)]);
+        let mut life = CA2::new_life(cells.clone(), vec![2, 3], vec![3], Boundary::Wrap).unwrap();
+        let rule = get_weighted_rule(vec![1; 8],
+                                     vec![3],
+                                     vec![2, 3],
+                                     nb::Neighborhood::Moore(1),
+                                     Boundary::Wrap)
+            .unwrap();
+        let mut ca = CA2::new(cells, rule).unwrap();
+        for _ in 0..3 {
+            life.tick();
+            ca.tick();
+            assert_eq!(ca.cells, life.cells);
+        }
+
+        // only the cell above counts
+        let mut weights = vec![0; 8];
+        weights[1] = 1;
+        let rule = get_weighted_rule(weights,
+                                     vec![1],
+                                     vec![],
+                                     nb::Neighborhood::Moore(1),
+                                     Boundary::Skip)
+            .unwrap();
+        let mut ca = CA2::new(gen::points2d(3, 3, vec![(1, 0)]), rule).unwrap();
+        ca.tick();
+        assert_eq!(ca.cells, gen::points2d(3, 3, vec![(1, 1)]).concat());
+
+        let err = get_weighted_rule(vec![1; 4],
+                                    vec![],
+                                    vec![],
+                                    nb::Neighborhood::Moore(1),
+                                    Boundary::Wrap)
+            .err();
+        assert_eq!(err, Some(CaError::WeightsLengthMismatch(8)));
+    }
+
+    #[test]
+    fn test_ca2_totalistic_rule() {
+        // Life: dead cell with 3 live neighbors is born, live cell with 2 or 3 survives
+        let code = Some(String::from("000001100000001000"));
+        let blinker = gen::points2d(5, 5, vec![(1, 2), (2, 2), (3, 2)]);
+        let mut ca = CA2::new_ca2_totalistic(blinker.clone(),
+                                             nb::Neighborhood::Moore(1),
+                                             2,
+                                             code,
+                                             Boundary::Wrap)
+            .unwrap();
+        let mut life = CA2::new_life(blinker, vec![2, 3], vec![3], Boundary::Wrap).unwrap();
+        for _ in 0..2 {
+            ca.tick();
+            life.tick();
+            assert_eq!(ca.iter_cells().collect::<Vec<_>>(),
+                       life.iter_cells().collect::<Vec<_>>());
+        }
+
+        let code = Some(String::from("0101"));
+        assert!(get_ca2_totalistic_rule(nb::Neighborhood::Moore(1), 2, code, Boundary::Wrap)
+            .is_err());
+        assert!(get_ca2_totalistic_rule(nb::Neighborhood::VonNeumann(1), 3, None, Boundary::Wrap)
+            .is_ok());
+        let err = CA2::new_ca2_totalistic(vec![vec![0, 3]],
+                                          nb::Neighborhood::Moore(1),
+                                          3,
+                                          None,
+                                          Boundary::Wrap)
+            .err();
+        assert_eq!(err, Some(CaError::InvalidCellState(3)));
+    }
+
+    #[test]
+    fn test_ltl_rule() {
+        // range 1 with B3/S23 intervals is Life
+        let cells = gen::points2d(5, 5, vec![(1, 2), (2, 2), (3, 2), (3, 1)]);
+        let mut life = CA2::new_life(cells.clone(), vec![2, 3], vec![3], Boundary::Wrap).unwrap();
+        let mut ca = CA2::new_ltl(cells, 1, (3, 3), (2, 3), Boundary::Wrap).unwrap();
+        for _ in 0..4 {
+            life.tick();
+            ca.tick();
+            assert_eq!(ca.cells, life.cells);
+        }
+
+        // born in 4x4 square seeing both live cells, they die
+        let cells = gen::points2d(7, 7, vec![(0, 0), (2, 2)]);
+        let mut ca = CA2::new_ltl(cells, 3, (2, 2), (0, 0), Boundary::Constant(0)).unwrap();
+        ca.tick();
+        assert_eq!(ca.population()[1], 14);
+    }
+
+    #[test]
+    fn test_brians_brain() {
+        let cells = gen::points2d(4, 4, vec![(1, 1), (2, 1)]);
+        let mut ca = CA2::new_brians_brain(cells, Boundary::Constant(0)).unwrap();
+        ca.tick();
+        assert_eq!(ca.cells,
+                   vec![0, 1, 1, 0, 0, 2, 2, 0, 0, 1, 1, 0, 0, 0, 0, 0]);
+        ca.tick();
+        assert_eq!(ca.population(), vec![8, 4, 4]);
+    }
+
+    #[test]
+    fn test_generations() {
+        // Brian's Brain is generations rule /2/3
+        let cells = gen::random2d_seeded(3, 20, 20, vec![0, 1, 2], None, None, None, None);
+        let mut bb = CA2::new_brians_brain(cells.clone(), Boundary::Wrap).unwrap();
+        let mut generations = CA2::new_generations(cells, vec![], vec![2], 3, Boundary::Wrap)
+            .unwrap();
+        for _ in 0..5 {
+            bb.tick();
+            generations.tick();
+            assert_eq!(bb.cells, generations.cells);
+        }
+    }
+
+    #[test]
+    fn test_wireworld() {
+        // electron running along a wire: tail, head, conductor, conductor
+        let cells = vec![vec![2, 1, 3, 3]];
+        let mut ca = CA2::new_wireworld(cells, Boundary::Constant(0)).unwrap();
+        ca.tick();
+        assert_eq!(ca.cells, vec![3, 2, 1, 3]);
+        ca.tick();
+        assert_eq!(ca.cells, vec![3, 3, 2, 1]);
+    }
+
+    #[test]
+    fn test_langtons_ant() {
+        let cells = vec![vec![0; 3]; 3];
+        let mut ant = LangtonsAnt::new(cells, vec![Ant::new(1, 1)], "RL").unwrap();
+        ant.tick();
+        assert_eq!(ant.ants, vec![Ant { row: 1, col: 2, heading: Heading::Right }]);
+        ant.tick();
+        ant.tick();
+        ant.tick();
+        assert_eq!(ant.ants, vec![Ant { row: 1, col: 1, heading: Heading::Up }]);
+        assert_eq!(ant.cells, vec![0, 0, 0, 0, 1, 1, 0, 1, 1]);
+        // back on black cell, turns left
+        ant.tick();
+        assert_eq!(ant.ants, vec![Ant { row: 1, col: 0, heading: Heading::Left }]);
+        assert_eq!(ant.cells[4], 0);
+
+        assert!(LangtonsAnt::new(vec![vec![0; 3]; 3], vec![], "RX").is_err());
+        assert!(LangtonsAnt::new(vec![vec![2; 3]; 3], vec![], "RL").is_err());
+        assert_eq!(LangtonsAnt::new(vec![], vec![], "RL").err(), Some(CaError::EmptyGrid));
+        assert_eq!(LangtonsAnt::new(vec![vec![0; 3], vec![0]], vec![], "RL").err(),
+                   Some(CaError::JaggedGrid(1)));
+    }
+
+    #[test]
+    fn test_async_update() {
+        // every dead cell with a live neighbor is born, live cells survive
+        let cells = gen::points2d(5, 5, vec![(2, 2)]);
+        let mut ca = CA2::new_life(cells,
+                                   (0..9).collect(),
+                                   (1..9).collect(),
+                                   Boundary::Wrap)
+            .unwrap()
+            .with_update(UpdateMode::AsyncRandom);
+        for _ in 0..20 {
+            let population = ca.population()[1];
+            ca.tick();
+            assert!(ca.population()[1] - population <= 1);
+        }
+
+        // the same rng picks the same cells
+        let run = |update| {
+            let cells = gen::random2d_seeded(2, 6, 6, vec![0, 1], None, None, None, None);
+            let mut ca = CA2::new_life(cells, vec![2, 3], vec![3], Boundary::Wrap)
+                .unwrap()
+                .with_update(update)
+                .with_update_rng(gen::seeded_rng(9));
+            for _ in 0..50 {
+                ca.tick();
+            }
+            ca.cells
+        };
+        assert_eq!(run(UpdateMode::AsyncRandom), run(UpdateMode::AsyncRandom));
+        assert_eq!(run(UpdateMode::AsyncShuffle), run(UpdateMode::AsyncShuffle));
+    }
+
+    #[test]
+    fn test_cell_order() {
+        let mut order = CellOrder::new(gen::seeded_rng(1));
+        let sweep: Vec<usize> = (0..5).map(|_| order.next(UpdateMode::AsyncSweep, 3)).collect();
+        assert_eq!(sweep, vec![0, 1, 2, 0, 1]);
+        let mut order = CellOrder::new(gen::seeded_rng(1));
+        for _ in 0..3 {
+            let mut shuffled: Vec<usize> =
+                (0..10).map(|_| order.next(UpdateMode::AsyncShuffle, 10)).collect();
+            shuffled.sort();
+            assert_eq!(shuffled, (0..10).collect::<Vec<usize>>());
+        }
+    }
+
+    #[test]
+    fn test_growing_life() {
+        // glider moving down and right
+        let cells = gen::points2d(3, 3, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        let mut ca = GrowingLife::new(cells, vec![2, 3], vec![3]);
+        assert_eq!(ca.bounding_box(), Some((0, 0, 2, 2)));
+        for _ in 0..40 {
+            ca.tick();
+        }
+        assert_eq!(ca.population(), 5);
+        assert_eq!(ca.bounding_box(), Some((10, 10, 12, 12)));
+        let (w, h, cells) = ca.grid();
+        assert_eq!((w, h), (3, 3));
+        assert_eq!(cells, vec![0, 1, 0, 0, 0, 1, 1, 1, 1]);
+
+        let mut ca = GrowingLife::new(vec![vec![1]], vec![0], vec![]);
+        ca.tick();
+        assert_eq!(ca.get(0, 0), 1);
+    }
+
+    #[test]
+    fn test_ca2_builder() {
+        let ca = Ca2Builder::new()
+            .dimensions(8, 6)
+            .random(vec![0, 1], 5)
+            .life(vec![2, 3], vec![3])
+            .build()
+            .unwrap();
+        assert_eq!((ca.w, ca.h), (8, 6));
+        let cells = gen::random2d_seeded(5, 8, 6, vec![0, 1], None, None, None, None);
+        assert_eq!(ca.cells, cells.concat());
+
+        let ca = Ca2Builder::new()
+            .cells(vec![vec![1; 3]; 2])
+            .rule(get_brians_brain_rule(Boundary::Wrap))
+            .build()
+            .unwrap();
+        assert_eq!(ca.w, 3);
+        assert_eq!(Ca2Builder::new().random(vec![0, 1], 5).life(vec![], vec![]).build().err(),
+                   Some(CaError::MissingDimensions));
+        assert_eq!(Ca2Builder::new().dimensions(3, 3).life(vec![], vec![]).build().err(),
+                   Some(CaError::MissingInit));
+        assert_eq!(Ca2Builder::new().dimensions(3, 3).random(vec![0, 1], 5).build().err(),
+                   Some(CaError::MissingRule));
+    }
+
+    #[test]
+    fn test_detect_period() {
+        let life = |cells| CA2::new_life(cells, vec![2, 3], vec![3], Boundary::Wrap).unwrap();
+        let block = gen::points2d(6, 6, vec![(2, 2), (3, 2), (2, 3), (3, 3)]);
+        assert_eq!(life(block).detect_period(10), Some(1));
+        let blinker = gen::points2d(5, 5, vec![(1, 2), (2, 2), (3, 2)]);
+        assert_eq!(life(blinker).detect_period(10), Some(2));
+        // glider needs 4 * 8 generations to come back on 8x8 torus
+        let glider = gen::points2d(8, 8, vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]);
+        assert_eq!(life(glider.clone()).detect_period(10), None);
+        assert_eq!(life(glider).detect_period(40), Some(32));
+    }
+
+    #[test]
+    fn test_find_translating_patterns() {
+        let mut cells = vec![vec![0; 24]; 24];
+        // glider moving down and right, lwss moving left, blinker and block stay in place
+        gen::stamp(&mut cells, &patterns::by_name("glider").unwrap(), 1, 1);
+        gen::stamp(&mut cells, &patterns::by_name("lwss").unwrap(), 14, 2);
+        gen::stamp(&mut cells, &patterns::by_name("blinker").unwrap(), 2, 16);
+        gen::stamp(&mut cells, &patterns::by_name("block").unwrap(), 20, 20);
+        let mut ca = CA2::new_life(cells, vec![2, 3], vec![3], Boundary::Wrap).unwrap();
+        let mut found = ca.find_translating_patterns(8);
+        found.sort();
+        assert_eq!(found, vec![(-2, 0, 4), (1, 1, 4)]);
+
+        let blinker = gen::points2d(5, 5, vec![(1, 2), (2, 2), (3, 2)]);
+        let mut ca = CA2::new_life(blinker, vec![2, 3], vec![3], Boundary::Wrap).unwrap();
+        assert!(ca.find_translating_patterns(8).is_empty());
+    }
+
+    #[test]
+    fn test_coupled_ca2() {
+        // every layer takes the other one's previous state
+        let rule = Box::new(|layers: &[Vec<Cell>; 2], w, _, row, col, layer: usize| -> Cell {
+            layers[1 - layer][row * w + col]
+        });
+        let mut ca = CoupledCA2::new(vec![vec![1, 0]], vec![vec![0, 2]], rule).unwrap();
+        ca.tick();
+        assert_eq!(ca.row(0, 0), &[0, 2]);
+        assert_eq!(ca.row(1, 0), &[1, 0]);
+
+        // blinker in layer 0 can't grow into the cell occupied by layer 1
+        let blinker = gen::points2d(5, 5, vec![(1, 2), (2, 2), (3, 2)]);
+        let blocker = gen::points2d(5, 5, vec![(2, 1)]);
+        let mut ca =
+            CoupledCA2::new_coupled_life(blinker, blocker, vec![2, 3], vec![3], Boundary::Wrap)
+                .unwrap();
+        ca.tick();
+        assert_eq!(ca.row(0, 1), &[0, 0, 0, 0, 0]);
+        assert_eq!(ca.row(0, 2), &[0, 0, 1, 0, 0]);
+        assert_eq!(ca.row(0, 3), &[0, 0, 1, 0, 0]);
+        assert_eq!(ca.get(1, 1, 2), 0);
+
+        let layers = |layer1| {
+            let rule = get_coupled_life_rule(vec![2, 3], vec![3], Boundary::Wrap);
+            CoupledCA2::new(vec![vec![0; 2]; 2], layer1, rule).err()
+        };
+        assert_eq!(layers(vec![]), Some(CaError::EmptyGrid));
+        assert_eq!(layers(vec![vec![0; 2], vec![0]]), Some(CaError::JaggedGrid(1)));
+        assert_eq!(layers(vec![vec![0; 3]; 2]), Some(CaError::LayerSizeMismatch));
+    }
+
+    #[test]
+    fn test_period_tracker() {
+        let blinker = gen::points2d(5, 5, vec![(1, 2), (2, 2), (3, 2)]);
+        let mut ca = CA2::new_life(blinker, vec![2, 3], vec![3], Boundary::Wrap).unwrap();
+        let mut tracker = PeriodTracker::new(4);
+        assert_eq!(tracker.push(ca.state_hash()), None);
+        ca.tick();
+        assert_eq!(tracker.push(ca.state_hash()), None);
+        ca.tick();
+        assert_eq!(tracker.push(ca.state_hash()), Some(2));
+
+        let mut tracker = PeriodTracker::new(1);
+        tracker.push(1);
+        tracker.push(2);
+        assert_eq!(tracker.push(1), None);
+    }
+
+    #[test]
+    fn test_cyclic_many_states() {
+        let states: Cell = 100;
+        let cells = gen::random2d(8, 8, (0..states).collect(), None, None, None, None);
+        assert!(cells.iter().all(|row| row.iter().all(|&cell| cell < states)));
+
+        // last state is followed by the first one
+        let cells = vec![vec![states - 2, 0, 0], vec![0, states - 1, 0], vec![0, 0, 0]];
+        let nbh = nb::Neighborhood::Moore(1);
+        let mut ca = CA2::new_cyclic(cells, nbh, 1, states as u32, Boundary::Wrap).unwrap();
+        ca.tick();
+        let cells: Vec<Cell> = ca.iter_cells().map(|(_, _, cell)| cell).collect();
+        assert_eq!(cells, vec![states - 1, 0, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_cyclic_successors() {
+        // 0 -> 2 -> 1 -> 0
+        let cells = vec![vec![0, 2, 1], vec![0, 0, 0], vec![0, 0, 0]];
+        let mut ca = CA2::new_cyclic_with_successors(cells,
+                                                     nb::Neighborhood::VonNeumann(1),
+                                                     1,
+                                                     vec![2, 0, 1],
+                                                     Boundary::Wrap).unwrap();
+        ca.tick();
+        assert_eq!(ca.cells, vec![2, 1, 0, 0, 2, 0, 0, 2, 0]);
+    }
+
+    #[test]
+    fn test_cyclic_reflect() {
+        // neighborhood wider than the world reads only mirrored cells inside of it
+        let cells = gen::random2d_seeded(4, 3, 2, vec![0, 1, 2], None, None, None, None);
+        let mut ca = CA2::new_cyclic(cells, nb::Neighborhood::Moore(5), 1, 3, Boundary::Reflect)
+            .unwrap();
+        ca.tick_n(5);
+
+        // mirror symmetric world stays symmetric
+        let row = vec![0, 1, 2, 3, 3, 2, 1, 0];
+        let mut cells = vec![row.clone(), row.clone(), row.iter().map(|&c| (c + 1) % 4).collect()];
+        cells.push(row);
+        let mut ca = CA2::new_cyclic(cells, nb::Neighborhood::Moore(1), 2, 4, Boundary::Reflect)
+            .unwrap();
+        for _ in 0..10 {
+            ca.tick();
+            for row in 0..ca.h {
+                let cells = ca.row(row);
+                assert!(cells.iter().eq(cells.iter().rev()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_ages() {
+        let blinker = gen::points2d(5, 5, vec![(1, 2), (2, 2), (3, 2)]);
+        let mut ca = CA2::new_life(blinker, vec![2, 3], vec![3], Boundary::Wrap)
+            .unwrap()
+            .with_ages();
+        ca.tick();
+        ca.tick();
+        // the center of blinker never dies, its ends are born again every tick
+        assert_eq!(ca.age(2, 2), Some(2));
+        assert_eq!(ca.age(2, 1), Some(0));
+        assert_eq!(ca.age(0, 0), Some(0));
+        assert_eq!(CA2::new_life(vec![vec![0]], vec![], vec![], Boundary::Wrap).unwrap().age(0, 0),
+                   None);
+    }
+
+    #[test]
+    fn test_tick_tracked() {
+        let blinker = gen::points2d(5, 5, vec![(1, 2), (2, 2), (3, 2)]);
+        let mut ca = CA2::new_life(blinker, vec![2, 3], vec![3], Boundary::Wrap).unwrap();
+        let mut changed = Vec::new();
+        ca.tick_tracked(&mut changed);
+        assert_eq!(changed, vec![(1, 2), (2, 1), (2, 3), (3, 2)]);
+        ca.tick_tracked(&mut changed);
+        assert_eq!(changed.len(), 8);
+    }
+
+    #[test]
+    fn test_activity() {
+        let blinker = gen::points2d(5, 5, vec![(1, 2), (2, 2), (3, 2)]);
+        let mut ca = CA2::new_life(blinker, vec![2, 3], vec![3], Boundary::Wrap).unwrap();
+        assert!(!ca.is_frozen());
+        ca.tick();
+        assert_eq!(ca.changed_count(), 4);
+        assert_eq!(ca.activity(), 0.16);
+        assert!(!ca.is_frozen());
+
+        let block = gen::points2d(4, 4, vec![(1, 1), (2, 1), (1, 2), (2, 2)]);
+        let mut ca = CA2::new_life(block.clone(), vec![2, 3], vec![3], Boundary::Wrap).unwrap();
+        ca.tick();
+        assert_eq!(ca.changed_count(), 0);
+        assert!(ca.is_frozen());
+
+        // the block may still die on a later tick of a random rule
+        let rule = get_probabilistic_life_rule(vec![2, 3],
+                                               vec![3],
+                                               1.0,
+                                               1.0,
+                                               gen::seeded_rng(1),
+                                               Boundary::Wrap);
+        let mut ca = CA2::new(block, rule).unwrap().with_nondeterministic_rule();
+        ca.tick();
+        assert_eq!(ca.changed_count(), 0);
+        assert!(!ca.is_frozen());
+    }
+
+    #[test]
+    fn test_tick_n() {
+        let cells = gen::random2d_seeded(1, 20, 20, vec![0, 1], None, None, None, None);
+        let mut ca = CA2::new_life(cells.clone(), vec![2, 3], vec![3], Boundary::Wrap).unwrap();
+        let mut ca_n = CA2::new_life(cells, vec![2, 3], vec![3], Boundary::Wrap).unwrap();
+        for _ in 0..7 {
+            ca.tick();
+        }
+        ca_n.tick_n(7);
+        assert_eq!(ca.cells, ca_n.cells);
+
+        let mut ca = CA1::new_elementary(gen::points1d(9, vec![4]), 90);
+        ca.tick_n(2);
+        assert_eq!(ca.cells, gen::points1d(9, vec![2, 6]));
+    }
+
+    #[test]
+    fn test_step_back() {
+        let cells = gen::points2d(5, 5, vec![(1, 2), (2, 2), (3, 2)]);
+        let mut ca = CA2::new_life(cells.clone(), vec![2, 3], vec![3], Boundary::Wrap)
+            .unwrap()
+            .with_history(1);
+        ca.tick();
+        ca.tick();
+        assert!(ca.step_back());
+        assert!(ca.cells != cells.concat());
+        assert!(!ca.step_back());
+        ca.tick();
+        assert_eq!(ca.cells, cells.concat());
+
+        let mut ca = CA1::new_elementary(gen::points1d(5, vec![2]), 30);
+        ca.tick();
+        assert!(!ca.step_back());
+    }
+
+    #[test]
+    fn test_count_wrapped_moore() {
+        let cells = gen::random2d_seeded(3, 7, 5, vec![0, 1, 2], None, None, None, None).concat();
+        for row in 0..5 {
+            for col in 0..7 {
+                let expected = nb::MooreNeighborhoodIterator::new(&cells, 7, 5, row, col, 1,
+                                                                  Boundary::Wrap)
+                    .filter(|&nb| nb == 2)
+                    .count() as Cell;
+                assert_eq!(count_wrapped_moore(&cells, 7, 5, row, col, 2), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_ca2_invalid_grid() {
+        let life = |cells| CA2::new_life(cells, vec![2, 3], vec![3], Boundary::Wrap).err();
+        assert_eq!(life(vec![]), Some(CaError::EmptyGrid));
+        assert_eq!(life(vec![vec![], vec![]]), Some(CaError::EmptyGrid));
+        assert_eq!(life(vec![vec![0, 1], vec![1, 0], vec![1]]), Some(CaError::JaggedGrid(2)));
+        assert_eq!(life(vec![vec![0, 1], vec![1, 0]]), None);
+    }
+
+    #[test]
+    fn test_b0_life() {
+        // B0/S: background turns on and off, cells with neighbors stay dead in even generations
+        let empty = vec![vec![0; 4]; 4];
+        let mut ca = CA2::new_life(empty.clone(), vec![], vec![0], Boundary::Constant(0))
+            .unwrap();
+        ca.tick_n(3);
+        assert_eq!(ca.cells, empty.concat());
+
+        let cells = gen::random2d_seeded(5, 8, 6, vec![0, 1], None, None, None, None);
+        let rule = get_life_rule(vec![2, 3], vec![0, 3], Boundary::Wrap);
+        let mut literal = CA2::new(cells.clone(), rule).unwrap();
+        let mut ca = CA2::new_life(cells, vec![2, 3], vec![0, 3], Boundary::Wrap)
+            .unwrap()
+            .with_history(1);
+        for _ in 0..2 {
+            literal.tick();
+            ca.tick();
+            let inverted: Vec<Cell> = literal.cells.iter().map(|&c| 1 - c).collect();
+            assert_eq!(ca.cells, inverted);
+            literal.tick();
+            ca.tick();
+            assert_eq!(ca.cells, literal.cells);
+        }
+        assert!(ca.step_back());
+        ca.tick();
+        assert_eq!(ca.cells, literal.cells);
+
+        // counts above 8 can't happen and are ignored
+        let cells = gen::random2d_seeded(5, 8, 6, vec![0, 1], None, None, None, None);
+        let mut ca = CA2::new_life(cells.clone(), vec![9], vec![0], Boundary::Wrap).unwrap();
+        let mut plain = CA2::new_life(cells, vec![], vec![0], Boundary::Wrap).unwrap();
+        ca.tick_n(2);
+        plain.tick_n(2);
+        assert_eq!(ca.cells, plain.cells);
+    }
+
+    #[test]
+    fn test_resize() {
+        let cells = vec![vec![1, 1, 0], vec![1, 1, 0]];
+        let mut ca = CA2::new_life(cells, vec![2, 3], vec![3], Boundary::Wrap)
+            .unwrap()
+            .with_ages();
+        ca.resize(2, 3);
+        assert_eq!((ca.w, ca.h), (2, 3));
+        assert_eq!(ca.cells, vec![1, 1, 1, 1, 0, 0]);
+        assert_eq!(ca.age(2, 1), Some(0));
+        ca.resize(4, 1);
+        assert_eq!(ca.cells, vec![1, 1, 0, 0]);
+        ca.tick();
+        assert_eq!(ca.cells.len(), 4);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_tick() {
+        let cells = gen::random2d_seeded(7, 200, 150, vec![0, 1, 2, 3], None, None, None, None);
+        let nbh = nb::Neighborhood::Moore(1);
+        let mut seq = CA2::new_cyclic(cells.clone(), nbh, 2, 4, Boundary::Wrap).unwrap();
+        let nbh = nb::Neighborhood::Moore(1);
+        let mut par = CA2::new_cyclic(cells, nbh, 2, 4, Boundary::Wrap).unwrap();
+        for _ in 0..10 {
+            seq.compute_future_sequential();
+            mem::swap(&mut seq.cells, &mut seq.future);
+            par.compute_future_parallel();
+            mem::swap(&mut par.cells, &mut par.future);
+            assert_eq!(seq.cells, par.cells);
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_ca1_tick() {
+        let cells = gen::random1d_seeded(3, 1000000, vec![0, 1], None, None);
+        let mut seq = CA1::new_elementary(cells.clone(), 110);
+        let mut par = CA1::new_elementary(cells, 110);
+        for _ in 0..3 {
+            seq.compute_future_sequential();
+            mem::swap(&mut seq.cells, &mut seq.future);
+            par.compute_future_parallel();
+            mem::swap(&mut par.cells, &mut par.future);
+            assert_eq!(seq.cells, par.cells);
+        }
+    }
+
+    #[test]
+    fn test_ca1_invalid_constant_boundary() {
+        assert!(get_ca1_rule(1, 2, None, Boundary::Constant(2)).is_err());
+    }
+
+    #[test]
+    fn test_ca1_rule_table() {
+        let table = ca1_rule_table(1, 1, 2, Some(String::from("00011110"))).unwrap();
+        assert_eq!(table.len(), 8);
+        assert_eq!(table[0], (vec![1, 1, 1], 0));
+        assert_eq!(table[3], (vec![1, 0, 0], 1));
+        assert_eq!(table[7], (vec![0, 0, 0], 0));
+        let table = ca1_rule_table(0, 1, 3, Some(String::from("012012012"))).unwrap();
+        assert_eq!(table[1], (vec![2, 1], 1));
+        assert!(ca1_rule_table(1, 1, 2, Some(String::from("0"))).is_err());
+    }
+
+    #[test]
+    fn test_random_ca1_code() {
+        let code = random_ca1_code(1, 2, 3).unwrap();
+        assert_eq!(code.len(), 81);
+        assert!(code.chars().all(|c| c.to_digit(3).is_some()));
+        assert!(random_ca1_code(1, 1, 1).is_err());
+    }
+
+    #[test]
+    fn test_ca1_errors() {
+        let err = |r: Result<Box<CA1Rule>, CaError>| r.err().unwrap();
+        assert_eq!(err(get_ca1_rule(0, 2, None, Boundary::Wrap)), CaError::ZeroRadius);
+        assert_eq!(err(get_ca1_rule(1, 37, None, Boundary::Wrap)), CaError::InvalidStates);
+        assert_eq!(err(get_ca1_rule(100, 36, None, Boundary::Wrap)), CaError::ParamsTooBig);
+        let code = Some(String::from("0110"));
+        assert_eq!(err(get_ca1_rule(1, 2, code, Boundary::Wrap)),
+                   CaError::CodeLengthMismatch);
+        let code = Some(String::from("01102110"));
+        let e = err(get_ca1_rule(1, 2, code, Boundary::Wrap));
+        assert_eq!(e, CaError::InvalidCodeDigit('2', 2));
+        assert_eq!(e.to_string(), "2 is not a digit in base 2!");
     }
 }
@@ -1,31 +1,89 @@
 extern crate getopts;
 extern crate sdl2;
+extern crate rand;
 extern crate ca;
 
 mod config;
+mod export;
+mod palette;
 
+use std::cmp;
 use std::error::Error;
 use std::env;
+use std::io;
+use std::time::Instant;
 use getopts::Options;
-use sdl2::event::Event;
+use sdl2::event::{Event, WindowEventId};
 use sdl2::keyboard::Keycode;
+use sdl2::mouse::Mouse;
 use sdl2::pixels::Color;
-use sdl2::rect::Rect;
+use sdl2::rect::{Point, Rect};
 use sdl2::render::Renderer;
-use config::{CAType, InitType};
+use ca::spec::{InitSpec, RuleSpec};
+use config::CellShape;
+
+// Milliseconds to hold a generation restored by stepping back.
+const STEP_BACK_DELAY: u32 = 500;
+// Delay change by +/- keys and its upper limit in milliseconds.
+const DELAY_STEP: u32 = 10;
+const MAX_DELAY: u32 = 1000;
+// Milliseconds to show current delay in window title.
+const TITLE_DURATION: u32 = 1500;
+// Window redraws per second unless set by --fps.
+const DEFAULT_FPS: u32 = 60;
+// Most ticks done between two frames when CA falls behind its schedule, the rest is dropped.
+const MAX_TICKS_PER_FRAME: u32 = 16;
+// Grid lines are drawn only for cells at least this wide.
+const GRID_MIN_CELL_WIDTH: u32 = 4;
+const GRID_COLOR: Color = Color::RGB(40, 40, 40);
+// Color of world edges that don't wrap.
+const BORDER_COLOR: Color = Color::RGB(220, 0, 0);
+// Color of one cell wide gaps between CA shown side by side.
+const TILE_GAP_COLOR: Color = Color::RGB(90, 90, 90);
+const MAX_ZOOM: u32 = 16;
+// Cells to pan by arrow key.
+const PAN_STEP: i64 = 4;
+// Count of ages distinguished by --age.
+const AGE_COLORS: usize = 32;
+// Longest cycle noticed by --until-stable and its default tick limit.
+const STABLE_MAX_PERIOD: usize = 30;
+const DEFAULT_MAX_TICKS: u32 = 10000;
 
 static USAGE_TYPE: &'static str = "\
 TYPE:
 1 RADIUS STATES CODE
   General 1D CA.
-  RADIUS: radius of neighborhood, positive non-zero number.
+  RADIUS: radius of neighborhood, positive non-zero number. Pair L,R sets
+different radii to the left and to the right of the cell, one of them may be
+0. RADIUS is the same as RADIUS,RADIUS.
   STATES: count of states, number in range 2-36.
-  STATES.pow(2*RADIUS+1) must fit in usize.
-  CODE: STATES-base STATES.pow(2*RADIUS+1)-digit number. Far-right digit
+  STATES.pow(L+R+1) must fit in usize.
+  CODE: STATES-base STATES.pow(L+R+1)-digit number. Far-right digit
   sets state of middle cell for neighborhood 0...0, next digit to the left
   sets state of middle cell for neighborhood 0...01, ..., far-left digit
   sets state of middle cell for neighborhood X...X, where X is last digit in
-  base of STATES. Special value 'random' sets random code.
+  base of STATES. Special value 'random' sets random code. CODE may also be
+written as hexadecimal number 0xHEX or as number in any base 2-36 as
+base:N:DIGITS, it's converted to STATES-base code padded with zeros.
+
+1t RADIUS STATES CODE
+  Totalistic 1D CA.
+  RADIUS: radius of neighborhood, positive non-zero number.
+  STATES: same as for general 1D CA.
+  CODE: STATES-base ((STATES-1)*(2*RADIUS+1)+1)-digit number. Far-right digit
+  sets state of middle cell for neighborhood with sum of states 0, next digit
+  to the left sets state for sum 1, etc. Special value 'random' sets random
+  code.
+
+1ot RADIUS STATES CODE
+  Outer totalistic 1D CA.
+  RADIUS: radius of neighborhood, positive non-zero number.
+  STATES: same as for general 1D CA.
+  CODE: STATES-base (STATES*((STATES-1)*2*RADIUS+1))-digit number. Digits
+  are grouped by state of middle cell: far-right group is for middle cell
+  in state 0. Inside a group far-right digit sets new state for sum of other
+  cells states 0, next digit to the left for sum 1, etc. Special value
+  'random' sets random code.
 
 elementary CODE
   Elementary CA.
@@ -34,15 +92,92 @@ elementary CODE
 cyclic NEIGHBORHOOD THRESHOLD STATES
   Cyclic CA.
   NEIGHBORHOOD: mR for Moore neighborhood of range R, nR for Von Neumann
-neighborhood of range R.
+neighborhood of range R, hR for hexagonal neighborhood of range R (every row
+of the world is treated as shifted half a cell to the right relative to the
+previous one).
   THRESHOLD: count of next state neighbors necessary to switch to next
 state.
-  STATES: count of states.
+  STATES: count of states, 2-256.
+
+2t NEIGHBORHOOD STATES CODE
+  Outer totalistic 2D CA.
+  NEIGHBORHOOD: same as for cyclic CA.
+  STATES: count of states, number in range 2-36.
+  CODE: STATES-base (STATES*((STATES-1)*N+1))-digit number, where N is count
+of cells in the neighborhood. Digits are grouped by state of middle cell like
+for outer totalistic 1D CA. E.g. '2t m1 2 000001100000001000' is Life.
+Special value 'random' sets random code.
 
 life SURVIVE BIRTH
+life RULESTRING
   Life-like CA.
   SURVIVE, BIRTH: comma-separated lists of live cells counts needed for
-survival/birth. 'empty' stands for empty list.";
+survival/birth. 'empty' stands for empty list.
+  RULESTRING: rule in B/S notation, e.g. B3/S23 for Conway's Life or B36/S23
+for HighLife. Digits after B are counts of live neighbors needed for birth,
+digits after S are counts needed for survival.
+  With B0 but without S8 the background around live cells turns on and off
+every tick, so every other generation is shown inverted to keep it dead.
+
+ltl R,B1,B2,S1,S2
+  Larger than Life CA: dead cell is born if count of live cells in its Moore
+neighborhood of range R is in range B1-B2, live cell survives if the count is
+in range S1-S2 (the cell itself is not counted). E.g. 'ltl 5,34,45,33,57' is
+Bosco's rule.
+
+generations SURVIVE BIRTH STATES
+generations RULESTRING STATES
+  Generations CA: Life-like CA where cells that don't survive go through
+dying states 2..STATES-1 before death. Only cells in state 1 count as live
+neighbors.
+  SURVIVE, BIRTH, RULESTRING: same as for life.
+  STATES: count of states, at least 2.
+
+coupled SURVIVE BIRTH
+coupled RULESTRING
+  Two layers of life-like CA competing for space: dead cell of a layer can't
+be born where the other layer has a live cell. Random init fills the layers
+independently. Colors of layers are blended, so --gif isn't supported.
+  SURVIVE, BIRTH, RULESTRING: same as for life.
+
+brian
+  Brian's Brain CA: off cell (0) turns on (1) if exactly 2 of its Moore
+neighbors are on, on cell starts dying (2), dying cell turns off.
+
+wireworld
+  Wireworld CA: electron head (1) turns into electron tail (2), tail turns
+into conductor (3), conductor turns into head if 1 or 2 of its Moore
+neighbors are heads. Requires pattern file or csv init.
+
+ant TURNS
+  Langton's ant and other turmites: ant on a cell in state I turns according
+to I-th letter of TURNS, switches the cell to the next state and moves
+forward. 'points' init places ants at given points of empty world, other
+inits place one ant at the center.
+  TURNS: string of letters R (turn right), L (turn left), N (no turn) and
+U (u-turn), at least 2 letters. 'RL' is the classic Langton's ant.
+
+TYPE + TYPE ...
+  Several CA of the same dimension side by side, e.g. elementary 30 +
+elementary 90 + elementary 110. They start from the same --init and tick
+together.";
+
+static USAGE_KEYS: &'static str = "\
+KEYS:
+[, ]: switch elementary CA to the previous/next rule and restart it.
+R: switch elementary or general 1D CA to random rule and restart it.
+Space: pause/resume. 2D CAs pause by themselves when they stop changing.
+N, Right: advance one tick while paused.
++, -: speed up/slow down (decrease/increase delay).
+S: print visible pattern in RLE format to stdout.
+G: toggle grid lines (for cells at least 4 pixels wide).
+Left click while paused: toggle cell between states 0 and 1.
+Right click while paused: switch cell to the next state.
+Mouse wheel: zoom in/out. Arrows pan the view while zoomed in instead of
+their other functions.
+Up, Down: scroll 1D CA through generations kept by --scrollback.
+Left: step back (see --history).
+Escape: quit.";
 
 fn make_opts() -> Options {
     let mut opts = Options::new();
@@ -55,13 +190,31 @@ fn make_opts() -> Options {
                  writing value V N times you can write V*N. 'uniform' stands for uniform \
                  distribution of all possible states. X1,X2,Y1,Y2: if specified, cells will be \
                  filled only in this coordinates ranges. For 1D CA values Y1 and Y2 must be \
-                 omitted.\n'points' fills specified points with value 1 leaving other contain 0. \
+                 omitted. 'random:density=D' fills every cell of 2D CA with 1 with probability \
+                 D between 0 and 1, leaving other cells 0.\n'points' fills specified points with \
+                 value 1 leaving other contain 0. \
                  COORDS: semicolon-separated list of coordinates of initially filled cells. For \
                  1D CA coordinate must be integer >= 0, for 2D CA coordinate must have form X,Y, \
                  where X and Y are integers >= 0. Special value 'c' means center point. Also you \
                  can specify coordinates relative to center point in form c+X/c-X for 1D CA and \
-                 c+X,Y/c-X,Y for 2D CA.",
-                "random:STATES[:X1[,X2[,Y1[,Y2]]]] or points:COORDS");
+                 c+X,Y/c-X,Y for 2D CA. Coordinate followed by =STATE (e.g. 5,7=2) fills the \
+                 point with STATE instead of 1. 'points:rle:FILE' loads pattern in RLE format \
+                 from FILE and places it at center of 2D CA, 'points:cells:FILE' does the same \
+                 for plaintext (.cells) format. 'points:pattern:NAME@COORD' places top left \
+                 corner of built-in Life pattern NAME at COORD, several patterns are separated \
+                 by semicolons. NAME: glider, blinker, block, lwss, r-pentomino or \
+                 gosper-gun.\n'image' loads PNG or JPEG image from FILE, \
+                 scales it to fit 2D CA world and splits brightness of its pixels into levels, \
+                 one per state, black is state 0. Parts of the world not covered by the image \
+                 are in state 0.\n'binary' sets initial row of 1D CA to STRING, every \
+                 character of which is the state of a cell in base of count of states (e.g. \
+                 1101). ALIGN: center (default) or right, where STRING is placed in the row.\n\
+                 'csv' loads states of 2D CA from FILE of comma-separated rows, e.g. saved from \
+                 a spreadsheet, and places them at the top left corner of the world. Cells \
+                 beyond the world are dropped, the rest of the world is in state 0.",
+                "random:STATES[:X1[,X2[,Y1[,Y2]]]] or random:density=D or points:COORDS or \
+                 points:rle:FILE or points:cells:FILE or points:pattern:NAME@COORD or \
+                 image:FILE or binary:STRING[:ALIGN] or csv:FILE");
     opts.optopt("s",
                 "size",
                 "(default: 2/3 of desktop width and height) Screen size in pixels. Defaults to \
@@ -70,12 +223,144 @@ fn make_opts() -> Options {
     opts.optopt("c",
                 "cell",
                 "(default: maximum divisor of width and height from values 1, 2, 3, 4) Cell size \
-                 in pixels. Must be divisor of width and height.",
-                "CELL_WIDTH");
+                 in pixels. Cell width must be divisor of width and cell height must be divisor \
+                 of height. Single number sets both.",
+                "CELL_WIDTH[xCELL_HEIGHT]");
     opts.optopt("d",
                 "delay",
-                "(default: 5) Delay after every tick in milliseconds.",
+                "(default: 5) Delay between ticks in milliseconds. 0 ticks as fast as \
+                 possible.",
                 "DELAY");
+    opts.optopt("",
+                "tps",
+                "Ticks per second, alternative to --delay.",
+                "TPS");
+    opts.optopt("",
+                "fps",
+                "(default: 60) Window redraws per second, independent of ticks. Panning and \
+                 zooming stay responsive while CA ticks slowly.",
+                "FPS");
+    opts.optopt("",
+                "seed",
+                "(default: random) Seed for random initialization and --async order. Runs \
+                 with the same seed and parameters start from the same world and update cells \
+                 in the same order. Seed of random run is printed to stderr as --seed SEED.",
+                "SEED");
+    opts.optopt("",
+                "record",
+                "Save every drawn frame to DIR as frame_00001.png, frame_00002.png, ...",
+                "DIR");
+    opts.optopt("",
+                "gif",
+                "Don't open window, run CA for FRAMES ticks and save them as animated GIF to \
+                 FILE. Requires --size.",
+                "FILE");
+    opts.optflag("",
+                 "ppm-stream",
+                 "Don't open window, run CA for FRAMES ticks and write them to stdout as binary \
+                  PPM images, e.g. for 'ffmpeg -f image2pipe -i - out.mp4'. Requires --size.");
+    opts.optopt("",
+                "frames",
+                "(default: 100) Count of frames for --gif and --ppm-stream.",
+                "FRAMES");
+    opts.optopt("",
+                "history",
+                "(default: 0) Count of previous generations to keep. Left arrow key steps back \
+                 to them.",
+                "GENERATIONS");
+    opts.optopt("",
+                "skip",
+                "(default: 0) Count of generations to run before showing the first one.",
+                "GENERATIONS");
+    opts.optopt("",
+                "scrollback",
+                "(default: window height in cells) Count of generations of 1D CA to keep for \
+                 scrolling with Up and Down keys.",
+                "ROWS");
+    opts.optflagopt("",
+                    "async",
+                    "Update one cell per tick instead of all cells at once. Only for 2D CA. \
+                     ORDER: random (default) picks random cell, sweep goes row by row, shuffle \
+                     visits every cell once per sweep in random order.",
+                    "ORDER");
+    opts.optflag("",
+                 "growing",
+                 "Run life on unbounded plane instead of wrapped world, view follows the \
+                  pattern. Initial world is placed at the center of the plane.");
+    opts.optopt("",
+                "symmetry",
+                "(default: none) Make random initialization of 2D CA symmetric: horizontal \
+                 mirrors left half of the world to the right one, vertical mirrors top half to \
+                 the bottom one, both does both, rot4 makes centered square of the world \
+                 symmetric under rotation by 90 degrees and clears the rest.",
+                "SYMMETRY");
+    opts.optopt("",
+                "topology",
+                "(default: torus) Edges of 2D world: torus wraps both axes, cylinder-x wraps \
+                 only left and right edges, cylinder-y only top and bottom ones, plane wraps \
+                 neither. Cells beyond edges that don't wrap are not counted as neighbors. \
+                 mirror wraps neither, but cells beyond every edge are mirror images of the \
+                 ones inside, e.g. pins spirals of cyclic CA to the walls. helix[:SHIFT] is \
+                 torus whose right edge continues at the left one SHIFT (default: 1) rows \
+                 lower.",
+                "TOPOLOGY");
+    opts.optopt("",
+                "shape",
+                "(default: rect) Shape of cells in the window: rect or circle. Circles of CA \
+                 with hexagonal neighborhood are laid out as hexagonal grid, every row shifted \
+                 half a cell to the right of the previous one. Recorded frames keep rects.",
+                "SHAPE");
+    opts.optflag("",
+                 "no-border",
+                 "Don't outline world edges that don't wrap (see --topology).");
+    opts.optflag("",
+                 "age",
+                 "Color live cells of 2D CA by count of generations they have been alive for \
+                  instead of their state. With --palette the first color is for dead cells and \
+                  the next ones are for ages 0, 1, ...");
+    opts.optflag("",
+                 "headless",
+                 "Don't open window, run CA for TICKS ticks, save final state to --out FILE \
+                  and print summary of the run. Requires --size.");
+    opts.optopt("",
+                "ticks",
+                "(default: 100) Count of ticks for --headless.",
+                "TICKS");
+    opts.optopt("",
+                "out",
+                "File to save final state of --headless run to, in the same format as \
+                 snapshots. 2D CA state is gzip-compressed if FILE ends with .gz.",
+                "FILE");
+    opts.optflag("",
+                 "until-stable",
+                 "Run --headless, --gif or --ppm-stream CA until it stops changing or starts \
+                  repeating one of last 30 generations instead of fixed count of ticks or \
+                  frames.");
+    opts.optopt("",
+                "max-ticks",
+                "(default: 10000) Count of ticks after which --until-stable run stops anyway.",
+                "TICKS");
+    opts.optflag("",
+                 "report-json",
+                 "Print summary of --headless run (ticks, population of every state, period of \
+                  the cycle CA ended in, time and seed) as a JSON object instead of plain text.");
+    opts.optflag("",
+                 "random-rule",
+                 "Start elementary or general 1D CA with random rule instead of given one, its \
+                  code is shown in the title. Press R to try another one.");
+    opts.optflag("",
+                 "dump-rule",
+                 "Print new state for every neighborhood of general or elementary 1D CA, in \
+                  the order of digits of its code, and exit.");
+    opts.optopt("",
+                "palette",
+                "Load colors of states from FILE containing one R,G,B line per state, starting \
+                 from state 0. Blank lines and comments starting with # are ignored.",
+                "FILE");
+    opts.optopt("",
+                "bg",
+                "(default: first palette color) Background color, it's the color of state 0.",
+                "R,G,B");
     opts
 }
 
@@ -84,6 +369,7 @@ fn make_window(video_subsystem: &sdl2::VideoSubsystem,
                -> Result<sdl2::video::Window, &'static str> {
     let mut window = try!(video_subsystem.window("CA", 0, 0)
         .position(0, 0)
+        .resizable()
         .build()
         .map_err(|_| "Failed to create window!"));
     let di = try!(window.display_index().map_err(|_| "Failed to get display index!"));
@@ -106,23 +392,35 @@ fn make_window(video_subsystem: &sdl2::VideoSubsystem,
     Ok(window)
 }
 
-fn get_cell_width(width: u32,
-                  height: u32,
-                  requested_cell_width: Option<u8>)
-                  -> Result<u32, String> {
-    match requested_cell_width {
-        Some(cw) => {
-            let cw = cw as u32;
-            if width % cw != 0 || height % cw != 0 {
-                Err(format!("Cell width ({}) must me divisor of width ({}) and height ({})!",
+fn set_title(renderer: &mut Renderer, title: &str) {
+    if let Some(window) = renderer.window_mut() {
+        window.set_title(title).unwrap();
+    }
+}
+
+// (cell width, cell height)
+fn get_cell_size(width: u32,
+                 height: u32,
+                 requested_cell_size: Option<(u8, u8)>)
+                 -> Result<(u32, u32), String> {
+    match requested_cell_size {
+        Some((cw, ch)) => {
+            let (cw, ch) = (cw as u32, ch as u32);
+            if width % cw != 0 || height % ch != 0 {
+                Err(format!("Cell width ({}) must me divisor of width ({}) and cell height ({}) \
+                             must be divisor of height ({})!",
                             cw,
                             width,
+                            ch,
                             height))
             } else {
-                Ok(cw)
+                Ok((cw, ch))
             }
         }
-        None => Ok((1..5).filter(|x| width % x == 0 && height % x == 0).max().unwrap()),
+        None => {
+            let cw = (1..5).filter(|x| width % x == 0 && height % x == 0).max().unwrap();
+            Ok((cw, cw))
+        }
     }
 }
 
@@ -130,32 +428,170 @@ trait CAView {
     fn width(&self) -> usize;
     fn height(&self) -> usize;
     fn state_to_color(&self, state: ca::types::Cell) -> Color;
-    fn cells(&self) -> &Vec<Vec<ca::types::Cell>>;
+    fn row(&self, row: usize) -> &[ca::types::Cell];
     fn tick(&mut self);
+    fn step_back(&mut self) -> bool;
+    fn save(&self, path: &str) -> Result<(), String>;
+
+    fn tick_n(&mut self, n: usize) {
+        for _ in 0..n {
+            self.tick();
+        }
+    }
+
+    // Switches to the rule delta codes away and restarts from the first generation, returns
+    // false if the view can't change its rule.
+    fn step_rule(&mut self, _delta: i32) -> bool {
+        false
+    }
+
+    // Switches to random rule of the same kind and restarts from the first generation, returns
+    // false if the view can't change its rule.
+    fn random_rule(&mut self) -> bool {
+        false
+    }
+
+    fn title(&self) -> String {
+        String::from("CA")
+    }
+
+    // Whether (left and right, top and bottom) edges of the world are walls instead of
+    // wrapping around.
+    fn walls(&self) -> (bool, bool) {
+        (false, false)
+    }
+
+    // Color of visible cell, views may color cells by more than their state.
+    fn cell_color(&self, _row: usize, _col: usize, state: ca::types::Cell) -> Color {
+        self.state_to_color(state)
+    }
+
+    // Whether the CA stopped changing, views without activity tracking never freeze.
+    fn frozen(&self) -> bool {
+        false
+    }
+
+    // Hash of current generation for cycle detection, None if the view doesn't provide it.
+    fn state_hash(&self) -> Option<u64> {
+        None
+    }
+
+    // Count of visible cells in every state, index is state.
+    fn population(&self) -> Vec<usize> {
+        let mut counts: Vec<usize> = Vec::new();
+        for row in 0..self.height() {
+            for &cell in self.row(row) {
+                let state = cell as usize;
+                if state >= counts.len() {
+                    counts.resize(state + 1, 0);
+                }
+                counts[state] += 1;
+            }
+        }
+        counts
+    }
+
+    // Changes size of the world to w x h cells, returns false if the view has fixed size.
+    fn resize(&mut self, _w: usize, _h: usize) -> bool {
+        false
+    }
+
+    // Scroll view up by rows (down if negative), views without scrollback ignore it.
+    fn scroll(&mut self, _rows: i32) {}
+
+    // Sets state of visible cell, returns false if the cell can't be edited.
+    fn set(&mut self, _row: usize, _col: usize, _state: ca::types::Cell) -> bool {
+        false
+    }
+
+    // Visible cells in RLE format.
+    fn to_rle(&self) -> Result<String, String> {
+        let cells: Vec<ca::types::Cell> = (0..self.height())
+            .flat_map(|row| self.row(row).iter().cloned())
+            .collect();
+        ca::gen::to_rle(self.width(), self.height(), &cells)
+    }
+}
+
+// Rule of 1D CA known to the view, so that it can switch the CA to another rule of the kind.
+enum Ca1ViewRule {
+    Unknown,
+    Elementary(u8),
+    General {
+        radius: (u8, u8),
+        states: u8,
+        code: String,
+    },
 }
 
+// Spacetime diagram of 1D CA. Keeps last `scrollback` generations, shows `height` of them.
 struct CA1View {
     automaton: ca::CA1,
+    // first generation and rule, used to restart CA with another rule
+    initial: Vec<ca::types::Cell>,
+    rule: Ca1ViewRule,
+    // ring buffer of generations, the oldest one is at index first
     cells: Vec<Vec<ca::types::Cell>>,
+    first: usize,
     palette: Vec<Color>,
     height: usize,
     current_row: usize,
     last_row: usize,
+    // count of rows the view is scrolled up from the latest generation
+    scrolled: usize,
 }
 
 impl CA1View {
-    fn new(automaton: ca::CA1, palette: Vec<Color>, height: usize) -> CA1View {
-        let mut cells = vec![vec![0; automaton.w]; height];
+    fn new(automaton: ca::CA1,
+           palette: Vec<Color>,
+           height: usize,
+           scrollback: usize)
+           -> CA1View {
+        let scrollback = cmp::max(scrollback, height);
+        let mut cells = vec![vec![0; automaton.w]; scrollback];
         cells[0].copy_from_slice(&automaton.cells);
         CA1View {
+            initial: automaton.cells.clone(),
+            rule: Ca1ViewRule::Unknown,
             automaton: automaton,
             cells: cells,
+            first: 0,
             palette: palette,
             height: height,
             current_row: 0,
-            last_row: height - 1,
+            last_row: scrollback - 1,
+            scrolled: 0,
         }
     }
+
+    // First visible row of the buffer when the view isn't scrolled.
+    fn bottom_top_row(&self) -> usize {
+        (self.current_row + 1).saturating_sub(self.height)
+    }
+
+    fn with_rule(mut self, rule: Ca1ViewRule) -> CA1View {
+        self.rule = rule;
+        self
+    }
+
+    // Restarts CA from the first generation with new rule.
+    fn restart(&mut self, rule: Box<ca::CA1Rule>) {
+        self.automaton.restart(self.initial.clone(), rule);
+        for row in self.cells.iter_mut() {
+            for cell in row.iter_mut() {
+                *cell = 0;
+            }
+        }
+        self.cells[0].copy_from_slice(&self.initial);
+        self.first = 0;
+        self.current_row = 0;
+        self.scrolled = 0;
+    }
+
+    // Index in cells of the row-th oldest generation.
+    fn buffer_row(&self, row: usize) -> usize {
+        (self.first + row) % self.cells.len()
+    }
 }
 
 impl CAView for CA1View {
@@ -171,22 +607,107 @@ impl CAView for CA1View {
         self.palette[state as usize]
     }
 
-    fn cells(&self) -> &Vec<Vec<ca::types::Cell>> {
-        &self.cells
+    fn row(&self, row: usize) -> &[ca::types::Cell] {
+        &self.cells[self.buffer_row(self.bottom_top_row() - self.scrolled + row)]
     }
 
     fn tick(&mut self) {
         self.automaton.tick();
         if self.current_row < self.last_row {
             self.current_row += 1;
-            self.cells[self.current_row].copy_from_slice(&self.automaton.cells);
         } else {
-            for row in 0..self.last_row {
-                for col in 0..self.automaton.w {
-                    self.cells[row][col] = self.cells[row + 1][col];
-                }
+            // the oldest generation is overwritten by the new one
+            self.first = self.buffer_row(1);
+        }
+        let current = self.buffer_row(self.current_row);
+        self.cells[current].copy_from_slice(&self.automaton.cells);
+        if self.scrolled > 0 {
+            // keep scrolled view on the same generations
+            self.scrolled = cmp::min(self.scrolled + 1, self.bottom_top_row());
+        }
+    }
+
+    fn step_back(&mut self) -> bool {
+        if !self.automaton.step_back() {
+            return false;
+        }
+        if self.current_row > 0 {
+            let current = self.buffer_row(self.current_row);
+            for col in 0..self.automaton.w {
+                self.cells[current][col] = 0;
+            }
+            self.current_row -= 1;
+        } else {
+            let current = self.buffer_row(0);
+            self.cells[current].copy_from_slice(&self.automaton.cells);
+        }
+        self.scrolled = cmp::min(self.scrolled, self.bottom_top_row());
+        true
+    }
+
+    fn scroll(&mut self, rows: i32) {
+        let scrolled = (self.scrolled as i64) + (rows as i64);
+        self.scrolled = cmp::min(cmp::max(scrolled, 0) as usize, self.bottom_top_row());
+    }
+
+    // Only the current generation can be edited.
+    fn set(&mut self, row: usize, col: usize, state: ca::types::Cell) -> bool {
+        if self.bottom_top_row() - self.scrolled + row != self.current_row {
+            return false;
+        }
+        self.automaton.cells[col] = state;
+        let current = self.buffer_row(self.current_row);
+        self.cells[current][col] = state;
+        true
+    }
+
+    fn save(&self, path: &str) -> Result<(), String> {
+        self.automaton.save(path)
+    }
+
+    // only the latest generation, not the whole diagram
+    fn population(&self) -> Vec<usize> {
+        self.automaton.population()
+    }
+
+    fn step_rule(&mut self, delta: i32) -> bool {
+        let code = match self.rule {
+            Ca1ViewRule::Elementary(code) => (code as i32) + delta,
+            _ => return false,
+        };
+        // rule numbers wrap around
+        let code = ((code % 256 + 256) % 256) as u8;
+        self.rule = Ca1ViewRule::Elementary(code);
+        self.restart(ca::get_elementary_rule(code));
+        true
+    }
+
+    fn random_rule(&mut self) -> bool {
+        let rule = match self.rule {
+            Ca1ViewRule::Unknown => return false,
+            Ca1ViewRule::Elementary(_) => {
+                let code = random_elementary_code();
+                self.rule = Ca1ViewRule::Elementary(code);
+                ca::get_elementary_rule(code)
+            }
+            Ca1ViewRule::General { radius, states, ref mut code } => {
+                // parameters were checked when CA was created
+                *code = ca::random_ca1_code(radius.0, radius.1, states).unwrap();
+                let (left, right) = radius;
+                let boundary = ca::nb::Boundary::default();
+                ca::get_ca1_asymmetric_rule(left, right, states, Some(code.clone()), boundary)
+                    .unwrap()
             }
-            self.cells[self.last_row].copy_from_slice(&self.automaton.cells);
+        };
+        self.restart(rule);
+        true
+    }
+
+    fn title(&self) -> String {
+        match self.rule {
+            Ca1ViewRule::Unknown => String::from("CA"),
+            Ca1ViewRule::Elementary(code) => format!("CA - rule {}", code),
+            Ca1ViewRule::General { ref code, .. } => format!("CA - code {}", code),
         }
     }
 }
@@ -194,13 +715,15 @@ impl CAView for CA1View {
 struct CA2View {
     automaton: ca::CA2,
     palette: Vec<Color>,
+    topology: ca::nb::Topology,
 }
 
 impl CA2View {
-    fn new(automaton: ca::CA2, palette: Vec<Color>) -> CA2View {
+    fn new(automaton: ca::CA2, palette: Vec<Color>, topology: ca::nb::Topology) -> CA2View {
         CA2View {
             automaton: automaton,
             palette: palette,
+            topology: topology,
         }
     }
 }
@@ -218,29 +741,692 @@ impl CAView for CA2View {
         self.palette[state as usize]
     }
 
-    fn cells(&self) -> &Vec<Vec<ca::types::Cell>> {
-        &self.automaton.cells
+    fn row(&self, row: usize) -> &[ca::types::Cell] {
+        self.automaton.row(row)
     }
 
     fn tick(&mut self) {
         self.automaton.tick();
     }
+
+    fn tick_n(&mut self, n: usize) {
+        self.automaton.tick_n(n);
+    }
+
+    fn step_back(&mut self) -> bool {
+        self.automaton.step_back()
+    }
+
+    fn save(&self, path: &str) -> Result<(), String> {
+        if path.ends_with(".gz") {
+            self.automaton.save_compressed(path)
+        } else {
+            self.automaton.save(path)
+        }
+    }
+
+    fn set(&mut self, row: usize, col: usize, state: ca::types::Cell) -> bool {
+        self.automaton.set(row, col, state);
+        true
+    }
+
+    fn population(&self) -> Vec<usize> {
+        self.automaton.population()
+    }
+
+    fn resize(&mut self, w: usize, h: usize) -> bool {
+        self.automaton.resize(w, h);
+        true
+    }
+
+    fn frozen(&self) -> bool {
+        self.automaton.is_frozen()
+    }
+
+    fn state_hash(&self) -> Option<u64> {
+        Some(self.automaton.state_hash())
+    }
+
+    fn walls(&self) -> (bool, bool) {
+        (!self.topology.x.wraps(), !self.topology.y.wraps())
+    }
+
+    // With tracked ages live cells are colored by age, cells older than the palette get its
+    // last color.
+    fn cell_color(&self, row: usize, col: usize, state: ca::types::Cell) -> Color {
+        match self.automaton.age(row, col) {
+            Some(age) if state != 0 => {
+                let idx = cmp::min(1 + age as usize, self.palette.len() - 1);
+                self.palette[idx]
+            }
+            _ => self.state_to_color(state),
+        }
+    }
 }
 
-fn draw_ca(caview: &Box<CAView>, renderer: &mut Renderer, cwidth: u32) {
-    for row in 0..caview.height() {
-        for col in 0..caview.width() {
-            let cell = caview.cells()[row][col];
-            let color = caview.state_to_color(cell);
+struct AntView {
+    automaton: ca::LangtonsAnt,
+    palette: Vec<Color>,
+}
+
+impl AntView {
+    fn new(automaton: ca::LangtonsAnt, palette: Vec<Color>) -> AntView {
+        AntView {
+            automaton: automaton,
+            palette: palette,
+        }
+    }
+}
+
+impl CAView for AntView {
+    fn width(&self) -> usize {
+        self.automaton.w
+    }
+
+    fn height(&self) -> usize {
+        self.automaton.h
+    }
+
+    fn state_to_color(&self, state: ca::types::Cell) -> Color {
+        self.palette[state as usize]
+    }
+
+    fn row(&self, row: usize) -> &[ca::types::Cell] {
+        self.automaton.row(row)
+    }
+
+    fn tick(&mut self) {
+        self.automaton.tick();
+    }
+
+    fn step_back(&mut self) -> bool {
+        false
+    }
+
+    fn save(&self, path: &str) -> Result<(), String> {
+        self.automaton.save(path)
+    }
+
+    fn set(&mut self, row: usize, col: usize, state: ca::types::Cell) -> bool {
+        let w = self.automaton.w;
+        self.automaton.cells[row * w + col] = state;
+        true
+    }
+}
+
+// Both layers of coupled CA in one picture, colors of their states are blended. Cell state
+// is layer0 + states * layer1.
+struct CoupledView {
+    automaton: ca::CoupledCA2,
+    palettes: [Vec<Color>; 2],
+    states: usize,
+    cells: Vec<ca::types::Cell>,
+}
+
+impl CoupledView {
+    fn new(automaton: ca::CoupledCA2, palettes: [Vec<Color>; 2], states: usize) -> CoupledView {
+        let mut view = CoupledView {
+            automaton: automaton,
+            palettes: palettes,
+            states: states,
+            cells: Vec::new(),
+        };
+        view.update_cells();
+        view
+    }
+
+    fn update_cells(&mut self) {
+        let states = self.states as ca::types::Cell;
+        let layers = &self.automaton.layers;
+        self.cells = layers[0]
+            .iter()
+            .zip(layers[1].iter())
+            .map(|(&cell0, &cell1)| cell0 + states * cell1)
+            .collect();
+    }
+}
+
+impl CAView for CoupledView {
+    fn width(&self) -> usize {
+        self.automaton.w
+    }
+
+    fn height(&self) -> usize {
+        self.automaton.h
+    }
+
+    fn state_to_color(&self, state: ca::types::Cell) -> Color {
+        let states = self.states as ca::types::Cell;
+        palette::blend(self.palettes[0][(state % states) as usize],
+                       self.palettes[1][(state / states) as usize])
+    }
+
+    fn row(&self, row: usize) -> &[ca::types::Cell] {
+        let w = self.automaton.w;
+        &self.cells[row * w..(row + 1) * w]
+    }
+
+    fn tick(&mut self) {
+        self.automaton.tick();
+        self.update_cells();
+    }
+
+    fn step_back(&mut self) -> bool {
+        false
+    }
+
+    fn save(&self, path: &str) -> Result<(), String> {
+        ca::snapshot::save(path, self.automaton.w, self.automaton.h, &self.cells)
+    }
+
+    fn set(&mut self, row: usize, col: usize, state: ca::types::Cell) -> bool {
+        let states = self.states as ca::types::Cell;
+        self.automaton.set(0, row, col, state % states);
+        self.automaton.set(1, row, col, state / states % states);
+        self.update_cells();
+        true
+    }
+
+    fn state_hash(&self) -> Option<u64> {
+        Some(self.automaton.state_hash())
+    }
+}
+
+// Several CA shown side by side in a grid of equal tiles, row by row. They tick together, so
+// that rules can be compared.
+struct TiledView {
+    tiles: Vec<Box<CAView>>,
+    states: Vec<u32>, // of every tile
+    cols: usize, // tiles in a row
+    tile_size: (usize, usize),
+    width: usize,
+    height: usize,
+    cells: Vec<ca::types::Cell>,
+}
+
+impl TiledView {
+    fn new(tiles: Vec<Box<CAView>>,
+           states: Vec<u32>,
+           cols: usize,
+           tile_size: (usize, usize),
+           width: usize,
+           height: usize)
+           -> TiledView {
+        let mut view = TiledView {
+            tiles: tiles,
+            states: states,
+            cols: cols,
+            tile_size: tile_size,
+            width: width,
+            height: height,
+            cells: Vec::new(),
+        };
+        view.update_cells();
+        view
+    }
+
+    // (tile, row, col inside the tile) of the view cell, None for cells in gaps.
+    fn tile_at(&self, row: usize, col: usize) -> Option<(usize, usize, usize)> {
+        let (tw, th) = self.tile_size;
+        let (tile_col, tile_row) = (col / (tw + 1), row / (th + 1));
+        let (x, y) = (col % (tw + 1), row % (th + 1));
+        let tile = tile_row * self.cols + tile_col;
+        if x < tw && y < th && tile_col < self.cols && tile < self.tiles.len() {
+            Some((tile, y, x))
+        } else {
+            None
+        }
+    }
+
+    fn update_cells(&mut self) {
+        let mut cells = vec![0; self.width * self.height];
+        for row in 0..self.height {
+            for col in 0..self.width {
+                if let Some((tile, y, x)) = self.tile_at(row, col) {
+                    cells[row * self.width + col] = self.tiles[tile].row(y)[x];
+                }
+            }
+        }
+        self.cells = cells;
+    }
+}
+
+impl CAView for TiledView {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn state_to_color(&self, state: ca::types::Cell) -> Color {
+        self.tiles[0].state_to_color(state)
+    }
+
+    fn row(&self, row: usize) -> &[ca::types::Cell] {
+        &self.cells[row * self.width..(row + 1) * self.width]
+    }
+
+    fn tick(&mut self) {
+        for tile in self.tiles.iter_mut() {
+            tile.tick();
+        }
+        self.update_cells();
+    }
+
+    fn tick_n(&mut self, n: usize) {
+        for tile in self.tiles.iter_mut() {
+            tile.tick_n(n);
+        }
+        self.update_cells();
+    }
+
+    fn step_back(&mut self) -> bool {
+        // every tile steps back, even when another one can't
+        let stepped = self.tiles.iter_mut().fold(false, |stepped, tile| {
+            tile.step_back() || stepped
+        });
+        self.update_cells();
+        stepped
+    }
+
+    fn save(&self, path: &str) -> Result<(), String> {
+        ca::snapshot::save(path, self.width, self.height, &self.cells)
+    }
+
+    fn step_rule(&mut self, delta: i32) -> bool {
+        let changed = self.tiles.iter_mut().fold(false, |changed, tile| {
+            tile.step_rule(delta) || changed
+        });
+        self.update_cells();
+        changed
+    }
+
+    fn random_rule(&mut self) -> bool {
+        let changed = self.tiles.iter_mut().fold(false, |changed, tile| {
+            tile.random_rule() || changed
+        });
+        self.update_cells();
+        changed
+    }
+
+    fn title(&self) -> String {
+        let titles: Vec<String> = self.tiles.iter().map(|tile| tile.title()).collect();
+        titles.join(" | ")
+    }
+
+    fn cell_color(&self, row: usize, col: usize, state: ca::types::Cell) -> Color {
+        match self.tile_at(row, col) {
+            Some((tile, y, x)) => self.tiles[tile].cell_color(y, x, state),
+            None => TILE_GAP_COLOR,
+        }
+    }
+
+    fn frozen(&self) -> bool {
+        self.tiles.iter().all(|tile| tile.frozen())
+    }
+
+    fn state_hash(&self) -> Option<u64> {
+        self.tiles.iter().fold(Some(0), |hash, tile| {
+            match (hash, tile.state_hash()) {
+                (Some(hash), Some(tile_hash)) => {
+                    Some(hash.wrapping_mul(31).wrapping_add(tile_hash))
+                }
+                _ => None,
+            }
+        })
+    }
+
+    fn population(&self) -> Vec<usize> {
+        let mut counts: Vec<usize> = Vec::new();
+        for tile in self.tiles.iter() {
+            for (state, count) in tile.population().into_iter().enumerate() {
+                if state >= counts.len() {
+                    counts.resize(state + 1, 0);
+                }
+                counts[state] += count;
+            }
+        }
+        counts
+    }
+
+    fn scroll(&mut self, rows: i32) {
+        for tile in self.tiles.iter_mut() {
+            tile.scroll(rows);
+        }
+        self.update_cells();
+    }
+
+    fn set(&mut self, row: usize, col: usize, state: ca::types::Cell) -> bool {
+        let set = match self.tile_at(row, col) {
+            // states are cycled through the most states of all tiles, the tile may have less
+            Some((tile, y, x)) => {
+                let state = (state as u32 % self.states[tile]) as ca::types::Cell;
+                self.tiles[tile].set(y, x, state)
+            }
+            None => false,
+        };
+        self.update_cells();
+        set
+    }
+}
+
+// Window-sized part of unbounded world centered at the pattern.
+struct GrowingLifeView {
+    automaton: ca::GrowingLife,
+    palette: Vec<Color>,
+    width: usize,
+    height: usize,
+    cells: Vec<ca::types::Cell>,
+}
+
+impl GrowingLifeView {
+    fn new(automaton: ca::GrowingLife,
+           palette: Vec<Color>,
+           width: usize,
+           height: usize)
+           -> GrowingLifeView {
+        let mut view = GrowingLifeView {
+            automaton: automaton,
+            palette: palette,
+            width: width,
+            height: height,
+            cells: vec![0; width * height],
+        };
+        view.update_cells();
+        view
+    }
+
+    fn update_cells(&mut self) {
+        let (top, left) = match self.automaton.bounding_box() {
+            Some((r1, c1, r2, c2)) => {
+                ((r1 + r2) / 2 - (self.height / 2) as i64, (c1 + c2) / 2 - (self.width / 2) as i64)
+            }
+            None => (0, 0),
+        };
+        for row in 0..self.height {
+            for col in 0..self.width {
+                self.cells[row * self.width + col] =
+                    self.automaton.get(top + row as i64, left + col as i64);
+            }
+        }
+    }
+}
+
+impl CAView for GrowingLifeView {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn state_to_color(&self, state: ca::types::Cell) -> Color {
+        self.palette[state as usize]
+    }
+
+    fn row(&self, row: usize) -> &[ca::types::Cell] {
+        &self.cells[row * self.width..(row + 1) * self.width]
+    }
+
+    fn tick(&mut self) {
+        self.automaton.tick();
+        self.update_cells();
+    }
+
+    fn step_back(&mut self) -> bool {
+        false
+    }
+
+    fn save(&self, path: &str) -> Result<(), String> {
+        self.automaton.save(path)
+    }
+}
+
+// Part of the world shown in the window: cells are enlarged zoom times and (ox, oy) is the
+// top left visible cell.
+struct Viewport {
+    zoom: u32,
+    ox: usize,
+    oy: usize,
+}
+
+impl Viewport {
+    fn new() -> Viewport {
+        Viewport {
+            zoom: 1,
+            ox: 0,
+            oy: 0,
+        }
+    }
+
+    // Count of (partially) visible columns and rows starting from (ox, oy).
+    fn visible(&self, caview: &Box<CAView>) -> (usize, usize) {
+        let zoom = self.zoom as usize;
+        let cols = cmp::min((caview.width() + zoom - 1) / zoom, caview.width() - self.ox);
+        let rows = cmp::min((caview.height() + zoom - 1) / zoom, caview.height() - self.oy);
+        (cols, rows)
+    }
+
+    fn pan(&mut self, caview: &Box<CAView>, dx: i64, dy: i64) {
+        let zoom = self.zoom as usize;
+        let max_ox = (caview.width() - caview.width() / zoom) as i64;
+        let max_oy = (caview.height() - caview.height() / zoom) as i64;
+        self.ox = cmp::max(0, cmp::min(self.ox as i64 + dx, max_ox)) as usize;
+        self.oy = cmp::max(0, cmp::min(self.oy as i64 + dy, max_oy)) as usize;
+    }
+
+    // (row, col) of the cell drawn at pixel (x, y), None if there is no cell.
+    fn cell_at_pixel(&self,
+                     caview: &Box<CAView>,
+                     x: i32,
+                     y: i32,
+                     cell_size: (u32, u32),
+                     hex: bool)
+                     -> Option<(usize, usize)> {
+        if x < 0 || y < 0 {
+            return None;
+        }
+        let cwidth = cell_size.0 * self.zoom;
+        let col = (x as u32 / cwidth) as usize;
+        let row = (y as u32 / (cell_size.1 * self.zoom)) as usize;
+        let (cols, rows) = self.visible(caview);
+        if col >= cols || row >= rows {
+            return None;
+        }
+        // undo the shift of the row, see cell_x
+        let shift = if hex {
+            ((self.oy + row) as u32) * cwidth / 2
+        } else {
+            0
+        };
+        Some(ca::nb::pixel_to_cell(x as i64 - shift as i64,
+                                   y as i64,
+                                   (cwidth, cell_size.1 * self.zoom),
+                                   (self.ox, self.oy),
+                                   caview.width(),
+                                   caview.height()))
+    }
+
+    // Pixel x of the left edge of the cell relative to the window. In hex layout every row
+    // is drawn half a cell to the right of the previous one, as hexagonal neighborhoods see
+    // it, and cells shifted beyond the right edge of the world continue at the left one.
+    fn cell_x(&self, caview: &Box<CAView>, row: usize, col: usize, cwidth: u32, hex: bool) -> i32 {
+        let shift = if hex {
+            (row as u32) * cwidth / 2
+        } else {
+            0
+        };
+        let x = ((col as u32) * cwidth + shift) % ((caview.width() as u32) * cwidth);
+        (x as i32) - ((self.ox as u32) * cwidth) as i32
+    }
+
+    // Changes zoom keeping the center of the view in place.
+    fn set_zoom(&mut self, caview: &Box<CAView>, zoom: u32) {
+        let zoom = cmp::max(1, cmp::min(zoom, MAX_ZOOM));
+        let (w, h) = (caview.width() as i64, caview.height() as i64);
+        let (old_zoom, new_zoom) = (self.zoom as i64, zoom as i64);
+        let cx = self.ox as i64 + w / old_zoom / 2;
+        let cy = self.oy as i64 + h / old_zoom / 2;
+        self.zoom = zoom;
+        self.ox = 0;
+        self.oy = 0;
+        self.pan(caview, cx - w / new_zoom / 2, cy - h / new_zoom / 2);
+    }
+}
+
+fn draw_grid(caview: &Box<CAView>,
+             renderer: &mut Renderer,
+             (cwidth, cheight): (u32, u32),
+             viewport: &Viewport) {
+    let (cols, rows) = viewport.visible(caview);
+    let w = (cols as u32 * cwidth) as i32;
+    let h = (rows as u32 * cheight) as i32;
+    renderer.set_draw_color(GRID_COLOR);
+    for col in 1..cols {
+        let x = ((col as u32) * cwidth) as i32;
+        renderer.draw_line(Point::new(x, 0), Point::new(x, h - 1)).unwrap();
+    }
+    for row in 1..rows {
+        let y = ((row as u32) * cheight) as i32;
+        renderer.draw_line(Point::new(0, y), Point::new(w - 1, y)).unwrap();
+    }
+}
+
+// Outlines the visible world edges that are walls.
+fn draw_border(caview: &Box<CAView>,
+               renderer: &mut Renderer,
+               (cwidth, cheight): (u32, u32),
+               viewport: &Viewport) {
+    let (cols, rows) = viewport.visible(caview);
+    let w = (cols as u32 * cwidth) as i32;
+    let h = (rows as u32 * cheight) as i32;
+    let (walls_x, walls_y) = caview.walls();
+    renderer.set_draw_color(BORDER_COLOR);
+    if walls_x {
+        if viewport.ox == 0 {
+            renderer.draw_line(Point::new(0, 0), Point::new(0, h - 1)).unwrap();
+        }
+        if viewport.ox + cols == caview.width() {
+            renderer.draw_line(Point::new(w - 1, 0), Point::new(w - 1, h - 1)).unwrap();
+        }
+    }
+    if walls_y {
+        if viewport.oy == 0 {
+            renderer.draw_line(Point::new(0, 0), Point::new(w - 1, 0)).unwrap();
+        }
+        if viewport.oy + rows == caview.height() {
+            renderer.draw_line(Point::new(0, h - 1), Point::new(w - 1, h - 1)).unwrap();
+        }
+    }
+}
+
+// Horizontal one pixel high spans filling the ellipse inscribed in w x h cell, relative to
+// the top left corner of the cell.
+fn ellipse_spans(w: u32, h: u32) -> Vec<Rect> {
+    let (rx, ry) = ((w as f64) / 2.0, (h as f64) / 2.0);
+    let mut spans = Vec::new();
+    for y in 0..h {
+        let dy = ((y as f64) + 0.5 - ry) / ry;
+        let half = rx * (1.0 - dy * dy).sqrt();
+        let (x1, x2) = ((rx - half).round() as i32, (rx + half).round() as i32);
+        if x2 > x1 {
+            spans.push(Rect::new(x1, y as i32, (x2 - x1) as u32, 1));
+        }
+    }
+    spans
+}
+
+// How cells are drawn in the window, hex shifts rows like Viewport::cell_x describes.
+struct CellStyle {
+    shape: CellShape,
+    hex: bool,
+}
+
+// Draws only cells visible through the viewport, cell_size is the size of not zoomed cell.
+fn draw_ca(caview: &Box<CAView>,
+           renderer: &mut Renderer,
+           cell_size: (u32, u32),
+           viewport: &Viewport,
+           grid: bool,
+           border: bool,
+           style: &CellStyle) {
+    let cwidth = cell_size.0 * viewport.zoom;
+    let cheight = cell_size.1 * viewport.zoom;
+    let (cols, rows) = viewport.visible(caview);
+    if viewport.zoom > 1 || style.shape != CellShape::Rect {
+        // visible cells may not cover the whole window
+        renderer.set_draw_color(caview.state_to_color(0));
+        renderer.clear();
+    }
+    let spans = ellipse_spans(cwidth, cheight);
+    // shifted rows may bring any cell into view
+    let (first_col, last_col) = if style.hex {
+        (0, caview.width())
+    } else {
+        (viewport.ox, viewport.ox + cols)
+    };
+    let view_width = (cols as i32) * (cwidth as i32);
+    for row in 0..rows {
+        let cells = caview.row(viewport.oy + row);
+        let y = ((row as u32) * cheight) as i32;
+        for col in first_col..last_col {
+            let x = viewport.cell_x(caview, viewport.oy + row, col, cwidth, style.hex);
+            if x + (cwidth as i32) <= 0 || x >= view_width {
+                continue;
+            }
+            let color = caview.cell_color(viewport.oy + row, col, cells[col]);
             renderer.set_draw_color(color);
-            let x = ((col as u32) * cwidth) as i32;
-            let y = ((row as u32) * cwidth) as i32;
-            renderer.fill_rect(Rect::new(x, y, cwidth, cwidth)).unwrap();
+            match style.shape {
+                CellShape::Rect => renderer.fill_rect(Rect::new(x, y, cwidth, cheight)).unwrap(),
+                CellShape::Circle => {
+                    let rects: Vec<Rect> = spans.iter()
+                        .map(|s| Rect::new(x + s.x(), y + s.y(), s.width(), s.height()))
+                        .collect();
+                    renderer.fill_rects(&rects).unwrap();
+                }
+            }
         }
     }
+    // grid lines don't follow shifted rows
+    if grid && !style.hex && cmp::min(cwidth, cheight) >= GRID_MIN_CELL_WIDTH {
+        draw_grid(caview, renderer, (cwidth, cheight), viewport);
+    }
+    if border {
+        draw_border(caview, renderer, (cwidth, cheight), viewport);
+    }
     renderer.present();
 }
 
+fn render_frame(caview: &Box<CAView>, (cwidth, cheight): (u32, u32)) -> export::Frame {
+    let w = (caview.width() as u32) * cwidth;
+    let h = (caview.height() as u32) * cheight;
+    let mut pixels: Vec<u8> = Vec::with_capacity((w * h * 3) as usize);
+    for row in 0..caview.height() {
+        let colors: Vec<(u8, u8, u8)> = caview.row(row)
+            .iter()
+            .enumerate()
+            .map(|(col, &cell)| caview.cell_color(row, col, cell).rgb())
+            .collect();
+        for _ in 0..cheight {
+            for &(r, g, b) in colors.iter() {
+                for _ in 0..cwidth {
+                    pixels.push(r);
+                    pixels.push(g);
+                    pixels.push(b);
+                }
+            }
+        }
+    }
+    export::Frame {
+        w: w,
+        h: h,
+        pixels: pixels,
+    }
+}
+
 fn get_abs_coord(origin: usize, shift: i32, limit: usize) -> Result<usize, &'static str> {
     let abs = (origin as i64) + (shift as i64);
     if abs < 0 || abs >= (limit as i64) {
@@ -249,120 +1435,506 @@ fn get_abs_coord(origin: usize, shift: i32, limit: usize) -> Result<usize, &'sta
     Ok(abs as usize)
 }
 
-fn points1d_to_coords(points: Vec<config::Point1D>,
-                      ca_width: usize)
-                      -> Result<Vec<usize>, &'static str> {
-    let c = ca_width / 2;
-    let mut coords: Vec<usize> = Vec::new();
+// Points of 1D CA are in the only row of a world with ca_height 1.
+fn points_to_coords(points: Vec<ca::spec::Point>,
+                    ca_width: usize,
+                    ca_height: usize)
+                    -> Result<Vec<(usize, usize)>, &'static str> {
+    let c = (ca_width / 2, ca_height / 2);
+    let mut coords: Vec<(usize, usize)> = Vec::new();
     for p in points {
         let coord = match p {
-            config::Point1D::Abs(i) => i,
-            config::Point1D::RelToCenter(shift) => try!(get_abs_coord(c, shift, ca_width)),
+            ca::spec::Point::Abs(x, y) => (x, y.unwrap_or(0)),
+            ca::spec::Point::RelToCenter(x, y) => {
+                (try!(get_abs_coord(c.0, x, ca_width)),
+                 try!(get_abs_coord(c.1, y.unwrap_or(0), ca_height)))
+            }
+            ca::spec::Point::Center => c,
         };
         coords.push(coord);
     }
     Ok(coords)
 }
 
-fn points2d_to_coords(points: Vec<config::Point2D>,
-                      ca_width: usize,
-                      ca_height: usize)
-                      -> Result<Vec<(usize, usize)>, &'static str> {
-    let c = (ca_width / 2, ca_height / 2);
-    let mut coords: Vec<(usize, usize)> = Vec::new();
-    for p in points {
-        let coord = match p {
-            config::Point2D::Abs(x, y) => (x, y),
-            config::Point2D::RelToCenter(x, y) => {
-                (try!(get_abs_coord(c.0, x, ca_width)), try!(get_abs_coord(c.1, y, ca_height)))
+// Explicit states of random init or every state of the CA.
+fn random_states(states: Option<Vec<ca::types::Cell>>, count: u32) -> Vec<ca::types::Cell> {
+    states.unwrap_or_else(|| (0..count).map(|state| state as ca::types::Cell).collect())
+}
+
+fn get_ca1_view(cfg: config::Config,
+                ca_width: usize,
+                ca_height: usize,
+                palette: Vec<Color>)
+                -> Result<Box<CAView>, String> {
+    let cells = match cfg.init_type {
+        InitSpec::Random { states, x1, x2, .. } => {
+            let states = random_states(states, cfg.ca_type.states());
+            match cfg.seed {
+                Some(seed) => ca::gen::random1d_seeded(seed, ca_width, states, x1, x2),
+                None => ca::gen::random1d(ca_width, states, x1, x2),
+            }
+        }
+        InitSpec::Points(points) => {
+            let (points, states): (Vec<_>, Vec<_>) = points.into_iter().unzip();
+            let coords = try!(points_to_coords(points, ca_width, 1));
+            let coords = coords.into_iter().map(|(x, _)| x);
+            ca::gen::points1d_with_state(ca_width, coords.zip(states).collect())
+        }
+        InitSpec::Binary(states, align) => ca::gen::aligned1d(ca_width, &states, align),
+        _ => unreachable!(),
+    };
+    let mut ca_type = cfg.ca_type;
+    if cfg.random_rule {
+        ca_type = match ca_type {
+            RuleSpec::Elementary(_) => RuleSpec::Elementary(random_elementary_code()),
+            RuleSpec::CA1 { radius, states, .. } => {
+                RuleSpec::CA1 {
+                    radius: radius,
+                    states: states,
+                    code: Some(try!(ca::random_ca1_code(radius.0, radius.1, states))),
+                }
             }
+            ca_type => ca_type,
         };
-        coords.push(coord);
     }
-    Ok(coords)
+    let rule = match ca_type {
+        RuleSpec::Elementary(code) => Ca1ViewRule::Elementary(code),
+        RuleSpec::CA1 { radius, states, code: Some(ref code) } => {
+            Ca1ViewRule::General {
+                radius: radius,
+                states: states,
+                code: code.clone(),
+            }
+        }
+        _ => Ca1ViewRule::Unknown,
+    };
+    let boundary = ca::nb::Boundary::default();
+    let ca = match ca_type {
+        RuleSpec::Elementary(code) => ca::CA1::new_elementary(cells, code),
+        RuleSpec::CA1 { radius, states, code } => {
+            try!(ca::CA1::new_ca1_asymmetric(cells, radius, states, code, boundary))
+        }
+        RuleSpec::CA1Totalistic { radius, states, code } => {
+            try!(ca::CA1::new_ca1_totalistic(cells, radius, states, code, boundary))
+        }
+        RuleSpec::CA1OuterTotalistic { radius, states, code } => {
+            try!(ca::CA1::new_ca1_outer_totalistic(cells, radius, states, code, boundary))
+        }
+        _ => unreachable!(),
+    };
+    let ca = ca.with_history(cfg.history.unwrap_or(0));
+    let scrollback = cfg.scrollback.unwrap_or(ca_height);
+    Ok(Box::new(CA1View::new(ca, palette, ca_height, scrollback).with_rule(rule)))
 }
 
-fn get_ca_view(cfg: config::Config,
-               ca_width: usize,
-               ca_height: usize,
-               palette: Vec<Color>)
-               -> Result<Box<CAView>, String> {
-    match cfg.ca_type {
-        CAType::Elementary(..) |
-        CAType::CA1 { .. } => {
-            let cells = match cfg.init_type {
-                InitType::Random { states, x1, x2, .. } => {
-                    ca::gen::random1d(ca_width, states, x1, x2)
-                }
-                InitType::Points1D(points) => {
-                    let coords = try!(points1d_to_coords(points, ca_width));
-                    ca::gen::points1d(ca_width, coords)
-                }
-                _ => unreachable!(),
-            };
-            let ca = match cfg.ca_type {
-                CAType::Elementary(code) => ca::CA1::new_elementary(cells, code),
-                CAType::CA1 { radius, states, code } => {
-                    try!(ca::CA1::new_ca1(cells, radius, states, code))
+fn random_elementary_code() -> u8 {
+    u8::from_str_radix(&ca::random_ca1_code(1, 1, 2).unwrap(), 2).unwrap()
+}
+
+fn get_ca2_cells(init_type: InitSpec,
+                 ca_states: u32,
+                 seed: Option<u64>,
+                 symmetry: ca::gen::Symmetry,
+                 ca_width: usize,
+                 ca_height: usize)
+                 -> Result<Vec<Vec<ca::types::Cell>>, String> {
+    Ok(match init_type {
+        InitSpec::Random { states, x1, x2, y1, y2 } => {
+            let states = random_states(states, ca_states);
+            let mut cells = match seed {
+                Some(seed) => {
+                    ca::gen::random2d_seeded(seed, ca_width, ca_height, states, x1, x2, y1, y2)
                 }
-                _ => unreachable!(),
+                None => ca::gen::random2d(ca_width, ca_height, states, x1, x2, y1, y2),
             };
-            Ok(Box::new(CA1View::new(ca, palette, ca_height)))
+            ca::gen::symmetrize(&mut cells, symmetry);
+            cells
         }
-        _ => {
-            let cells = match cfg.init_type {
-                InitType::Random { states, x1, x2, y1, y2 } => {
-                    ca::gen::random2d(ca_width, ca_height, states, x1, x2, y1, y2)
+        InitSpec::RandomDensity(density) => {
+            let mut cells = match seed {
+                Some(seed) => {
+                    let mut rng = ca::gen::seeded_rng(seed);
+                    ca::gen::random2d_density(ca_width, ca_height, density, &mut rng)
                 }
-                InitType::Points2D(points) => {
-                    let coords = try!(points2d_to_coords(points, ca_width, ca_height));
-                    ca::gen::points2d(ca_width, ca_height, coords)
+                None => {
+                    let mut rng = rand::thread_rng();
+                    ca::gen::random2d_density(ca_width, ca_height, density, &mut rng)
                 }
-                _ => unreachable!(),
             };
-            let ca = match cfg.ca_type {
-                CAType::Cyclic(nbh, threshold, states) => {
-                    ca::CA2::new_cyclic(cells, nbh, threshold, states)
-                }
-                CAType::Life(survive, birth) => ca::CA2::new_life(cells, survive, birth),
-                _ => unreachable!(),
-            };
-            Ok(Box::new(CA2View::new(ca, palette)))
-        }
-    }
-}
-
-fn make_palette() -> Vec<Color> {
-    vec![
-        Color::RGB(0, 0, 0),
-        Color::RGB(200, 200, 0),
-	    Color::RGB(0, 153, 255),
-	    Color::RGB(0, 255, 153),
-	    Color::RGB(51, 255, 0),
-	    Color::RGB(255, 255, 0),
-	    Color::RGB(255, 51, 0),
-	    Color::RGB(255, 0, 153),
-	    Color::RGB(182, 0, 255),
-	    Color::RGB(37, 0, 255),
-	    Color::RGB(0, 102, 255),
-	    Color::RGB(0, 255, 204),
-	    Color::RGB(0, 255, 0),
-	    Color::RGB(204, 255, 0),
-	    Color::RGB(255, 102, 0),
-	    Color::RGB(255, 0, 102),
-	    Color::RGB(219, 0, 255),
-	    Color::RGB(73, 0, 255),
-    ]
+            ca::gen::symmetrize(&mut cells, symmetry);
+            cells
+        }
+        InitSpec::Points(points) => {
+            let (points, states): (Vec<_>, Vec<_>) = points.into_iter().unzip();
+            let coords = try!(points_to_coords(points, ca_width, ca_height));
+            let points = coords.into_iter().zip(states).collect();
+            ca::gen::points2d_with_state(ca_width, ca_height, points)
+        }
+        InitSpec::Patterns(patterns) => {
+            let (names, points): (Vec<_>, Vec<_>) = patterns.into_iter().unzip();
+            let coords = try!(points_to_coords(points, ca_width, ca_height));
+            let mut cells = vec![vec![0; ca_width]; ca_height];
+            for (name, (x, y)) in names.into_iter().zip(coords) {
+                let pattern = ca::patterns::by_name(&name).unwrap();
+                ca::gen::stamp(&mut cells, &pattern, y, x);
+            }
+            cells
+        }
+        InitSpec::Rle(path) => {
+            let center = (ca_width / 2, ca_height / 2);
+            let cells = try!(ca::gen::from_rle(&path, ca_width, ca_height, center));
+            if cells.iter().any(|row| row.iter().any(|&cell| cell as u32 >= ca_states)) {
+                return Err(String::from("rle: state must be less than count of states!"));
+            }
+            cells
+        }
+        InitSpec::Plaintext(path) => {
+            let center = (ca_width / 2, ca_height / 2);
+            try!(ca::gen::from_plaintext(&path, ca_width, ca_height, center))
+        }
+        InitSpec::Image(path) => {
+            try!(ca::gen::from_image(&path, ca_width, ca_height, ca_states))
+        }
+        InitSpec::Csv(path) => {
+            let grid = try!(ca::gen::from_csv(&path));
+            if grid.iter().any(|row| row.iter().any(|&cell| cell as u32 >= ca_states)) {
+                return Err(String::from("csv: state must be less than count of states!"));
+            }
+            // the grid starts at the top left corner like in a spreadsheet
+            let mut cells = vec![vec![0; ca_width]; ca_height];
+            ca::gen::stamp(&mut cells, &grid, 0, 0);
+            cells
+        }
+        _ => unreachable!(),
+    })
+}
+
+fn get_ca2_view(cfg: config::Config,
+                ca_width: usize,
+                ca_height: usize,
+                palette: Vec<Color>)
+                -> Result<Box<CAView>, String> {
+    let states = cfg.ca_type.states();
+    let cells =
+        try!(get_ca2_cells(cfg.init_type, states, cfg.seed, cfg.symmetry, ca_width, ca_height));
+    if cfg.growing {
+        if let RuleSpec::Life(survive, birth) = cfg.ca_type {
+            let ca = ca::GrowingLife::new(cells, survive, birth);
+            return Ok(Box::new(GrowingLifeView::new(ca, palette, ca_width, ca_height)));
+        }
+    }
+    let boundary = cfg.topology.unwrap_or_default();
+    let ca = try!(match cfg.ca_type {
+        RuleSpec::CA2Totalistic(nbh, states, code) => {
+            ca::CA2::new_ca2_totalistic(cells, nbh, states, code, boundary)
+        }
+        RuleSpec::Cyclic(nbh, threshold, states) => {
+            ca::CA2::new_cyclic(cells, nbh, threshold, states, boundary)
+        }
+        RuleSpec::Life(survive, birth) => ca::CA2::new_life(cells, survive, birth, boundary),
+        RuleSpec::Ltl(range, birth, survive) => {
+            ca::CA2::new_ltl(cells, range, birth, survive, boundary)
+        }
+        RuleSpec::Generations(survive, birth, states) => {
+            ca::CA2::new_generations(cells, survive, birth, states, boundary)
+        }
+        RuleSpec::BriansBrain => ca::CA2::new_brians_brain(cells, boundary),
+        RuleSpec::Wireworld => ca::CA2::new_wireworld(cells, boundary),
+        _ => unreachable!(),
+    });
+    let mut ca = ca.with_history(cfg.history.unwrap_or(0)).with_update(cfg.update);
+    if let Some(seed) = cfg.seed {
+        ca = ca.with_update_rng(ca::gen::seeded_rng(seed));
+    }
+    if cfg.age_colors {
+        ca = ca.with_ages();
+    }
+    Ok(Box::new(CA2View::new(ca, palette, boundary)))
+}
+
+fn get_coupled_view(cfg: config::Config,
+                    ca_width: usize,
+                    ca_height: usize,
+                    palette: Vec<Color>)
+                    -> Result<Box<CAView>, String> {
+    let states = cfg.ca_type.states();
+    let (survive, birth) = match cfg.ca_type {
+        RuleSpec::Coupled(survive, birth) => (survive, birth),
+        _ => unreachable!(),
+    };
+    // random init fills the second layer with the next seed, so layers differ
+    let seed = cfg.seed;
+    let layer0 = try!(get_ca2_cells(cfg.init_type.clone(),
+                                    states,
+                                    seed,
+                                    cfg.symmetry,
+                                    ca_width,
+                                    ca_height));
+    let layer1 = try!(get_ca2_cells(cfg.init_type,
+                                    states,
+                                    seed.map(|seed| seed.wrapping_add(1)),
+                                    cfg.symmetry,
+                                    ca_width,
+                                    ca_height));
+    let boundary = cfg.topology.unwrap_or_default();
+    let ca = try!(ca::CoupledCA2::new_coupled_life(layer0, layer1, survive, birth, boundary));
+    let mut palettes = [palette.clone(), palette::rotate_channels(&palette)];
+    // background comes from the first layer only
+    palettes[1][0] = Color::RGB(0, 0, 0);
+    Ok(Box::new(CoupledView::new(ca, palettes, 2)))
+}
+
+fn get_ant_view(cfg: config::Config,
+                ca_width: usize,
+                ca_height: usize,
+                palette: Vec<Color>)
+                -> Result<Box<CAView>, String> {
+    let states = cfg.ca_type.states();
+    let turns = match cfg.ca_type {
+        RuleSpec::Ant(turns) => turns,
+        _ => unreachable!(),
+    };
+    let (cells, ants) = match cfg.init_type {
+        InitSpec::Points(points) => {
+            // ants don't have states
+            let points = points.into_iter().map(|(point, _)| point).collect();
+            let coords = try!(points_to_coords(points, ca_width, ca_height));
+            let ants = coords.iter().map(|&(x, y)| ca::Ant::new(y, x)).collect();
+            (vec![vec![0; ca_width]; ca_height], ants)
+        }
+        init_type => {
+            let cells = try!(get_ca2_cells(init_type,
+                                           states,
+                                           cfg.seed,
+                                           cfg.symmetry,
+                                           ca_width,
+                                           ca_height));
+            (cells, vec![ca::Ant::new(ca_height / 2, ca_width / 2)])
+        }
+    };
+    let ant = try!(ca::LangtonsAnt::new(cells, ants, &turns));
+    Ok(Box::new(AntView::new(ant, palette)))
+}
+
+// Tiles are laid out in a square-ish grid with one cell wide gaps between them.
+fn get_tiled_view(mut cfg: config::Config,
+                  ca_width: usize,
+                  ca_height: usize,
+                  palette: Vec<Color>)
+                  -> Result<Box<CAView>, String> {
+    let mut specs = vec![(cfg.ca_type.clone(), cfg.init_type.clone())];
+    specs.extend(cfg.tiles.drain(..));
+    let cols = (1..).find(|&cols| cols * cols >= specs.len()).unwrap();
+    let rows = (specs.len() + cols - 1) / cols;
+    let tile_width = ca_width.saturating_sub(cols - 1) / cols;
+    let tile_height = ca_height.saturating_sub(rows - 1) / rows;
+    if tile_width == 0 || tile_height == 0 {
+        return Err(format!("World is too small for {} CA!", specs.len()));
+    }
+    let states = specs.iter().map(|&(ref ca_type, _)| ca_type.states()).collect();
+    let mut tiles = Vec::new();
+    for (ca_type, init_type) in specs {
+        let tile_cfg = config::Config {
+            ca_type: ca_type,
+            init_type: init_type,
+            ..cfg.clone()
+        };
+        tiles.push(try!(get_ca_view(tile_cfg, tile_width, tile_height, palette.clone())));
+    }
+    let tile_size = (tile_width, tile_height);
+    Ok(Box::new(TiledView::new(tiles, states, cols, tile_size, ca_width, ca_height)))
+}
+
+fn get_ca_view(cfg: config::Config,
+               ca_width: usize,
+               ca_height: usize,
+               palette: Vec<Color>)
+               -> Result<Box<CAView>, String> {
+    if !cfg.tiles.is_empty() {
+        // every tile skips ticks by itself
+        return get_tiled_view(cfg, ca_width, ca_height, palette);
+    }
+    let skip = cfg.skip.unwrap_or(0);
+    let mut ca_view = try!(if cfg.ca_type.is_1d() {
+        get_ca1_view(cfg, ca_width, ca_height, palette)
+    } else if let RuleSpec::Ant(..) = cfg.ca_type {
+        get_ant_view(cfg, ca_width, ca_height, palette)
+    } else if let RuleSpec::Coupled(..) = cfg.ca_type {
+        get_coupled_view(cfg, ca_width, ca_height, palette)
+    } else {
+        get_ca2_view(cfg, ca_width, ca_height, palette)
+    });
+    ca_view.tick_n(skip);
+    Ok(ca_view)
 }
 
 fn print_help(opts: &Options) {
     let short_usage_prefix = format!("{} TYPE", &env::args().nth(0).unwrap());
-    let usage_prefix = format!("{}\n\n{}",
+    let usage_prefix = format!("{}\n\n{}\n\n{}",
                                opts.short_usage(&short_usage_prefix),
-                               USAGE_TYPE);
+                               USAGE_TYPE,
+                               USAGE_KEYS);
     println!("{}", opts.usage(&usage_prefix))
 }
 
+// Whether the view stopped changing or repeats one of recent generations, see --until-stable.
+fn is_stable(ca_view: &CAView, tracker: &mut ca::PeriodTracker) -> bool {
+    ca_view.frozen() || ca_view.state_hash().map_or(false, |hash| tracker.push(hash).is_some())
+}
+
+// Runs CA without window and passes its frames to record, for --gif and --ppm-stream.
+fn run_frames<F>(cfg: config::Config, palette: Vec<Color>, mut record: F) -> Result<(), String>
+    where F: FnMut(&export::Frame) -> Result<(), String>
+{
+    let (width, height) = cfg.size.unwrap();
+    let cell_size = try!(get_cell_size(width, height, cfg.cell_size));
+    let until_stable = cfg.until_stable;
+    let frames = if until_stable {
+        cfg.max_ticks.unwrap_or(DEFAULT_MAX_TICKS)
+    } else {
+        cfg.frames.unwrap_or(100)
+    };
+    let ca_width = (width / cell_size.0) as usize;
+    let ca_height = (height / cell_size.1) as usize;
+    let mut ca_view = try!(get_ca_view(cfg, ca_width, ca_height, palette));
+    let mut tracker = ca::PeriodTracker::new(STABLE_MAX_PERIOD);
+    for _ in 0..frames {
+        // repeated generation isn't recorded, so animation of a cycle loops seamlessly
+        if until_stable && is_stable(&*ca_view, &mut tracker) {
+            break;
+        }
+        try!(record(&render_frame(&ca_view, cell_size)));
+        ca_view.tick();
+    }
+    Ok(())
+}
+
+fn run_gif(cfg: config::Config, palette: Vec<Color>, path: &str) -> Result<(), String> {
+    let (width, height) = cfg.size.unwrap();
+    let delay = match cfg.delay {
+        None => 5,
+        Some(d) => d,
+    };
+    let mut rgb_palette: Vec<(u8, u8, u8)> = palette.iter().map(|c| c.rgb()).collect();
+    // several CA are drawn with gaps between them
+    if !cfg.tiles.is_empty() && !rgb_palette.contains(&TILE_GAP_COLOR.rgb()) {
+        rgb_palette.push(TILE_GAP_COLOR.rgb());
+    }
+    let mut recorder = try!(export::GifRecorder::new(path, width, height, &rgb_palette, delay));
+    run_frames(cfg, palette, |frame| recorder.record(frame))
+}
+
+fn run_ppm_stream(cfg: config::Config, palette: Vec<Color>) -> Result<(), String> {
+    let stdout = io::stdout();
+    let mut stream = export::PpmStream::new(stdout.lock());
+    run_frames(cfg, palette, |frame| stream.record(frame))
+}
+
+// Summary of --headless run.
+struct Report {
+    ticks: u32,
+    population: Vec<usize>,
+    period: Option<usize>,
+    seconds: f64,
+    seed: Option<u64>,
+}
+
+impl Report {
+    fn to_text(&self) -> String {
+        let population: Vec<String> = self.population.iter().map(|c| c.to_string()).collect();
+        let mut text = format!("ticks: {}\npopulation: {}\n", self.ticks, population.join(" "));
+        if let Some(period) = self.period {
+            text.push_str(&format!("period: {}\n", period));
+        }
+        text.push_str(&format!("time: {:.3} s\n", self.seconds));
+        text
+    }
+
+    fn to_json(&self) -> String {
+        let population: Vec<String> = self.population.iter().map(|c| c.to_string()).collect();
+        let option = |x: Option<String>| x.unwrap_or(String::from("null"));
+        format!("{{\"ticks\":{},\"population\":[{}],\"period\":{},\"seconds\":{:.3},\
+                 \"seed\":{}}}",
+                self.ticks,
+                population.join(","),
+                option(self.period.map(|p| p.to_string())),
+                self.seconds,
+                option(self.seed.map(|s| s.to_string())))
+    }
+}
+
+fn run_headless(cfg: config::Config, palette: Vec<Color>, path: &str) -> Result<(), String> {
+    let (width, height) = cfg.size.unwrap();
+    let cell_size = try!(get_cell_size(width, height, cfg.cell_size));
+    let until_stable = cfg.until_stable;
+    let ticks = if until_stable {
+        cfg.max_ticks.unwrap_or(DEFAULT_MAX_TICKS)
+    } else {
+        cfg.ticks.unwrap_or(100)
+    };
+    let report_json = cfg.report_json;
+    let seed = cfg.seed;
+    let ca_width = (width / cell_size.0) as usize;
+    let ca_height = (height / cell_size.1) as usize;
+    let started = Instant::now();
+    let mut ca_view = try!(get_ca_view(cfg, ca_width, ca_height, palette));
+    let mut tracker = ca::PeriodTracker::new(STABLE_MAX_PERIOD);
+    let mut period = None;
+    let mut ticked = 0;
+    while ticked < ticks {
+        // the first detected cycle is reported, later generations just repeat it
+        if period.is_none() {
+            period = ca_view.state_hash().and_then(|hash| tracker.push(hash));
+        }
+        if until_stable && (period.is_some() || ca_view.frozen()) {
+            break;
+        }
+        ca_view.tick();
+        ticked += 1;
+        if ca_view.frozen() {
+            break;
+        }
+    }
+    if period.is_none() && ca_view.frozen() {
+        period = Some(1);
+    }
+    try!(ca_view.save(path));
+    let elapsed = started.elapsed();
+    let report = Report {
+        ticks: ticked,
+        population: ca_view.population(),
+        period: period,
+        seconds: elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1e9,
+        seed: seed,
+    };
+    if report_json {
+        println!("{}", report.to_json());
+    } else {
+        print!("{}", report.to_text());
+    }
+    Ok(())
+}
+
+// Prints new state for every neighborhood of 1D CA, e.g. 110 -> 1.
+fn dump_rule(ca_type: &RuleSpec) -> Result<(), String> {
+    let table = match *ca_type {
+        RuleSpec::CA1 { radius, states, ref code } => {
+            ca::ca1_rule_table(radius.0, radius.1, states, code.clone())
+        }
+        RuleSpec::Elementary(code) => ca::ca1_rule_table(1, 1, 2, Some(format!("{:08b}", code))),
+        _ => unreachable!(),
+    };
+    let table = try!(table);
+    let states = ca_type.states();
+    for (neighborhood, state) in table {
+        let neighborhood: String = neighborhood.into_iter()
+            .map(|cell| std::char::from_digit(cell as u32, states).unwrap())
+            .collect();
+        println!("{} -> {}", neighborhood, std::char::from_digit(state as u32, states).unwrap());
+    }
+    Ok(())
+}
+
 fn execute(opts: &Options) -> Result<(), String> {
     let matches = try!(opts.parse(env::args().skip(1))
         .map_err(|fail| String::from(fail.description())));
@@ -370,35 +1942,202 @@ fn execute(opts: &Options) -> Result<(), String> {
         print_help(opts);
         return Ok(());
     }
-    let cfg = try!(config::Config::from_matches(&matches));
-    let palette = make_palette();
+    let mut cfg = try!(config::Config::from_matches(&matches));
+    if cfg.dump_rule {
+        return dump_rule(&cfg.ca_type);
+    }
+    if cfg.init_type.is_random() || cfg.update.is_random() {
+        // printed in the form of the option, so the run can be repeated
+        let seed = cfg.seed.unwrap_or_else(ca::gen::random_seed);
+        eprintln!("--seed {}", seed);
+        cfg.seed = Some(seed);
+    }
+    let states = cfg.states() as usize;
+    let mut palette = match cfg.palette {
+        Some(ref colors) => {
+            if colors.len() < states {
+                return Err(format!("Palette has {} colors, but CA has {} states!",
+                                   colors.len(),
+                                   states));
+            }
+            colors.iter().map(|&(r, g, b)| Color::RGB(r, g, b)).collect()
+        }
+        None if cfg.age_colors => palette::age_palette(AGE_COLORS),
+        None => palette::palette_for(states),
+    };
+    if let Some((r, g, b)) = cfg.bg {
+        palette[0] = Color::RGB(r, g, b);
+    }
+    if let Some(path) = cfg.gif.clone() {
+        return run_gif(cfg, palette, &path);
+    }
+    if cfg.ppm_stream {
+        return run_ppm_stream(cfg, palette);
+    }
+    if cfg.headless {
+        let path = cfg.out.clone().unwrap();
+        return run_headless(cfg, palette, &path);
+    }
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
     let window = try!(make_window(&video_subsystem, cfg.size));
     let (width, height) = window.size();
-    let cell_width = try!(get_cell_width(width, height, cfg.cell_width));
+    let cell_size = try!(get_cell_size(width, height, cfg.cell_size));
     let mut timer_subsystem = sdl_context.timer().unwrap();
-    let delay = match cfg.delay {
+    let mut delay = match cfg.delay {
         None => 5,
         Some(d) => d,
     };
+    let frame_interval = 1000 / cfg.fps.unwrap_or(DEFAULT_FPS);
     let mut renderer = window.renderer().build().unwrap();
-    let ca_width = (width / cell_width) as usize;
-    let ca_height = (height / cell_width) as usize;
+    let ca_width = (width / cell_size.0) as usize;
+    let ca_height = (height / cell_size.1) as usize;
+    let mut recorder = match cfg.record {
+        Some(ref dir) => Some(try!(export::FrameRecorder::new(dir))),
+        None => None,
+    };
+    let states = cfg.states();
+    let border = cfg.border;
+    let style = CellStyle {
+        shape: cfg.shape,
+        hex: cfg.shape != CellShape::Rect && cfg.ca_type.is_hex() && cfg.tiles.is_empty(),
+    };
     let mut ca_view = try!(get_ca_view(cfg, ca_width, ca_height, palette));
 
     let mut event_pump = sdl_context.event_pump().unwrap();
+    let mut stepped_back = false;
+    let mut paused = false;
+    let mut grid = false;
+    let mut viewport = Viewport::new();
+    let mut title_reset_at: Option<u32> = None;
+    set_title(&mut renderer, &ca_view.title());
+    let mut next_tick = timer_subsystem.ticks();
     'running: loop {
+        let frame_start = timer_subsystem.ticks();
+        let mut step = false;
+        let mut rule_changed = false;
+        let old_delay = delay;
         for event in event_pump.poll_iter() {
             match event {
                 Event::Quit { .. } |
                 Event::KeyDown { keycode: Some(Keycode::Escape), .. } => break 'running,
+                Event::MouseButtonDown { mouse_btn, x, y, .. } if paused => {
+                    let cell = viewport.cell_at_pixel(&ca_view, x, y, cell_size, style.hex);
+                    if let Some((row, col)) = cell {
+                        let state = ca_view.row(row)[col];
+                        let state = match mouse_btn {
+                            Mouse::Left if state == 0 => 1,
+                            Mouse::Left => 0,
+                            Mouse::Right => ((state as u32 + 1) % states) as ca::types::Cell,
+                            _ => state,
+                        };
+                        ca_view.set(row, col, state);
+                    }
+                }
+                Event::Window { win_event_id: WindowEventId::Resized, data1, data2, .. } => {
+                    // CA with fixed size stays in the top left corner of the window
+                    let w = cmp::max(1, data1 as u32 / cell_size.0) as usize;
+                    let h = cmp::max(1, data2 as u32 / cell_size.1) as usize;
+                    if ca_view.resize(w, h) {
+                        viewport = Viewport::new();
+                    }
+                }
+                Event::MouseWheel { y, .. } => {
+                    let zoom = (viewport.zoom as i32 + y.signum()) as u32;
+                    viewport.set_zoom(&ca_view, zoom);
+                }
+                // arrows pan zoomed view, otherwise they are bound to commands below
+                Event::KeyDown { keycode: Some(Keycode::Left), .. } if viewport.zoom > 1 => {
+                    viewport.pan(&ca_view, -PAN_STEP, 0);
+                }
+                Event::KeyDown { keycode: Some(Keycode::Right), .. } if viewport.zoom > 1 => {
+                    viewport.pan(&ca_view, PAN_STEP, 0);
+                }
+                Event::KeyDown { keycode: Some(Keycode::Up), .. } if viewport.zoom > 1 => {
+                    viewport.pan(&ca_view, 0, -PAN_STEP);
+                }
+                Event::KeyDown { keycode: Some(Keycode::Down), .. } if viewport.zoom > 1 => {
+                    viewport.pan(&ca_view, 0, PAN_STEP);
+                }
+                Event::KeyDown { keycode: Some(Keycode::Left), .. } => {
+                    stepped_back = ca_view.step_back() || stepped_back;
+                }
+                Event::KeyDown { keycode: Some(Keycode::Space), .. } => paused = !paused,
+                Event::KeyDown { keycode: Some(Keycode::G), .. } => grid = !grid,
+                Event::KeyDown { keycode: Some(Keycode::S), .. } => {
+                    match ca_view.to_rle() {
+                        Ok(rle) => print!("{}", rle),
+                        Err(err) => eprintln!("{}", err),
+                    }
+                }
+                Event::KeyDown { keycode: Some(Keycode::Up), .. } => ca_view.scroll(1),
+                Event::KeyDown { keycode: Some(Keycode::Down), .. } => ca_view.scroll(-1),
+                Event::KeyDown { keycode: Some(Keycode::N), .. } |
+                Event::KeyDown { keycode: Some(Keycode::Right), .. } => step = paused,
+                Event::KeyDown { keycode: Some(Keycode::Plus), .. } |
+                Event::KeyDown { keycode: Some(Keycode::Equals), .. } |
+                Event::KeyDown { keycode: Some(Keycode::KpPlus), .. } => {
+                    delay = delay.saturating_sub(DELAY_STEP);
+                }
+                Event::KeyDown { keycode: Some(Keycode::Minus), .. } |
+                Event::KeyDown { keycode: Some(Keycode::KpMinus), .. } => {
+                    delay = cmp::min(delay.saturating_add(DELAY_STEP), MAX_DELAY);
+                }
+                Event::KeyDown { keycode: Some(Keycode::LeftBracket), .. } => {
+                    rule_changed = ca_view.step_rule(-1) || rule_changed;
+                }
+                Event::KeyDown { keycode: Some(Keycode::RightBracket), .. } => {
+                    rule_changed = ca_view.step_rule(1) || rule_changed;
+                }
+                Event::KeyDown { keycode: Some(Keycode::R), .. } => {
+                    rule_changed = ca_view.random_rule() || rule_changed;
+                }
                 _ => {}
             }
         }
-        draw_ca(&ca_view, &mut renderer, cell_width);
-        ca_view.tick();
-        timer_subsystem.delay(delay);
+        if delay != old_delay {
+            set_title(&mut renderer, &format!("{} - delay {} ms", ca_view.title(), delay));
+            title_reset_at = Some(timer_subsystem.ticks() + TITLE_DURATION);
+        } else if rule_changed ||
+                  title_reset_at.map_or(false, |t| timer_subsystem.ticks() >= t) {
+            set_title(&mut renderer, &ca_view.title());
+            title_reset_at = None;
+        }
+        draw_ca(&ca_view, &mut renderer, cell_size, &viewport, grid, border, &style);
+        if let Some(ref mut recorder) = recorder {
+            try!(recorder.record(&render_frame(&ca_view, cell_size)));
+        }
+        // ticks follow their own schedule, frames are drawn at fixed rate regardless of it
+        let now = timer_subsystem.ticks();
+        if paused {
+            // resumed CA doesn't try to catch up with the pause
+            next_tick = now;
+        } else if stepped_back {
+            // show restored generation for a while instead of ticking it away at once
+            next_tick = now + STEP_BACK_DELAY;
+        }
+        stepped_back = false;
+        let mut ticks = 0;
+        while (step || (!paused && next_tick <= now)) && ticks < MAX_TICKS_PER_FRAME {
+            ca_view.tick();
+            step = false;
+            ticks += 1;
+            next_tick = next_tick.saturating_add(delay);
+            // nothing will change anymore, pause instead of redrawing the same picture
+            if ca_view.frozen() {
+                paused = true;
+            }
+        }
+        if next_tick <= now {
+            next_tick = now;
+        }
+        let elapsed = timer_subsystem.ticks() - frame_start;
+        if elapsed < frame_interval {
+            timer_subsystem.delay(frame_interval - elapsed);
+        }
+    }
+    if let Some(recorder) = recorder {
+        println!("Recorded {} frames.", recorder.frames());
     }
     Ok(())
 }
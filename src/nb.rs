@@ -1,8 +1,106 @@
 use types::Cell;
 
+#[derive(Clone, PartialEq, Debug)]
 pub enum Neighborhood {
     Moore(u32),
     VonNeumann(u32),
+    Hex(u32),
+    // (drow, dcol) offsets of neighbors relative to the cell
+    Custom(Vec<(i64, i64)>),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Boundary {
+    Wrap,
+    Constant(Cell),
+    Reflect,
+    Skip,
+    // Wraps like Wrap and shifts the other axis by shift cells on every crossing, so that
+    // the world becomes a helix. Twisted x with shift 1 continues the right edge at the left
+    // edge one row down. Without the other axis (in 1D CA) it is the same as Wrap.
+    Twist { shift: i64 },
+}
+
+impl Neighborhood {
+    // (drow, dcol) offsets of neighbors in the order neighborhood iterators yield them.
+    pub fn offsets(&self) -> Vec<(i64, i64)> {
+        let (range, shape): (u32, Shape) = match *self {
+            Neighborhood::Moore(range) => (range, moore_shape),
+            Neighborhood::VonNeumann(range) => (range, von_neumann_shape),
+            Neighborhood::Hex(range) => (range, hex_shape),
+            Neighborhood::Custom(ref offsets) => return offsets.clone(),
+        };
+        let range = range as i64;
+        let mut offsets = Vec::new();
+        for drow in -range..(range + 1) {
+            for dcol in -range..(range + 1) {
+                if (drow != 0 || dcol != 0) && shape(drow, dcol, range) {
+                    offsets.push((drow, dcol));
+                }
+            }
+        }
+        offsets
+    }
+
+    // Count of neighbors without the cell itself, the same as offsets().len(). Offsets of
+    // custom neighborhood are all counted, even (0, 0).
+    pub fn size(&self) -> usize {
+        match *self {
+            Neighborhood::Moore(range) => {
+                let side = 2 * (range as usize) + 1;
+                side * side - 1
+            }
+            Neighborhood::VonNeumann(range) => 2 * (range as usize) * (range as usize + 1),
+            Neighborhood::Hex(range) => 3 * (range as usize) * (range as usize + 1),
+            Neighborhood::Custom(ref offsets) => offsets.len(),
+        }
+    }
+}
+
+impl Boundary {
+    // Whether cells beyond the edges are taken from the opposite side of the world.
+    pub fn wraps(&self) -> bool {
+        match *self {
+            Boundary::Wrap | Boundary::Twist { .. } => true,
+            _ => false,
+        }
+    }
+}
+
+impl Default for Boundary {
+    fn default() -> Boundary {
+        Boundary::Wrap
+    }
+}
+
+// Boundaries of the 2D world along columns (x) and rows (y), e.g. wrapping x with skipped y
+// makes a cylinder.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Topology {
+    pub x: Boundary,
+    pub y: Boundary,
+}
+
+impl From<Boundary> for Topology {
+    fn from(boundary: Boundary) -> Topology {
+        Topology {
+            x: boundary,
+            y: boundary,
+        }
+    }
+}
+
+// (x, y) boundaries
+impl From<(Boundary, Boundary)> for Topology {
+    fn from((x, y): (Boundary, Boundary)) -> Topology {
+        Topology { x: x, y: y }
+    }
+}
+
+impl Default for Topology {
+    fn default() -> Topology {
+        Topology::from(Boundary::default())
+    }
 }
 
 pub fn wrap_idx(idx: i64, limit: usize) -> i64 {
@@ -22,36 +120,190 @@ fn test_wrap_idx() {
     assert_eq!(wrap_idx(13, 10), 3);
 }
 
+// (row, col) of the cell of w x h world drawn at pixel (px, py) when cells are cell_size
+// pixels big and the cell at offset (col, row) is drawn at pixel (0, 0). Pixels beyond the
+// world wrap around it.
+pub fn pixel_to_cell(px: i64,
+                     py: i64,
+                     cell_size: (u32, u32),
+                     offset: (usize, usize),
+                     w: usize,
+                     h: usize)
+                     -> (usize, usize) {
+    let (cw, ch) = (cell_size.0 as usize, cell_size.1 as usize);
+    let x = wrap_idx(px + (offset.0 * cw) as i64, w * cw) as usize;
+    let y = wrap_idx(py + (offset.1 * ch) as i64, h * ch) as usize;
+    (y / ch, x / cw)
+}
+
+#[test]
+fn test_pixel_to_cell() {
+    assert_eq!(pixel_to_cell(5, 9, (2, 4), (0, 0), 10, 10), (2, 2));
+    assert_eq!(pixel_to_cell(5, 9, (2, 4), (3, 1), 10, 10), (3, 5));
+    assert_eq!(pixel_to_cell(-1, 40, (2, 4), (0, 0), 10, 10), (0, 9));
+    assert_eq!(pixel_to_cell(19, 0, (2, 4), (9, 9), 10, 10), (9, 8));
+}
+
+// Mirrors idx about the first and the last cell: -1 -> 1, limit -> limit - 2.
+pub fn reflect_idx(idx: i64, limit: usize) -> i64 {
+    let last = (limit as i64) - 1;
+    if last == 0 {
+        return 0;
+    }
+    let idx = wrap_idx(idx, (2 * last) as usize);
+    if idx > last {
+        2 * last - idx
+    } else {
+        idx
+    }
+}
+
+#[test]
+fn test_reflect_idx() {
+    assert_eq!(reflect_idx(-1, 10), 1);
+    assert_eq!(reflect_idx(3, 10), 3);
+    assert_eq!(reflect_idx(10, 10), 8);
+    assert_eq!(reflect_idx(-12, 10), 6);
+    assert_eq!(reflect_idx(-1, 1), 0);
+}
+
+// Index of the cell that stands for idx, None if idx is outside of the world.
+pub fn bound_idx(idx: i64, limit: usize, boundary: Boundary) -> Option<usize> {
+    match boundary {
+        Boundary::Wrap |
+        Boundary::Twist { .. } => Some(wrap_idx(idx, limit) as usize),
+        Boundary::Reflect => Some(reflect_idx(idx, limit) as usize),
+        Boundary::Constant(_) |
+        Boundary::Skip => {
+            if idx < 0 || idx >= (limit as i64) {
+                None
+            } else {
+                Some(idx as usize)
+            }
+        }
+    }
+}
+
+// Coordinates with twists applied: every crossing of a twisted edge shifts the other
+// coordinate. Twisted axes are wrapped, the other boundary is left to bound_idx.
+fn twist(w: usize, h: usize, row: i64, col: i64, topology: Topology) -> (i64, i64) {
+    let (mut row, mut col) = (row, col);
+    if let Boundary::Twist { shift } = topology.x {
+        row += shift * (col - wrap_idx(col, w)) / (w as i64);
+        col = wrap_idx(col, w);
+    }
+    if let Boundary::Twist { shift } = topology.y {
+        col += shift * (row - wrap_idx(row, h)) / (h as i64);
+        row = wrap_idx(row, h);
+    }
+    (row, col)
+}
+
+#[test]
+fn test_twist() {
+    let helix = Topology::from((Boundary::Twist { shift: 1 }, Boundary::Wrap));
+    assert_eq!(twist(4, 3, 1, 2, helix), (1, 2));
+    assert_eq!(twist(4, 3, 1, 4, helix), (2, 0));
+    assert_eq!(twist(4, 3, 0, -1, helix), (-1, 3));
+    assert_eq!(twist(4, 3, 0, 9, helix), (2, 1));
+    let twisted_y = Topology::from((Boundary::Skip, Boundary::Twist { shift: -2 }));
+    assert_eq!(twist(4, 3, 3, 1, twisted_y), (0, -1));
+}
+
+// Coordinates of the cell standing for (row, col), None if there is no such cell.
+fn bound_coords(w: usize,
+                h: usize,
+                row: i64,
+                col: i64,
+                topology: Topology)
+                -> Option<(usize, usize)> {
+    let (row, col) = twist(w, h, row, col, topology);
+    match (bound_idx(row, h, topology.y), bound_idx(col, w, topology.x)) {
+        (Some(row), Some(col)) => Some((row, col)),
+        _ => None,
+    }
+}
+
+// State of the cell at (row, col) with boundary applied, None if the cell is skipped.
+pub fn cell_at<B: Into<Topology>>(cells: &[Cell],
+                                  w: usize,
+                                  h: usize,
+                                  row: i64,
+                                  col: i64,
+                                  boundary: B)
+                                  -> Option<Cell> {
+    let topology = boundary.into();
+    match bound_coords(w, h, row, col, topology) {
+        Some((row, col)) => Some(cells[row * w + col]),
+        None => {
+            // the row boundary wins if the cell is outside of the world along both axes
+            let (row, _) = twist(w, h, row, col, topology);
+            let boundary = if bound_idx(row, h, topology.y).is_none() {
+                topology.y
+            } else {
+                topology.x
+            };
+            match boundary {
+                Boundary::Constant(c) => Some(c),
+                _ => None,
+            }
+        }
+    }
+}
+
+// Scans rows of the (2*range+1)x(2*range+1) box around (row, col), or only the diamond of
+// cells with |drow| + |dcol| <= range.
 struct NeighborhoodCoordinatesIterator {
     row: i64,
     col: i64,
+    range: i64,
+    diamond: bool,
     nbrow: i64,
     nbcol: i64,
     lastrow: i64,
     lastcol: i64,
-    startcol: i64,
     finished: bool,
 }
 
 impl NeighborhoodCoordinatesIterator {
     fn new(row: usize, col: usize, range: u32) -> NeighborhoodCoordinatesIterator {
+        NeighborhoodCoordinatesIterator::with_shape(row, col, range, false)
+    }
+
+    fn new_diamond(row: usize, col: usize, range: u32) -> NeighborhoodCoordinatesIterator {
+        NeighborhoodCoordinatesIterator::with_shape(row, col, range, true)
+    }
+
+    fn with_shape(row: usize,
+                  col: usize,
+                  range: u32,
+                  diamond: bool)
+                  -> NeighborhoodCoordinatesIterator {
         let row_sgn = row as i64;
-        let col_sgn = col as i64;
         let range_sgn = range as i64;
-        let nbrow = row_sgn - range_sgn;
-        let nbcol = col_sgn - range_sgn;
-        let lastrow = row_sgn + range_sgn;
-        let lastcol = col_sgn + range_sgn;
-        NeighborhoodCoordinatesIterator {
+        let mut nci = NeighborhoodCoordinatesIterator {
             row: row_sgn,
-            col: col_sgn,
-            nbrow: nbrow,
-            nbcol: nbcol,
-            lastrow: lastrow,
-            lastcol: lastcol,
-            startcol: nbcol,
+            col: col as i64,
+            range: range_sgn,
+            diamond: diamond,
+            nbrow: row_sgn - range_sgn,
+            nbcol: 0,
+            lastrow: row_sgn + range_sgn,
+            lastcol: 0,
             finished: false,
-        }
+        };
+        nci.start_row();
+        nci
+    }
+
+    fn start_row(&mut self) {
+        let half_width = if self.diamond {
+            self.range - (self.nbrow - self.row).abs()
+        } else {
+            self.range
+        };
+        self.nbcol = self.col - half_width;
+        self.lastcol = self.col + half_width;
     }
 
     fn advance(&mut self) {
@@ -59,7 +311,7 @@ impl NeighborhoodCoordinatesIterator {
             self.nbcol += 1;
         } else if self.nbrow < self.lastrow {
             self.nbrow += 1;
-            self.nbcol = self.startcol;
+            self.start_row();
         } else {
             self.finished = true;
         }
@@ -79,96 +331,285 @@ impl Iterator for NeighborhoodCoordinatesIterator {
     }
 }
 
-pub struct MooreNeighborhoodIterator<'a> {
-    cells: &'a Vec<Vec<Cell>>,
+// (drow, dcol, range) -> whether cell at this shift from center belongs to neighborhood
+type Shape = fn(i64, i64, i64) -> bool;
+
+fn moore_shape(_: i64, _: i64, _: i64) -> bool {
+    true
+}
+
+fn von_neumann_shape(drow: i64, dcol: i64, range: i64) -> bool {
+    drow.abs() + dcol.abs() <= range
+}
+
+fn hex_shape(drow: i64, dcol: i64, range: i64) -> bool {
+    (drow + dcol).abs() <= range
+}
+
+// Cells of neighborhood of given shape with boundary applied, common part of the
+// neighborhood iterators.
+struct NeighborhoodCells<'a> {
+    cells: &'a [Cell],
     w: usize,
     h: usize,
+    range: i64,
+    topology: Topology,
+    shape: Shape,
     nci: NeighborhoodCoordinatesIterator,
+    // wrapped coordinates of the center and yielded neighbors, only if the neighborhood is
+    // wider than the world along a wrapped axis, so that some coordinates wrap to the same cell
+    seen: Option<Vec<(i64, i64)>>,
 }
 
-impl<'a> MooreNeighborhoodIterator<'a> {
-    pub fn new(cells: &'a Vec<Vec<Cell>>,
-               width: usize,
-               height: usize,
-               row: usize,
-               col: usize,
-               range: u32)
-               -> MooreNeighborhoodIterator {
-        let nci = NeighborhoodCoordinatesIterator::new(row, col, range);
-        MooreNeighborhoodIterator {
+impl<'a> NeighborhoodCells<'a> {
+    fn new(cells: &'a [Cell],
+           width: usize,
+           height: usize,
+           topology: Topology,
+           shape: Shape,
+           nci: NeighborhoodCoordinatesIterator)
+           -> NeighborhoodCells<'a> {
+        let wraps = |limit: usize, boundary: Boundary| {
+            boundary.wraps() && 2 * nci.range >= (limit as i64)
+        };
+        let seen = if wraps(width, topology.x) || wraps(height, topology.y) {
+            Some(Vec::new())
+        } else {
+            None
+        };
+        let mut nc = NeighborhoodCells {
             cells: cells,
             w: width,
             h: height,
+            range: nci.range,
+            topology: topology,
+            shape: shape,
             nci: nci,
+            seen: seen,
+        };
+        let (row, col) = (nc.nci.row, nc.nci.col);
+        nc.is_new(row, col);
+        nc
+    }
+
+    // Whether (row, col) doesn't wrap to the center or an already yielded neighbor.
+    fn is_new(&mut self, row: i64, col: i64) -> bool {
+        let (row, col) = twist(self.w, self.h, row, col, self.topology);
+        let coords = (self.wrap(row, self.h, self.topology.y),
+                      self.wrap(col, self.w, self.topology.x));
+        match self.seen {
+            Some(ref mut seen) if seen.contains(&coords) => false,
+            Some(ref mut seen) => {
+                seen.push(coords);
+                true
+            }
+            None => true,
         }
     }
+
+    fn wrap(&self, idx: i64, limit: usize, boundary: Boundary) -> i64 {
+        if boundary.wraps() {
+            wrap_idx(idx, limit)
+        } else {
+            idx
+        }
+    }
+
+    // Next neighbor coordinates before applying boundary.
+    fn next_coords(&mut self) -> Option<(i64, i64)> {
+        while let Some((row, col)) = self.nci.next() {
+            let drow = row - self.nci.row;
+            let dcol = col - self.nci.col;
+            if (drow != 0 || dcol != 0) && (self.shape)(drow, dcol, self.range) &&
+               self.is_new(row, col) {
+                return Some((row, col));
+            }
+        }
+        None
+    }
+
+    fn next_cell(&mut self) -> Option<Cell> {
+        while let Some((row, col)) = self.next_coords() {
+            let cell = cell_at(self.cells, self.w, self.h, row, col, self.topology);
+            if cell.is_some() {
+                return cell;
+            }
+        }
+        None
+    }
+
+    fn next_cell_with_coords(&mut self) -> Option<(usize, usize, Cell)> {
+        while let Some((row, col)) = self.next_coords() {
+            if let Some((row, col)) = bound_coords(self.w, self.h, row, col, self.topology) {
+                return Some((row, col, self.cells[row * self.w + col]));
+            }
+        }
+        None
+    }
+}
+
+// Yields (row, col, state) of neighbors. Cells outside of the world with constant boundary
+// have no coordinates, so they are not yielded.
+pub struct NeighborhoodWithCoordsIterator<'a> {
+    nc: NeighborhoodCells<'a>,
+}
+
+impl<'a> Iterator for NeighborhoodWithCoordsIterator<'a> {
+    type Item = (usize, usize, Cell);
+
+    fn next(&mut self) -> Option<(usize, usize, Cell)> {
+        self.nc.next_cell_with_coords()
+    }
+}
+
+pub struct MooreNeighborhoodIterator<'a> {
+    nc: NeighborhoodCells<'a>,
+}
+
+impl<'a> MooreNeighborhoodIterator<'a> {
+    pub fn new<B: Into<Topology>>(cells: &'a [Cell],
+                                  width: usize,
+                                  height: usize,
+                                  row: usize,
+                                  col: usize,
+                                  range: u32,
+                                  boundary: B)
+                                  -> MooreNeighborhoodIterator {
+        let nci = NeighborhoodCoordinatesIterator::new(row, col, range);
+        MooreNeighborhoodIterator {
+            nc: NeighborhoodCells::new(cells, width, height, boundary.into(), moore_shape, nci),
+        }
+    }
+
+    pub fn with_coords(self) -> NeighborhoodWithCoordsIterator<'a> {
+        NeighborhoodWithCoordsIterator { nc: self.nc }
+    }
 }
 
 impl<'a> Iterator for MooreNeighborhoodIterator<'a> {
     type Item = Cell;
 
     fn next(&mut self) -> Option<Cell> {
-        match self.nci.next() {
-            Some((row, col)) => {
-                if self.nci.row == row && self.nci.col == col {
-                    self.next()
-                } else {
-                    let row = wrap_idx(row, self.h) as usize;
-                    let col = wrap_idx(col, self.w) as usize;
-                    Some(self.cells[row][col])
-                }
-            }
-            None => None,
-        }
+        self.nc.next_cell()
     }
 }
 
 pub struct VonNeumannNeighborhoodIterator<'a> {
-    cells: &'a Vec<Vec<Cell>>,
-    w: usize,
-    h: usize,
-    range: i64,
-    nci: NeighborhoodCoordinatesIterator,
+    nc: NeighborhoodCells<'a>,
 }
 
 impl<'a> VonNeumannNeighborhoodIterator<'a> {
-    pub fn new(cells: &'a Vec<Vec<Cell>>,
-               width: usize,
-               height: usize,
-               row: usize,
-               col: usize,
-               range: u32)
-               -> VonNeumannNeighborhoodIterator {
-        let nci = NeighborhoodCoordinatesIterator::new(row, col, range);
+    pub fn new<B: Into<Topology>>(cells: &'a [Cell],
+                                  width: usize,
+                                  height: usize,
+                                  row: usize,
+                                  col: usize,
+                                  range: u32,
+                                  boundary: B)
+                                  -> VonNeumannNeighborhoodIterator {
+        let nci = NeighborhoodCoordinatesIterator::new_diamond(row, col, range);
+        let topology = boundary.into();
         VonNeumannNeighborhoodIterator {
+            nc: NeighborhoodCells::new(cells, width, height, topology, von_neumann_shape, nci),
+        }
+    }
+
+    pub fn with_coords(self) -> NeighborhoodWithCoordsIterator<'a> {
+        NeighborhoodWithCoordsIterator { nc: self.nc }
+    }
+}
+
+impl<'a> Iterator for VonNeumannNeighborhoodIterator<'a> {
+    type Item = Cell;
+
+    fn next(&mut self) -> Option<Cell> {
+        self.nc.next_cell()
+    }
+}
+
+// Hex grid is stored in axial coordinates: every row is shifted half a cell to the right
+// relative to the previous one, so neighbors of (row, col) are (row-1, col), (row-1, col+1),
+// (row, col-1), (row, col+1), (row+1, col-1) and (row+1, col).
+pub struct HexNeighborhoodIterator<'a> {
+    nc: NeighborhoodCells<'a>,
+}
+
+impl<'a> HexNeighborhoodIterator<'a> {
+    pub fn new<B: Into<Topology>>(cells: &'a [Cell],
+                                  width: usize,
+                                  height: usize,
+                                  row: usize,
+                                  col: usize,
+                                  range: u32,
+                                  boundary: B)
+                                  -> HexNeighborhoodIterator {
+        let nci = NeighborhoodCoordinatesIterator::new(row, col, range);
+        HexNeighborhoodIterator {
+            nc: NeighborhoodCells::new(cells, width, height, boundary.into(), hex_shape, nci),
+        }
+    }
+
+    pub fn with_coords(self) -> NeighborhoodWithCoordsIterator<'a> {
+        NeighborhoodWithCoordsIterator { nc: self.nc }
+    }
+}
+
+impl<'a> Iterator for HexNeighborhoodIterator<'a> {
+    type Item = Cell;
+
+    fn next(&mut self) -> Option<Cell> {
+        self.nc.next_cell()
+    }
+}
+
+// Yields cells at given (drow, dcol) offsets from (row, col) in the order of offsets.
+pub struct CustomNeighborhoodIterator<'a> {
+    cells: &'a [Cell],
+    w: usize,
+    h: usize,
+    row: i64,
+    col: i64,
+    offsets: &'a [(i64, i64)],
+    topology: Topology,
+    idx: usize,
+}
+
+impl<'a> CustomNeighborhoodIterator<'a> {
+    pub fn new<B: Into<Topology>>(cells: &'a [Cell],
+                                  width: usize,
+                                  height: usize,
+                                  row: usize,
+                                  col: usize,
+                                  offsets: &'a [(i64, i64)],
+                                  boundary: B)
+                                  -> CustomNeighborhoodIterator<'a> {
+        CustomNeighborhoodIterator {
             cells: cells,
             w: width,
             h: height,
-            range: range as i64,
-            nci: nci,
+            row: row as i64,
+            col: col as i64,
+            offsets: offsets,
+            topology: boundary.into(),
+            idx: 0,
         }
     }
 }
 
-impl<'a> Iterator for VonNeumannNeighborhoodIterator<'a> {
+impl<'a> Iterator for CustomNeighborhoodIterator<'a> {
     type Item = Cell;
 
     fn next(&mut self) -> Option<Cell> {
-        match self.nci.next() {
-            Some((row, col)) => {
-                let dist = (self.nci.row - row).abs() + (self.nci.col - col).abs();
-                if dist > self.range {
-                    self.next()
-                } else if self.nci.row == row && self.nci.col == col {
-                    self.next()
-                } else {
-                    let row = wrap_idx(row, self.h) as usize;
-                    let col = wrap_idx(col, self.w) as usize;
-                    Some(self.cells[row][col])
-                }
+        while self.idx < self.offsets.len() {
+            let (drow, dcol) = self.offsets[self.idx];
+            self.idx += 1;
+            let (row, col) = (self.row + drow, self.col + dcol);
+            let cell = cell_at(self.cells, self.w, self.h, row, col, self.topology);
+            if cell.is_some() {
+                return cell;
             }
-            None => None,
         }
+        None
     }
 }
 
@@ -180,17 +621,134 @@ mod tests {
 
     #[test]
     fn test_moore_neighborhood_iterator() {
-        let cells = gen::points2d(3, 3, vec![(0, 0), (1, 1), (2, 2)]);
-        let it = MooreNeighborhoodIterator::new(&cells, 3, 3, 1, 1, 1);
+        let cells = gen::points2d(3, 3, vec![(0, 0), (1, 1), (2, 2)]).concat();
+        let it = MooreNeighborhoodIterator::new(&cells, 3, 3, 1, 1, 1, Boundary::Wrap);
         let neighbors: Vec<Cell> = it.collect();
         assert_eq!(neighbors, vec![1, 0, 0, 0, 0, 0, 0, 1]);
     }
 
+    #[test]
+    fn test_moore_neighborhood_iterator_bounded() {
+        let cells = gen::points2d(3, 3, vec![(0, 0), (1, 1), (2, 2)]).concat();
+        let it = MooreNeighborhoodIterator::new(&cells, 3, 3, 0, 0, 1, Boundary::Skip);
+        let neighbors: Vec<Cell> = it.collect();
+        assert_eq!(neighbors, vec![0, 0, 1]);
+        let it = MooreNeighborhoodIterator::new(&cells, 3, 3, 0, 0, 1, Boundary::Constant(2));
+        let neighbors: Vec<Cell> = it.collect();
+        assert_eq!(neighbors, vec![2, 2, 2, 2, 0, 2, 0, 1]);
+    }
+
+    #[test]
+    fn test_neighborhood_iterator_with_coords() {
+        let cells = gen::points2d(3, 3, vec![(0, 0), (1, 1), (2, 2)]).concat();
+        let it = VonNeumannNeighborhoodIterator::new(&cells, 3, 3, 0, 0, 1, Boundary::Wrap);
+        let neighbors: Vec<(usize, usize, Cell)> = it.with_coords().collect();
+        assert_eq!(neighbors, vec![(2, 0, 0), (0, 2, 0), (0, 1, 0), (1, 0, 0)]);
+        let it = MooreNeighborhoodIterator::new(&cells, 3, 3, 0, 0, 1, Boundary::Constant(1));
+        let neighbors: Vec<(usize, usize, Cell)> = it.with_coords().collect();
+        assert_eq!(neighbors, vec![(0, 1, 0), (1, 0, 0), (1, 1, 1)]);
+    }
+
+    #[test]
+    fn test_offsets() {
+        assert_eq!(Neighborhood::Moore(1).offsets().len(), 8);
+        assert_eq!(Neighborhood::VonNeumann(2).offsets().len(), 12);
+        assert_eq!(Neighborhood::Hex(1).offsets(),
+                   vec![(-1, 0), (-1, 1), (0, -1), (0, 1), (1, -1), (1, 0)]);
+    }
+
+    #[test]
+    fn test_size() {
+        for range in 1..4 {
+            for nbh in vec![Neighborhood::Moore(range),
+                            Neighborhood::VonNeumann(range),
+                            Neighborhood::Hex(range)] {
+                assert_eq!(nbh.size(), nbh.offsets().len());
+            }
+        }
+        assert_eq!(Neighborhood::Moore(3).size(), 48);
+        assert_eq!(Neighborhood::VonNeumann(3).size(), 24);
+        assert_eq!(Neighborhood::Custom(vec![(0, 1), (1, 0)]).size(), 2);
+    }
+
+    #[test]
+    fn test_custom_neighborhood_iterator() {
+        let cells = gen::points2d(3, 3, vec![(0, 0), (1, 1), (2, 2)]);
+        let cells = cells.concat();
+        // knight's moves
+        let offsets = vec![(-2, -1), (-1, -2), (1, 2), (2, 1), (0, 0)];
+        let it = CustomNeighborhoodIterator::new(&cells, 3, 3, 0, 0, &offsets, Boundary::Wrap);
+        assert_eq!(it.collect::<Vec<Cell>>(), vec![0, 0, 0, 0, 1]);
+        let it = CustomNeighborhoodIterator::new(&cells, 3, 3, 0, 0, &offsets, Boundary::Skip);
+        assert_eq!(it.collect::<Vec<Cell>>(), vec![0, 0, 1]);
+    }
+
+    #[test]
+    fn test_diamond_coordinates() {
+        let coords: Vec<(i64, i64)> = NeighborhoodCoordinatesIterator::new_diamond(5, 5, 3)
+            .collect();
+        assert_eq!(coords.len(), 25);
+        assert!(coords.iter().all(|&(row, col)| (row - 5).abs() + (col - 5).abs() <= 3));
+        assert_eq!(&coords[..4], &[(2, 5), (3, 4), (3, 5), (3, 6)]);
+    }
+
     #[test]
     fn test_von_neumann_neighborhood_iterator() {
         let cells = gen::points2d(3, 3, vec![(0, 0), (0, 1), (1, 0), (1, 1), (2, 2)]);
-        let it = VonNeumannNeighborhoodIterator::new(&cells, 3, 3, 1, 1, 1);
+        let cells = cells.concat();
+        let it = VonNeumannNeighborhoodIterator::new(&cells, 3, 3, 1, 1, 1, Boundary::Wrap);
         let neighbors: Vec<Cell> = it.collect();
         assert_eq!(neighbors, vec![1, 1, 0, 0]);
     }
+
+    #[test]
+    fn test_small_torus() {
+        // with range 2 on 3x3 torus every other cell is reachable by more than one shift
+        let cells: Vec<Cell> = (1..10).collect();
+        let it = VonNeumannNeighborhoodIterator::new(&cells, 3, 3, 1, 1, 2, Boundary::Wrap);
+        let mut neighbors: Vec<Cell> = it.collect();
+        neighbors.sort();
+        assert_eq!(neighbors, vec![1, 2, 3, 4, 6, 7, 8, 9]);
+        let it = MooreNeighborhoodIterator::new(&cells, 2, 2, 0, 0, 1, Boundary::Wrap);
+        assert_eq!(it.count(), 3);
+        // mirror images beyond reflected edges are counted like before, only wrapping dedupes
+        let topology = Topology::from((Boundary::Wrap, Boundary::Reflect));
+        let it = VonNeumannNeighborhoodIterator::new(&cells, 3, 3, 0, 1, 2, topology);
+        assert_eq!(it.count(), 10);
+    }
+
+    #[test]
+    fn test_hex_neighborhood_iterator() {
+        let cells = vec![1, 2, 3, 4, 5, 6, 7, 8, 9];
+        let it = HexNeighborhoodIterator::new(&cells, 3, 3, 1, 1, 1, Boundary::Wrap);
+        let neighbors: Vec<Cell> = it.collect();
+        assert_eq!(neighbors, vec![2, 3, 4, 6, 7, 8]);
+        let cells = vec![0; 25];
+        let it = HexNeighborhoodIterator::new(&cells, 5, 5, 2, 2, 2, Boundary::Wrap);
+        assert_eq!(it.count(), 18);
+    }
+
+    #[test]
+    fn test_topology() {
+        let cells = vec![1, 2, 3, 4, 5, 6, 7, 8, 9];
+        let cylinder = Topology::from((Boundary::Wrap, Boundary::Skip));
+        let it = MooreNeighborhoodIterator::new(&cells, 3, 3, 0, 0, 1, cylinder);
+        assert_eq!(it.collect::<Vec<_>>(), vec![3, 2, 6, 4, 5]);
+        let cylinder = Topology::from((Boundary::Constant(0), Boundary::Wrap));
+        assert_eq!(cell_at(&cells, 3, 3, -1, 0, cylinder), Some(7));
+        assert_eq!(cell_at(&cells, 3, 3, 0, -1, cylinder), Some(0));
+    }
+
+    #[test]
+    fn test_helix() {
+        let cells = vec![1, 2, 3, 4, 5, 6, 7, 8, 9];
+        let helix = Topology::from((Boundary::Twist { shift: 1 }, Boundary::Wrap));
+        let it = MooreNeighborhoodIterator::new(&cells, 3, 3, 0, 2, 1, helix);
+        assert_eq!(it.collect::<Vec<_>>(), vec![8, 9, 1, 2, 4, 5, 6, 7]);
+        // shifted row may fall beyond the edge that doesn't wrap
+        let strip = Topology::from((Boundary::Twist { shift: 1 }, Boundary::Constant(0)));
+        assert_eq!(cell_at(&cells, 3, 3, 2, 3, strip), Some(0));
+        assert_eq!(cell_at(&cells, 3, 3, 1, 3, strip), Some(7));
+    }
+
 }
@@ -0,0 +1,86 @@
+use sdl2::pixels::Color;
+
+// Count of colors in the default palette.
+const DEFAULT_COLORS: usize = 18;
+
+pub fn make_palette() -> Vec<Color> {
+    vec![
+        Color::RGB(0, 0, 0),
+        Color::RGB(200, 200, 0),
+	    Color::RGB(0, 153, 255),
+	    Color::RGB(0, 255, 153),
+	    Color::RGB(51, 255, 0),
+	    Color::RGB(255, 255, 0),
+	    Color::RGB(255, 51, 0),
+	    Color::RGB(255, 0, 153),
+	    Color::RGB(182, 0, 255),
+	    Color::RGB(37, 0, 255),
+	    Color::RGB(0, 102, 255),
+	    Color::RGB(0, 255, 204),
+	    Color::RGB(0, 255, 0),
+	    Color::RGB(204, 255, 0),
+	    Color::RGB(255, 102, 0),
+	    Color::RGB(255, 0, 102),
+	    Color::RGB(219, 0, 255),
+	    Color::RGB(73, 0, 255),
+    ]
+}
+
+// hue in degrees [0, 360), full saturation and value
+fn hue_to_color(hue: f64) -> Color {
+    let x = 1.0 - ((hue / 60.0) % 2.0 - 1.0).abs();
+    let (r, g, b) = match (hue / 60.0) as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+    let to_u8 = |c: f64| (c * 255.0).round() as u8;
+    Color::RGB(to_u8(r), to_u8(g), to_u8(b))
+}
+
+// n colors: black for state 0 like in the default palette, then n-1 colors with hues evenly
+// distributed around the HSV wheel.
+pub fn generate_palette(n: usize) -> Vec<Color> {
+    let mut palette = vec![Color::RGB(0, 0, 0)];
+    let hues = n.saturating_sub(1);
+    palette.extend((0..hues).map(|i| hue_to_color(360.0 * (i as f64) / (hues as f64))));
+    palette
+}
+
+// Default palette if it has enough colors, generated one otherwise.
+pub fn palette_for(states: usize) -> Vec<Color> {
+    if states <= DEFAULT_COLORS {
+        make_palette()
+    } else {
+        generate_palette(states)
+    }
+}
+
+// Colors for --age: black for dead cells, then n colors for live cells of ages 0..n-1, going
+// from yellow through green and blue to purple.
+pub fn age_palette(n: usize) -> Vec<Color> {
+    let mut palette = vec![Color::RGB(0, 0, 0)];
+    palette.extend((0..n).map(|i| hue_to_color(60.0 + 240.0 * (i as f64) / (n as f64))));
+    palette
+}
+
+// Same colors with red, green and blue channels rotated, e.g. to tell layers of coupled CA
+// apart.
+pub fn rotate_channels(palette: &[Color]) -> Vec<Color> {
+    palette.iter()
+        .map(|c| {
+            let (r, g, b) = c.rgb();
+            Color::RGB(b, r, g)
+        })
+        .collect()
+}
+
+// Colors of overlapping layers added up channel by channel.
+pub fn blend(a: Color, b: Color) -> Color {
+    let (r1, g1, b1) = a.rgb();
+    let (r2, g2, b2) = b.rgb();
+    Color::RGB(r1.saturating_add(r2), g1.saturating_add(g2), b1.saturating_add(b2))
+}
@@ -0,0 +1,102 @@
+use gen;
+use types::Cell;
+
+// Well-known Life patterns in plaintext (.cells) format.
+static GLIDER: &'static str = "\
+.O.
+..O
+OOO";
+
+static BLINKER: &'static str = "OOO";
+
+static BLOCK: &'static str = "\
+OO
+OO";
+
+static LWSS: &'static str = "\
+.O..O
+O....
+O...O
+OOOO.";
+
+static R_PENTOMINO: &'static str = "\
+.OO
+OO.
+.O.";
+
+static GOSPER_GLIDER_GUN: &'static str = "\
+........................O...........
+......................O.O...........
+............OO......OO............OO
+...........O...O....OO............OO
+OO........O.....O...OO..............
+OO........O...O.OO....O.O...........
+..........O.....O.......O...........
+...........O...O....................
+............OO......................";
+
+fn parse(s: &str) -> Vec<Vec<Cell>> {
+    gen::parse_plaintext(s).unwrap()
+}
+
+// Moves one cell down and right every 4 generations.
+pub fn glider() -> Vec<Vec<Cell>> {
+    parse(GLIDER)
+}
+
+pub fn blinker() -> Vec<Vec<Cell>> {
+    parse(BLINKER)
+}
+
+pub fn block() -> Vec<Vec<Cell>> {
+    parse(BLOCK)
+}
+
+// Lightweight spaceship, moves to the left.
+pub fn lwss() -> Vec<Vec<Cell>> {
+    parse(LWSS)
+}
+
+// Methuselah that settles after 1103 generations.
+pub fn r_pentomino() -> Vec<Vec<Cell>> {
+    parse(R_PENTOMINO)
+}
+
+// Emits a glider every 30 generations.
+pub fn gosper_glider_gun() -> Vec<Vec<Cell>> {
+    parse(GOSPER_GLIDER_GUN)
+}
+
+// Pattern by its name as used by points:pattern: init, None if there is no such pattern.
+pub fn by_name(name: &str) -> Option<Vec<Vec<Cell>>> {
+    match name {
+        "glider" => Some(glider()),
+        "blinker" => Some(blinker()),
+        "block" => Some(block()),
+        "lwss" => Some(lwss()),
+        "r-pentomino" => Some(r_pentomino()),
+        "gosper-gun" => Some(gosper_glider_gun()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gen;
+    use nb::Boundary;
+    use CA2;
+
+    #[test]
+    fn test_gosper_glider_gun() {
+        let gun = gosper_glider_gun();
+        assert_eq!((gun[0].len(), gun.len()), (36, 9));
+        let mut cells = vec![vec![0; 60]; 40];
+        gen::stamp(&mut cells, &gun, 1, 1);
+        let mut ca = CA2::new_life(cells, vec![2, 3], vec![3], Boundary::Constant(0)).unwrap();
+        let population = ca.population()[1];
+        ca.tick_n(30);
+        // the gun is back in its first phase and the glider has left it
+        assert_eq!(ca.population()[1], population + 5);
+    }
+}
@@ -0,0 +1,157 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+
+use types::Cell;
+
+// Snapshot format: first line is "WIDTH HEIGHT", then HEIGHT lines of WIDTH
+// space-separated cell states.
+
+// cells are stored row by row
+pub fn write_grid<W: Write>(out: &mut W,
+                            w: usize,
+                            h: usize,
+                            cells: &[Cell])
+                            -> Result<(), String> {
+    try!(writeln!(out, "{} {}", w, h).map_err(|e| e.to_string()));
+    for row in cells.chunks(w) {
+        let line: Vec<String> = row.iter().map(|c| c.to_string()).collect();
+        try!(writeln!(out, "{}", line.join(" ")).map_err(|e| e.to_string()));
+    }
+    Ok(())
+}
+
+// (width, height, cells)
+pub fn read_grid<R: BufRead>(input: R) -> Result<(usize, usize, Vec<Cell>), String> {
+    static ERR_NO_HEADER: &'static str = "snapshot: missing WIDTH HEIGHT header!";
+    static ERR_INVALID_HEADER: &'static str = "snapshot: invalid WIDTH HEIGHT header!";
+    static ERR_INVALID_CELL: &'static str = "snapshot: invalid cell state!";
+    static ERR_TOO_BIG: &'static str = "snapshot: grid is too big!";
+
+    let mut lines = input.lines();
+    let header = match lines.next() {
+        Some(line) => try!(line.map_err(|e| e.to_string())),
+        None => return Err(String::from(ERR_NO_HEADER)),
+    };
+    let dims: Vec<&str> = header.split_whitespace().collect();
+    if dims.len() != 2 {
+        return Err(String::from(ERR_INVALID_HEADER));
+    }
+    let w = try!(dims[0].parse::<usize>().map_err(|_| ERR_INVALID_HEADER));
+    let h = try!(dims[1].parse::<usize>().map_err(|_| ERR_INVALID_HEADER));
+
+    if w.checked_mul(h).is_none() {
+        return Err(String::from(ERR_TOO_BIG));
+    }
+
+    // header isn't trusted for preallocation, cells grow with the rows actually read
+    let mut cells: Vec<Cell> = Vec::new();
+    let mut rows = 0;
+    for line in lines {
+        let line = try!(line.map_err(|e| e.to_string()));
+        if line.trim().is_empty() {
+            continue;
+        }
+        if rows == h {
+            return Err(format!("snapshot: more than {} rows found!", h));
+        }
+        let mut row_len = 0;
+        for part in line.split_whitespace() {
+            cells.push(try!(part.parse::<Cell>().map_err(|_| ERR_INVALID_CELL)));
+            row_len += 1;
+        }
+        if row_len != w {
+            return Err(format!("snapshot: row {} has {} cells, expected {}!", rows, row_len, w));
+        }
+        rows += 1;
+    }
+    if rows != h {
+        return Err(format!("snapshot: {} rows found, expected {}!", rows, h));
+    }
+    Ok((w, h, cells))
+}
+
+pub fn save<P: AsRef<Path>>(path: P, w: usize, h: usize, cells: &[Cell]) -> Result<(), String> {
+    let file = try!(File::create(path).map_err(|e| e.to_string()));
+    let mut out = BufWriter::new(file);
+    try!(write_grid(&mut out, w, h, cells));
+    out.flush().map_err(|e| e.to_string())
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> Result<(usize, usize, Vec<Cell>), String> {
+    let file = try!(File::open(path).map_err(|e| e.to_string()));
+    read_grid(BufReader::new(file))
+}
+
+// Same format as write_grid(), gzip-compressed. Returns out back.
+pub fn write_grid_compressed<W: Write>(out: W,
+                                       w: usize,
+                                       h: usize,
+                                       cells: &[Cell])
+                                       -> Result<W, String> {
+    let mut encoder = GzEncoder::new(out, Compression::default());
+    try!(write_grid(&mut encoder, w, h, cells));
+    encoder.finish().map_err(|e| e.to_string())
+}
+
+pub fn read_grid_compressed<R: Read>(input: R) -> Result<(usize, usize, Vec<Cell>), String> {
+    read_grid(BufReader::new(GzDecoder::new(input)))
+}
+
+pub fn save_compressed<P: AsRef<Path>>(path: P,
+                                       w: usize,
+                                       h: usize,
+                                       cells: &[Cell])
+                                       -> Result<(), String> {
+    let file = try!(File::create(path).map_err(|e| e.to_string()));
+    let mut out = try!(write_grid_compressed(BufWriter::new(file), w, h, cells));
+    out.flush().map_err(|e| e.to_string())
+}
+
+pub fn load_compressed<P: AsRef<Path>>(path: P) -> Result<(usize, usize, Vec<Cell>), String> {
+    let file = try!(File::open(path).map_err(|e| e.to_string()));
+    read_grid_compressed(BufReader::new(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_grid_round_trip() {
+        let cells = vec![0, 1, 2, 3, 4, 5];
+        let mut buf: Vec<u8> = Vec::new();
+        write_grid(&mut buf, 3, 2, &cells).unwrap();
+        assert_eq!(String::from_utf8(buf.clone()).unwrap(), "3 2\n0 1 2\n3 4 5\n");
+        assert_eq!(read_grid(Cursor::new(buf)).unwrap(), (3, 2, cells));
+    }
+
+    #[test]
+    fn test_compressed_round_trip() {
+        let (w, h) = (1000, 1000);
+        let cells: Vec<Cell> = (0..w * h).map(|i| ((i * 7) % 13 / 10) as Cell).collect();
+        let mut buf: Vec<u8> = Vec::new();
+        write_grid_compressed(&mut buf, w, h, &cells).unwrap();
+        assert!(buf.len() < w * h);
+        assert_eq!(read_grid_compressed(Cursor::new(buf)).unwrap(), (w, h, cells));
+    }
+
+    #[test]
+    fn test_read_grid_size_mismatch() {
+        assert!(read_grid(Cursor::new("3 2\n0 1 2\n")).is_err());
+        assert!(read_grid(Cursor::new("3 1\n0 1\n")).is_err());
+    }
+
+    #[test]
+    fn test_read_grid_huge_header() {
+        let header = format!("{} {}\n0\n", usize::max_value(), 2);
+        assert!(read_grid(Cursor::new(header)).is_err());
+        assert!(read_grid(Cursor::new("1 1000000000000\n0\n")).is_err());
+        assert!(read_grid(Cursor::new("1 1\n0\n1\n")).is_err());
+    }
+}
@@ -0,0 +1,1097 @@
+use std::char;
+use std::fmt;
+use std::str::FromStr;
+
+use error::CaError;
+use gen::Align;
+use nb::Neighborhood;
+use patterns;
+use types::Cell;
+
+// Text forms of CA parameters, the same ones the command line accepts.
+
+// mR, nR or hR: Moore, Von Neumann or hexagonal neighborhood of range R.
+impl FromStr for Neighborhood {
+    type Err = CaError;
+
+    fn from_str(s: &str) -> Result<Neighborhood, CaError> {
+        let mut chars = s.chars();
+        let kind = chars.next();
+        let range = try!(chars.as_str().parse::<u32>().map_err(|_| CaError::InvalidNeighborhood));
+        match kind {
+            Some('m') => Ok(Neighborhood::Moore(range)),
+            Some('n') => Ok(Neighborhood::VonNeumann(range)),
+            Some('h') => Ok(Neighborhood::Hex(range)),
+            _ => Err(CaError::InvalidNeighborhood),
+        }
+    }
+}
+
+// Custom neighborhoods have no text form, their offsets are listed instead.
+impl fmt::Display for Neighborhood {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Neighborhood::Moore(range) => write!(f, "m{}", range),
+            Neighborhood::VonNeumann(range) => write!(f, "n{}", range),
+            Neighborhood::Hex(range) => write!(f, "h{}", range),
+            Neighborhood::Custom(ref offsets) => write!(f, "{:?}", offsets),
+        }
+    }
+}
+
+// Life-like rule in B/S notation, e.g. B3/S23 for Conway's Life: digits after B are counts
+// of live neighbors needed for birth, digits after S - for survival.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LifeRule {
+    pub survive: Vec<Cell>,
+    pub birth: Vec<Cell>,
+}
+
+impl FromStr for LifeRule {
+    type Err = CaError;
+
+    fn from_str(s: &str) -> Result<LifeRule, CaError> {
+        let parts: Vec<&str> = s.split('/').collect();
+        if parts.len() != 2 {
+            return Err(CaError::InvalidRulestring);
+        }
+        let (mut survive, mut birth) = (None, None);
+        for part in parts {
+            let mut chars = part.chars();
+            let head = chars.next();
+            let digits = try!(chars.map(|c| c.to_digit(10).map(|n| n as Cell))
+                .collect::<Option<Vec<Cell>>>()
+                .ok_or(CaError::InvalidRulestring));
+            match head {
+                Some('B') | Some('b') if birth.is_none() => birth = Some(digits),
+                Some('S') | Some('s') if survive.is_none() => survive = Some(digits),
+                _ => return Err(CaError::InvalidRulestring),
+            }
+        }
+        Ok(LifeRule {
+            survive: survive.unwrap(),
+            birth: birth.unwrap(),
+        })
+    }
+}
+
+impl fmt::Display for LifeRule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let digits = |counts: &Vec<Cell>| -> String {
+            counts.iter().map(|n| n.to_string()).collect()
+        };
+        write!(f, "B{}/S{}", digits(&self.birth), digits(&self.survive))
+    }
+}
+
+const ERR_INVALID_RANDOM: &'static str = "Invalid 'random' parameters!";
+const ERR_NO_STATES: &'static str = "STATES is not set!";
+const ERR_INVALID_STATES: &'static str = "Invalid STATES value!";
+const ERR_NO_POINTS: &'static str = "POINTS is not set!";
+const ERR_INVALID_POINTS: &'static str = "Invalid POINTS value!";
+const ERR_INVALID_BINARY_DIGIT: &'static str = "binary: STRING has digit that isn't a state!";
+
+// Kind of CA with its parameters as command line args, e.g. "life B3/S23" or "cyclic m1 3 4".
+// Codes written as 0xHEX or base:N:DIGITS are kept as digits in base of states.
+#[derive(Clone, PartialEq, Debug)]
+pub enum RuleSpec {
+    CA1 {
+        radius: (u8, u8), // left, right
+        states: u8,
+        code: Option<String>,
+    },
+    CA1Totalistic {
+        radius: u8,
+        states: u8,
+        code: Option<String>,
+    },
+    CA1OuterTotalistic {
+        radius: u8,
+        states: u8,
+        code: Option<String>,
+    },
+    Elementary(u8), // code
+    CA2Totalistic(Neighborhood, u8, Option<String>), // neighborhood, states, code
+    Cyclic(Neighborhood, u8, u32), // neighborhood, threshold, states
+    Life(Vec<Cell>, Vec<Cell>), // survive, birth
+    Generations(Vec<Cell>, Vec<Cell>, u32), // survive, birth, states
+    Coupled(Vec<Cell>, Vec<Cell>), // survive, birth
+    BriansBrain,
+    Wireworld,
+    Ant(String), // turns
+    Ltl(u32, (u32, u32), (u32, u32)), // range, birth interval, survive interval
+}
+
+impl RuleSpec {
+    // Args of a single CA, e.g. ["life", "23", "3"].
+    pub fn from_args(args: &[String]) -> Result<RuleSpec, CaError> {
+        parse_rule_spec(args).map_err(CaError::InvalidSpec)
+    }
+
+    pub fn is_1d(&self) -> bool {
+        match *self {
+            RuleSpec::CA1 { .. } |
+            RuleSpec::CA1Totalistic { .. } |
+            RuleSpec::CA1OuterTotalistic { .. } |
+            RuleSpec::Elementary(..) => true,
+            _ => false,
+        }
+    }
+
+    pub fn states(&self) -> u32 {
+        match *self {
+            RuleSpec::CA1 { states, .. } |
+            RuleSpec::CA1Totalistic { states, .. } |
+            RuleSpec::CA1OuterTotalistic { states, .. } |
+            RuleSpec::CA2Totalistic(_, states, _) => states as u32,
+            RuleSpec::Elementary(..) |
+            RuleSpec::Life(..) |
+            RuleSpec::Coupled(..) |
+            RuleSpec::Ltl(..) => 2,
+            RuleSpec::Cyclic(_, _, states) |
+            RuleSpec::Generations(_, _, states) => states,
+            RuleSpec::BriansBrain => 3,
+            RuleSpec::Wireworld => 4,
+            RuleSpec::Ant(ref turns) => turns.len() as u32,
+        }
+    }
+
+    pub fn is_hex(&self) -> bool {
+        match *self {
+            RuleSpec::CA2Totalistic(Neighborhood::Hex(_), _, _) |
+            RuleSpec::Cyclic(Neighborhood::Hex(_), _, _) => true,
+            _ => false,
+        }
+    }
+}
+
+// Args separated by whitespace.
+impl FromStr for RuleSpec {
+    type Err = CaError;
+
+    fn from_str(s: &str) -> Result<RuleSpec, CaError> {
+        let args: Vec<String> = s.split_whitespace().map(String::from).collect();
+        RuleSpec::from_args(&args)
+    }
+}
+
+impl fmt::Display for RuleSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let code_or_random = |code: &Option<String>| -> String {
+            code.clone().unwrap_or_else(|| String::from("random"))
+        };
+        let counts = |counts: &Vec<Cell>| -> String {
+            if counts.is_empty() {
+                return String::from("empty");
+            }
+            counts.iter().map(|n| n.to_string()).collect::<Vec<String>>().join(",")
+        };
+        match *self {
+            RuleSpec::CA1 { radius: (left, right), states, ref code } if left == right => {
+                write!(f, "1 {} {} {}", left, states, code_or_random(code))
+            }
+            RuleSpec::CA1 { radius: (left, right), states, ref code } => {
+                write!(f, "1 {},{} {} {}", left, right, states, code_or_random(code))
+            }
+            RuleSpec::CA1Totalistic { radius, states, ref code } => {
+                write!(f, "1t {} {} {}", radius, states, code_or_random(code))
+            }
+            RuleSpec::CA1OuterTotalistic { radius, states, ref code } => {
+                write!(f, "1ot {} {} {}", radius, states, code_or_random(code))
+            }
+            RuleSpec::Elementary(code) => write!(f, "elementary {}", code),
+            RuleSpec::CA2Totalistic(ref nbh, states, ref code) => {
+                write!(f, "2t {} {} {}", nbh, states, code_or_random(code))
+            }
+            RuleSpec::Cyclic(ref nbh, threshold, states) => {
+                write!(f, "cyclic {} {} {}", nbh, threshold, states)
+            }
+            RuleSpec::Life(ref survive, ref birth) => {
+                write!(f, "life {} {}", counts(survive), counts(birth))
+            }
+            RuleSpec::Generations(ref survive, ref birth, states) => {
+                write!(f, "generations {} {} {}", counts(survive), counts(birth), states)
+            }
+            RuleSpec::Coupled(ref survive, ref birth) => {
+                write!(f, "coupled {} {}", counts(survive), counts(birth))
+            }
+            RuleSpec::BriansBrain => write!(f, "brian"),
+            RuleSpec::Wireworld => write!(f, "wireworld"),
+            RuleSpec::Ant(ref turns) => write!(f, "ant {}", turns),
+            RuleSpec::Ltl(range, (b1, b2), (s1, s2)) => {
+                write!(f, "ltl {},{},{},{},{}", range, b1, b2, s1, s2)
+            }
+        }
+    }
+}
+
+// Cell given by its coordinates or by its shift from the center of the world, c alone is the
+// center itself. Points of 1D CA have no y.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Point {
+    Abs(usize, Option<usize>), // x, y
+    RelToCenter(i32, Option<i32>), // x, y
+    Center,
+}
+
+impl Point {
+    fn is_1d(&self) -> bool {
+        match *self {
+            Point::Abs(_, y) => y.is_none(),
+            Point::RelToCenter(_, y) => y.is_none(),
+            Point::Center => true,
+        }
+    }
+
+    fn is_2d(&self) -> bool {
+        *self == Point::Center || !self.is_1d()
+    }
+}
+
+impl fmt::Display for Point {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Point::Abs(x, None) => write!(f, "{}", x),
+            Point::Abs(x, Some(y)) => write!(f, "{},{}", x, y),
+            Point::Center => write!(f, "c"),
+            // both shifts have the same sign, the one of the non-zero shift
+            Point::RelToCenter(x, y) => {
+                let sign = if x < 0 || y.map_or(false, |y| y < 0) {
+                    '-'
+                } else {
+                    '+'
+                };
+                match y {
+                    None => write!(f, "c{}{}", sign, x.abs()),
+                    Some(y) => write!(f, "c{}{},{}", sign, x.abs(), y.abs()),
+                }
+            }
+        }
+    }
+}
+
+// Initial cells as the value of --init, e.g. "random:0,1*3" or "points:c;c+2,0=2". It doesn't
+// depend on the CA, see check() for the ones it can be used with.
+#[derive(Clone, PartialEq, Debug)]
+pub enum InitSpec {
+    Random {
+        states: Option<Vec<Cell>>, // None for every state of the CA
+        x1: Option<usize>,
+        x2: Option<usize>,
+        y1: Option<usize>,
+        y2: Option<usize>,
+    },
+    RandomDensity(f64), // probability of state 1
+    Points(Vec<(Point, Cell)>), // (point, state)
+    Binary(Vec<Cell>, Align), // states of cells
+    Patterns(Vec<(String, Point)>), // (name, top left cell)
+    Rle(String), // path
+    Plaintext(String), // path
+    Image(String), // path
+    Csv(String), // path
+}
+
+impl Default for InitSpec {
+    fn default() -> InitSpec {
+        InitSpec::Random {
+            states: None,
+            x1: None,
+            x2: None,
+            y1: None,
+            y2: None,
+        }
+    }
+}
+
+impl InitSpec {
+    // Whether cells depend on the seed.
+    pub fn is_random(&self) -> bool {
+        match *self {
+            InitSpec::Random { .. } |
+            InitSpec::RandomDensity(..) => true,
+            _ => false,
+        }
+    }
+
+    // Checks that the cells can be made for CA of the rule.
+    pub fn check(&self, rule: &RuleSpec) -> Result<(), CaError> {
+        check_init_spec(self, rule).map_err(CaError::InvalidSpec)
+    }
+}
+
+impl FromStr for InitSpec {
+    type Err = CaError;
+
+    fn from_str(s: &str) -> Result<InitSpec, CaError> {
+        parse_init_spec(s).map_err(CaError::InvalidSpec)
+    }
+}
+
+impl fmt::Display for InitSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            InitSpec::Random { ref states, x1, x2, y1, y2 } => {
+                let states = match *states {
+                    Some(ref states) => {
+                        states.iter().map(|s| s.to_string()).collect::<Vec<String>>().join(",")
+                    }
+                    None => String::from("uniform"),
+                };
+                let bounds: Vec<String> = [x1, x2, y1, y2]
+                    .iter()
+                    .take_while(|bound| bound.is_some())
+                    .map(|bound| bound.unwrap().to_string())
+                    .collect();
+                if bounds.is_empty() {
+                    write!(f, "random:{}", states)
+                } else {
+                    write!(f, "random:{}:{}", states, bounds.join(","))
+                }
+            }
+            InitSpec::RandomDensity(density) => write!(f, "random:density={}", density),
+            InitSpec::Points(ref points) => {
+                let points: Vec<String> = points.iter()
+                    .map(|&(ref point, state)| if state == 1 {
+                        point.to_string()
+                    } else {
+                        format!("{}={}", point, state)
+                    })
+                    .collect();
+                write!(f, "points:{}", points.join(";"))
+            }
+            InitSpec::Binary(ref cells, align) => {
+                let digits: String = cells.iter()
+                    .map(|&cell| char::from_digit(cell as u32, 36).unwrap_or('?'))
+                    .collect();
+                let align = match align {
+                    Align::Center => "center",
+                    Align::Right => "right",
+                };
+                write!(f, "binary:{}:{}", digits, align)
+            }
+            InitSpec::Patterns(ref patterns) => {
+                let patterns: Vec<String> = patterns.iter()
+                    .map(|&(ref name, ref point)| format!("{}@{}", name, point))
+                    .collect();
+                write!(f, "points:pattern:{}", patterns.join(";"))
+            }
+            InitSpec::Rle(ref path) => write!(f, "points:rle:{}", path),
+            InitSpec::Plaintext(ref path) => write!(f, "points:cells:{}", path),
+            InitSpec::Image(ref path) => write!(f, "image:{}", path),
+            InitSpec::Csv(ref path) => write!(f, "csv:{}", path),
+        }
+    }
+}
+
+fn parse<F>(args: &[String], idx: usize) -> Result<(F, usize), ()>
+    where F: FromStr
+{
+    if args.len() <= idx {
+        return Err(());
+    }
+    match args[idx].parse::<F>() {
+        Ok(val) => Ok((val, idx + 1)),
+        Err(_) => Err(()),
+    }
+}
+
+// (radius, states, code)
+fn parse_ca1_params(args: &[String],
+                    idx: usize)
+                    -> Result<((u8, u8, Option<String>), usize), &'static str> {
+    let (radius, idx) = try!(parse::<u8>(args, idx)
+        .map_err(|_| "RADIUS must be unsigned 8-bit integer!"));
+    let ((states, code), idx) = try!(parse_states_code(args, idx));
+    Ok(((radius, states, code), idx))
+}
+
+// (states, code)
+fn parse_states_code(args: &[String],
+                     idx: usize)
+                     -> Result<((u8, Option<String>), usize), &'static str> {
+    let (states, idx) = try!(parse::<u8>(args, idx)
+        .map_err(|_| "STATES must be unsigned 8-bit integer!"));
+    if args.len() <= idx {
+        return Err("Specify CODE value!");
+    }
+    let code = if args[idx] == "random" {
+        None
+    } else {
+        Some(args[idx].clone())
+    };
+    Ok(((states, code), idx + 1))
+}
+
+// RADIUS or RADIUS_LEFT,RADIUS_RIGHT
+fn parse_ca1_radius(args: &[String], idx: usize) -> Result<((u8, u8), usize), &'static str> {
+    static ERR_INVALID_RADIUS: &'static str = "RADIUS must be unsigned 8-bit integer or pair of \
+                                               them!";
+    if args.len() <= idx {
+        return Err(ERR_INVALID_RADIUS);
+    }
+    let radii = try!(args[idx]
+        .split(',')
+        .map(|r| r.parse::<u8>().map_err(|_| ERR_INVALID_RADIUS))
+        .collect::<Result<Vec<u8>, _>>());
+    match radii.len() {
+        1 => Ok(((radii[0], radii[0]), idx + 1)),
+        2 => Ok(((radii[0], radii[1]), idx + 1)),
+        _ => Err(ERR_INVALID_RADIUS),
+    }
+}
+
+// Digits of number in base from converted to base to, the most significant digit first.
+fn convert_base(digits: &[u32], from: u32, to: u32) -> Vec<u32> {
+    let mut number = digits.to_vec();
+    let mut converted = Vec::new();
+    while number.iter().any(|&d| d != 0) {
+        // long division of number by to
+        let mut rem = 0;
+        for d in number.iter_mut() {
+            let acc = rem * from + *d;
+            *d = acc / to;
+            rem = acc % to;
+        }
+        converted.push(rem);
+    }
+    converted.reverse();
+    converted
+}
+
+// Code written as 0xHEX or base:N:DIGITS is converted to len digits in base of states, other
+// codes are left as they are.
+fn convert_ca1_code(code: String, states: u8, len: Option<usize>) -> Result<String, &'static str> {
+    let (base, digits) = if code.starts_with("0x") {
+        (16, String::from(&code[2..]))
+    } else if code.starts_with("base:") {
+        let parts: Vec<&str> = code.splitn(3, ':').collect();
+        if parts.len() < 3 {
+            return Err("CODE must have form base:N:DIGITS!");
+        }
+        match parts[1].parse::<u32>() {
+            Ok(base) if base >= 2 && base <= 36 => (base, String::from(parts[2])),
+            _ => return Err("Base of CODE must be in range 2-36!"),
+        }
+    } else {
+        return Ok(code);
+    };
+    if digits.is_empty() {
+        return Err("CODE has no digits!");
+    }
+    let digits = try!(digits.chars()
+        .map(|c| c.to_digit(base).ok_or("CODE has digit that is invalid for its base!"))
+        .collect::<Result<Vec<u32>, _>>());
+    let states = states as u32;
+    if states < 2 || states > 36 {
+        // invalid STATES is reported by the rule
+        return Ok(code);
+    }
+    let converted: String = convert_base(&digits, base, states)
+        .into_iter()
+        .map(|d| char::from_digit(d, states).unwrap())
+        .collect();
+    match len {
+        Some(len) if converted.len() > len => Err("CODE is too big for the neighborhood!"),
+        Some(len) => Ok(format!("{:0>1$}", converted, len)),
+        None => Ok(converted),
+    }
+}
+
+fn parse_ca1(args: &[String], idx: usize) -> Result<(RuleSpec, usize), &'static str> {
+    let (radius, idx) = try!(parse_ca1_radius(args, idx));
+    let ((states, code), idx) = try!(parse_states_code(args, idx));
+    // count of neighborhoods is count of code digits, None if it doesn't fit in usize
+    let nb_width = (radius.0 as u32) + (radius.1 as u32) + 1;
+    let len = (states as usize).checked_pow(nb_width);
+    let code = match code {
+        Some(code) => Some(try!(convert_ca1_code(code, states, len))),
+        None => None,
+    };
+    Ok((RuleSpec::CA1 {
+        radius: radius,
+        states: states,
+        code: code,
+    },
+        idx))
+}
+
+fn parse_ca1_totalistic(args: &[String], idx: usize) -> Result<(RuleSpec, usize), &'static str> {
+    let ((radius, states, code), idx) = try!(parse_ca1_params(args, idx));
+    Ok((RuleSpec::CA1Totalistic {
+        radius: radius,
+        states: states,
+        code: code,
+    },
+        idx))
+}
+
+fn parse_ca1_outer_totalistic(args: &[String],
+                              idx: usize)
+                              -> Result<(RuleSpec, usize), &'static str> {
+    let ((radius, states, code), idx) = try!(parse_ca1_params(args, idx));
+    Ok((RuleSpec::CA1OuterTotalistic {
+        radius: radius,
+        states: states,
+        code: code,
+    },
+        idx))
+}
+
+fn parse_elementary_ca(args: &[String], idx: usize) -> Result<(RuleSpec, usize), &'static str> {
+    let (code, idx) = try!(parse::<u8>(args, idx)
+        .map_err(|_| "CODE must be unsigned 8-bit integer!"));
+    Ok((RuleSpec::Elementary(code as u8), idx))
+}
+
+fn parse_neighborhood(args: &[String],
+                      idx: usize)
+                      -> Result<(Neighborhood, usize), &'static str> {
+    if args.len() <= idx {
+        return Err("Expected neighborhood, found end of args!");
+    }
+    let nbh = try!(args[idx].parse().map_err(|_| "Neighborhood must look like m1, n2 or h1!"));
+    Ok((nbh, idx + 1))
+}
+
+fn parse_ca2_totalistic(args: &[String], idx: usize) -> Result<(RuleSpec, usize), &'static str> {
+    let (nb, idx) = try!(parse_neighborhood(args, idx));
+    let (states, idx) = try!(parse::<u8>(args, idx)
+        .map_err(|_| "STATES must be unsigned 8-bit integer!"));
+    if args.len() <= idx {
+        return Err("Specify CODE value!");
+    }
+    let code = if args[idx] == "random" {
+        None
+    } else {
+        Some(args[idx].clone())
+    };
+    Ok((RuleSpec::CA2Totalistic(nb, states, code), idx + 1))
+}
+
+fn parse_cyclic_ca(args: &[String], idx: usize) -> Result<(RuleSpec, usize), &'static str> {
+    let (nb, idx) = try!(parse_neighborhood(args, idx));
+    let (threshold, idx) = try!(match parse::<u8>(args, idx) {
+        Ok((val, idx)) => Ok((val, idx)),
+        Err(_) => Err("THRESHOLD must be unsigned 8-bit integer!"),
+    });
+    // more states than GIF palette can hold make no sense
+    let (states, idx) = try!(match parse::<u32>(args, idx) {
+        Ok((states, idx)) if states >= 2 && states <= 256 => Ok((states, idx)),
+        _ => Err("STATES must be integer in range 2-256!"),
+    });
+    if (threshold as usize) > nb.size() {
+        return Err("THRESHOLD can't be greater than count of cells in the neighborhood!");
+    }
+    Ok((RuleSpec::Cyclic(nb, threshold, states), idx))
+}
+
+fn parse_u32_csv(s: &str, sep: char) -> Result<Vec<u32>, ()> {
+    if s == "empty" {
+        return Ok(Vec::new());
+    }
+    let mut ints: Vec<u32> = Vec::new();
+    for part in s.split(sep) {
+        match part.parse::<u32>() {
+            Ok(x) => ints.push(x),
+            Err(_) => return Err(()),
+        }
+    }
+    Ok(ints)
+}
+
+fn parse_cell_csv(s: &str, sep: char) -> Result<Vec<Cell>, ()> {
+    let ints = try!(parse_u32_csv(s, sep));
+    if ints.iter().any(|&x| x > Cell::max_value() as u32) {
+        return Err(());
+    }
+    Ok(ints.into_iter().map(|x| x as Cell).collect())
+}
+
+// B3/S23 or S23/B3 in any case, lists of counts never contain '/'.
+fn is_rulestring(s: &str) -> bool {
+    s.contains('/') && s.starts_with(|c| c == 'B' || c == 'b' || c == 'S' || c == 's')
+}
+
+// Either SURVIVE BIRTH lists or a single rulestring.
+fn parse_survive_birth(args: &[String],
+                       idx: usize)
+                       -> Result<((Vec<Cell>, Vec<Cell>), usize), &'static str> {
+    if args.len() <= idx {
+        return Err("SURVIVE is not set!");
+    }
+    if is_rulestring(&args[idx]) {
+        let rule: LifeRule = try!(args[idx]
+            .parse()
+            .map_err(|_| "Rulestring must look like B3/S23!"));
+        return Ok(((rule.survive, rule.birth), idx + 1));
+    }
+    let (survive, idx) = try!(match parse_cell_csv(&args[idx], ',') {
+        Ok(survive) => Ok((survive, idx + 1)),
+        Err(_) => Err("Invalid SURVIVE value!"),
+    });
+    if args.len() <= idx {
+        return Err("BIRTH is not set!");
+    }
+    let (birth, idx) = try!(match parse_cell_csv(&args[idx], ',') {
+        Ok(birth) => Ok((birth, idx + 1)),
+        Err(_) => Err("Invalid BIRTH value!"),
+    });
+    Ok(((survive, birth), idx))
+}
+
+fn parse_life_ca(args: &[String], idx: usize) -> Result<(RuleSpec, usize), &'static str> {
+    let ((survive, birth), idx) = try!(parse_survive_birth(args, idx));
+    Ok((RuleSpec::Life(survive, birth), idx))
+}
+
+fn parse_generations_ca(args: &[String], idx: usize) -> Result<(RuleSpec, usize), &'static str> {
+    let ((survive, birth), idx) = try!(parse_survive_birth(args, idx));
+    let (states, idx) = try!(match parse::<u32>(args, idx) {
+        Ok((states, idx)) if states >= 2 => Ok((states, idx)),
+        _ => Err("STATES must be unsigned 32-bit integer >= 2!"),
+    });
+    Ok((RuleSpec::Generations(survive, birth, states), idx))
+}
+
+fn parse_coupled_ca(args: &[String], idx: usize) -> Result<(RuleSpec, usize), &'static str> {
+    let ((survive, birth), idx) = try!(parse_survive_birth(args, idx));
+    Ok((RuleSpec::Coupled(survive, birth), idx))
+}
+
+fn parse_ant(args: &[String], idx: usize) -> Result<(RuleSpec, usize), &'static str> {
+    if args.len() <= idx {
+        return Err("TURNS is not set!");
+    }
+    Ok((RuleSpec::Ant(args[idx].clone()), idx + 1))
+}
+
+fn parse_ltl_ca(args: &[String], idx: usize) -> Result<(RuleSpec, usize), &'static str> {
+    static ERR_INVALID_LTL: &'static str = "Invalid R,B1,B2,S1,S2 value!";
+    if args.len() <= idx {
+        return Err("R,B1,B2,S1,S2 is not set!");
+    }
+    let params = try!(parse_u32_csv(&args[idx], ',').map_err(|_| ERR_INVALID_LTL));
+    if params.len() != 5 || params[0] < 1 {
+        return Err(ERR_INVALID_LTL);
+    }
+    Ok((RuleSpec::Ltl(params[0], (params[1], params[2]), (params[3], params[4])), idx + 1))
+}
+
+fn parse_rule_spec(args: &[String]) -> Result<RuleSpec, &'static str> {
+    if args.is_empty() {
+        return Err("Specify CA type!");
+    }
+    let (rule, idx) = try!(match &*args[0] {
+        "1" => parse_ca1(args, 1),
+        "1t" => parse_ca1_totalistic(args, 1),
+        "1ot" => parse_ca1_outer_totalistic(args, 1),
+        "elementary" => parse_elementary_ca(args, 1),
+        "2t" => parse_ca2_totalistic(args, 1),
+        "cyclic" => parse_cyclic_ca(args, 1),
+        "life" => parse_life_ca(args, 1),
+        "generations" => parse_generations_ca(args, 1),
+        "coupled" => parse_coupled_ca(args, 1),
+        "brian" => Ok((RuleSpec::BriansBrain, 1)),
+        "wireworld" => Ok((RuleSpec::Wireworld, 1)),
+        "ant" => parse_ant(args, 1),
+        "ltl" => parse_ltl_ca(args, 1),
+        _ => Err("Unknown CA type!"),
+    });
+    if idx < args.len() {
+        Err("Trailing args!")
+    } else {
+        Ok(rule)
+    }
+}
+
+fn parse_init_state(part: &str) -> Result<(u32, u32), ()> {
+    match part.find('*') {
+        None => {
+            match part.parse::<u32>() {
+                Ok(val) => Ok((val, 1)),
+                Err(_) => Err(()),
+            }
+        }
+        Some(pos) => {
+            match part[..pos].parse::<u32>() {
+                Ok(val) => {
+                    match part[pos + 1..].parse::<u32>() {
+                        Ok(count) => Ok((val, count)),
+                        Err(_) => Err(()),
+                    }
+                }
+                Err(_) => Err(()),
+            }
+        }
+    }
+}
+
+const DENSITY_PREFIX: &'static str = "density=";
+
+// density=D, every cell is 1 with probability D
+fn parse_init_density(parts: &[&str]) -> Result<InitSpec, &'static str> {
+    if parts.len() > 1 {
+        return Err("random: X1, X2, Y1 and Y2 values can't be used with density!");
+    }
+    let density = try!(parts[0][DENSITY_PREFIX.len()..]
+        .parse::<f64>()
+        .map_err(|_| "random: invalid density value!"));
+    if !(density >= 0.0 && density <= 1.0) {
+        return Err("random: density must be between 0 and 1!");
+    }
+    Ok(InitSpec::RandomDensity(density))
+}
+
+fn parse_init_random(s: &str) -> Result<InitSpec, &'static str> {
+    if s == "" {
+        return Err(ERR_NO_STATES);
+    }
+    let parts: Vec<&str> = s.split(':').collect();
+    if parts.len() > 2 {
+        return Err(ERR_INVALID_RANDOM);
+    }
+
+    if parts[0].starts_with(DENSITY_PREFIX) {
+        return parse_init_density(&parts);
+    }
+
+    let states = if parts[0] == "uniform" {
+        None
+    } else {
+        let mut states = Vec::new();
+        for part in parts[0].split(',') {
+            let (state, count) = try!(parse_init_state(part).map_err(|_| ERR_INVALID_STATES));
+            if state > Cell::max_value() as u32 {
+                return Err(ERR_INVALID_STATES);
+            }
+            for _ in 0..count {
+                states.push(state as Cell);
+            }
+        }
+        if states.is_empty() {
+            return Err("random: STATES must contain at least one state!");
+        }
+        Some(states)
+    };
+
+    let (x1, x2, y1, y2) = if parts.len() == 1 {
+        (None, None, None, None)
+    } else {
+        let parts: Vec<&str> = parts[1].split(',').collect();
+        let x1 = Some(try!(parts[0]
+            .parse::<usize>()
+            .map_err(|_| "random: invalid X1 value!")));
+        let x2 = if parts.len() < 2 {
+            None
+        } else {
+            Some(try!(parts[1]
+                .parse::<usize>()
+                .map_err(|_| "random: invalid X2 value!")))
+        };
+        let y1 = if parts.len() < 3 {
+            None
+        } else {
+            Some(try!(parts[2]
+                .parse::<usize>()
+                .map_err(|_| "random: invalid Y1 value!")))
+        };
+        let y2 = if parts.len() < 4 {
+            None
+        } else {
+            Some(try!(parts[3]
+                .parse::<usize>()
+                .map_err(|_| "random: invalid Y2 value!")))
+        };
+        (x1, x2, y1, y2)
+    };
+
+    Ok(InitSpec::Random {
+        states: states,
+        x1: x1,
+        x2: x2,
+        y1: y1,
+        y2: y2,
+    })
+}
+
+// X[,Y], c, c+X[,Y] or c-X[,Y]
+fn parse_point(s: &str) -> Result<Point, ()> {
+    if s == "c" {
+        return Ok(Point::Center);
+    }
+    let (cplus, cminus) = (s.starts_with("c+"), s.starts_with("c-"));
+    let coords: Vec<&str> = if cplus || cminus {
+        s[2..].split(',').collect()
+    } else {
+        s.split(',').collect()
+    };
+    if coords.len() > 2 {
+        return Err(());
+    }
+    if cplus || cminus {
+        let sgn: i32 = if cplus {
+            1
+        } else {
+            -1
+        };
+        let shifts = try!(coords.iter()
+            .map(|shift| shift.parse::<u16>().map(|shift| (shift as i32) * sgn))
+            .collect::<Result<Vec<i32>, _>>()
+            .map_err(|_| ()));
+        Ok(Point::RelToCenter(shifts[0], shifts.get(1).cloned()))
+    } else {
+        let coords = try!(coords.iter()
+            .map(|coord| coord.parse::<usize>())
+            .collect::<Result<Vec<usize>, _>>()
+            .map_err(|_| ()));
+        Ok(Point::Abs(coords[0], coords.get(1).cloned()))
+    }
+}
+
+// POINT or POINT=STATE, state defaults to 1
+fn split_point_state(s: &str) -> Result<(&str, Cell), ()> {
+    match s.find('=') {
+        Some(pos) => Ok((&s[..pos], try!(s[pos + 1..].parse::<Cell>().map_err(|_| ())))),
+        None => Ok((s, 1)),
+    }
+}
+
+fn parse_points(s: &str) -> Result<InitSpec, ()> {
+    let mut points: Vec<(Point, Cell)> = Vec::new();
+    for part in s.split(';') {
+        let (point, state) = try!(split_point_state(part));
+        points.push((try!(parse_point(point)), state));
+    }
+    Ok(InitSpec::Points(points))
+}
+
+// NAME@POINT;NAME@POINT;...
+fn parse_patterns(s: &str) -> Result<InitSpec, &'static str> {
+    let mut patterns = Vec::new();
+    for part in s.split(';') {
+        let (name, point) = match part.find('@') {
+            Some(pos) => (&part[..pos], &part[pos + 1..]),
+            None => return Err("pattern: every pattern must have form NAME@POINT!"),
+        };
+        if patterns::by_name(name).is_none() {
+            return Err("pattern: unknown pattern name!");
+        }
+        let point = try!(parse_point(point).map_err(|_| ERR_INVALID_POINTS));
+        patterns.push((String::from(name), point));
+    }
+    Ok(InitSpec::Patterns(patterns))
+}
+
+fn parse_init_points(s: &str) -> Result<InitSpec, &'static str> {
+    static RLE_PREFIX: &'static str = "rle:";
+    static CELLS_PREFIX: &'static str = "cells:";
+    static PATTERN_PREFIX: &'static str = "pattern:";
+    if s == "" {
+        return Err(ERR_NO_POINTS);
+    }
+    if s.starts_with(PATTERN_PREFIX) {
+        return parse_patterns(&s[PATTERN_PREFIX.len()..]);
+    }
+    if s.starts_with(RLE_PREFIX) {
+        return Ok(InitSpec::Rle(String::from(&s[RLE_PREFIX.len()..])));
+    }
+    if s.starts_with(CELLS_PREFIX) {
+        return Ok(InitSpec::Plaintext(String::from(&s[CELLS_PREFIX.len()..])));
+    }
+    parse_points(s).map_err(|_| ERR_INVALID_POINTS)
+}
+
+// DIGITS[:ALIGN], every digit is the state of a cell in base of states
+fn parse_init_binary(s: &str) -> Result<InitSpec, &'static str> {
+    let (digits, align) = match s.find(':') {
+        Some(pos) => (&s[..pos], &s[pos + 1..]),
+        None => (s, "center"),
+    };
+    let align = match align {
+        "center" => Align::Center,
+        "right" => Align::Right,
+        _ => return Err("binary: ALIGN must be center or right!"),
+    };
+    if digits.is_empty() {
+        return Err("binary: STRING is empty!");
+    }
+    let cells = try!(digits.chars()
+        .map(|c| c.to_digit(36).map(|d| d as Cell).ok_or(ERR_INVALID_BINARY_DIGIT))
+        .collect::<Result<Vec<Cell>, _>>());
+    Ok(InitSpec::Binary(cells, align))
+}
+
+fn parse_init_spec(s: &str) -> Result<InitSpec, &'static str> {
+    static RANDOM_PREFIX: &'static str = "random:";
+    static POINTS_PREFIX: &'static str = "points:";
+    static IMAGE_PREFIX: &'static str = "image:";
+    static BINARY_PREFIX: &'static str = "binary:";
+    static CSV_PREFIX: &'static str = "csv:";
+    if s.starts_with(RANDOM_PREFIX) {
+        parse_init_random(&s[RANDOM_PREFIX.len()..])
+    } else if s.starts_with(POINTS_PREFIX) {
+        parse_init_points(&s[POINTS_PREFIX.len()..])
+    } else if s.starts_with(BINARY_PREFIX) {
+        parse_init_binary(&s[BINARY_PREFIX.len()..])
+    } else if s.starts_with(IMAGE_PREFIX) {
+        Ok(InitSpec::Image(String::from(&s[IMAGE_PREFIX.len()..])))
+    } else if s.starts_with(CSV_PREFIX) {
+        Ok(InitSpec::Csv(String::from(&s[CSV_PREFIX.len()..])))
+    } else {
+        Err("Unknown initialization type!")
+    }
+}
+
+fn check_init_spec(init: &InitSpec, rule: &RuleSpec) -> Result<(), &'static str> {
+    let (is_1d, states) = (rule.is_1d(), rule.states());
+    let fits = |point: &Point| if is_1d {
+        point.is_1d()
+    } else {
+        point.is_2d()
+    };
+    match *init {
+        InitSpec::Random { ref states, y1, y2, .. } => {
+            if is_1d && (y1.is_some() || y2.is_some()) {
+                return Err("random: Y1 and Y2 values are disabled for 1D CA!");
+            }
+            let max_state = states.as_ref().and_then(|states| states.iter().max().cloned());
+            if max_state.map_or(false, |state| state as u32 >= rule.states()) {
+                return Err("random: STATE must be less than count of states!");
+            }
+        }
+        InitSpec::RandomDensity(..) if is_1d => {
+            return Err("random: density is supported only for 2D CA!");
+        }
+        InitSpec::Points(ref points) => {
+            if points.iter().any(|&(ref point, _)| !fits(point)) {
+                return Err(ERR_INVALID_POINTS);
+            }
+            if points.iter().any(|&(_, state)| state as u32 >= states) {
+                return Err("points: STATE must be less than count of states!");
+            }
+        }
+        InitSpec::Binary(ref cells, _) => {
+            if !is_1d {
+                return Err("Binary init is supported only for 1D CA!");
+            }
+            if states > 36 {
+                return Err("Binary init is supported only for CA with at most 36 states!");
+            }
+            if cells.iter().any(|&cell| cell as u32 >= states) {
+                return Err(ERR_INVALID_BINARY_DIGIT);
+            }
+        }
+        InitSpec::Patterns(ref patterns) => {
+            if is_1d {
+                return Err("Patterns are supported only for 2D CA!");
+            }
+            if patterns.iter().any(|&(_, ref point)| !fits(point)) {
+                return Err(ERR_INVALID_POINTS);
+            }
+        }
+        InitSpec::Rle(..) |
+        InitSpec::Plaintext(..) if is_1d => {
+            return Err("Pattern files are supported only for 2D CA!");
+        }
+        InitSpec::Image(..) if is_1d => return Err("Image init is supported only for 2D CA!"),
+        InitSpec::Csv(..) if is_1d => return Err("CSV init is supported only for 2D CA!"),
+        _ => (),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_neighborhood() {
+        for s in &["m1", "n2", "h10"] {
+            assert_eq!(s.parse::<Neighborhood>().unwrap().to_string(), *s);
+        }
+        assert_eq!("n3".parse::<Neighborhood>(), Ok(Neighborhood::VonNeumann(3)));
+        for s in &["", "m", "x1", "m-1", "m1x"] {
+            assert_eq!(s.parse::<Neighborhood>(), Err(CaError::InvalidNeighborhood));
+        }
+    }
+
+    #[test]
+    fn test_life_rule() {
+        let life = LifeRule {
+            survive: vec![2, 3],
+            birth: vec![3],
+        };
+        assert_eq!("B3/S23".parse::<LifeRule>(), Ok(life.clone()));
+        assert_eq!("s23/b3".parse::<LifeRule>(), Ok(life.clone()));
+        assert_eq!(life.to_string().parse::<LifeRule>(), Ok(life));
+        assert_eq!("B/S".parse::<LifeRule>().unwrap().to_string(), "B/S");
+        for s in &["B3", "B3/S2x", "B3/B2", "B3/S2/S3"] {
+            assert_eq!(s.parse::<LifeRule>(), Err(CaError::InvalidRulestring));
+        }
+    }
+
+    #[test]
+    fn test_rule_spec() {
+        for s in &["1 1 3 012012012012012012012012012",
+                   "1 0,1 2 random",
+                   "1t 2 3 0120120",
+                   "1ot 1 2 1010",
+                   "elementary 110",
+                   "2t n1 2 random",
+                   "cyclic h1 2 5",
+                   "life 2,3 3",
+                   "generations empty 2 3",
+                   "coupled 2,3 3,6",
+                   "brian",
+                   "wireworld",
+                   "ant RLR",
+                   "ltl 5,34,45,34,58"] {
+            assert_eq!(s.parse::<RuleSpec>().unwrap().to_string(), *s);
+        }
+        for s in &["life B3/S23", "life S23/B3", "life s23/b3"] {
+            assert_eq!(s.parse::<RuleSpec>(), Ok(RuleSpec::Life(vec![2, 3], vec![3])));
+        }
+        assert_eq!("1 1 2 0x1e".parse::<RuleSpec>().unwrap().to_string(),
+                   "1 1 2 00011110");
+        assert_eq!("cyclic m1 2 5".parse::<RuleSpec>().unwrap().states(), 5);
+        for s in &["", "life", "life 23", "cyclic m1 10 3", "brian 1", "unknown"] {
+            assert!(s.parse::<RuleSpec>().is_err());
+        }
+    }
+
+    #[test]
+    fn test_init_spec() {
+        for s in &["random:uniform",
+                   "random:0,1,1:2,5",
+                   "random:density=0.25",
+                   "points:c;c+2,1=2;3,4",
+                   "points:c-3;7=0",
+                   "binary:101:right",
+                   "points:pattern:glider@c-1,1",
+                   "points:rle:glider.rle",
+                   "points:cells:glider.cells",
+                   "image:cat.png",
+                   "csv:world.csv"] {
+            assert_eq!(s.parse::<InitSpec>().unwrap().to_string(), *s);
+        }
+        assert_eq!("random:0*2,1".parse::<InitSpec>().unwrap().to_string(),
+                   "random:0,0,1");
+        assert_eq!(InitSpec::default().to_string(), "random:uniform");
+        for s in &["", "random:", "random:density=2", "points:1,2,3", "binary:", "nothing"] {
+            assert!(s.parse::<InitSpec>().is_err());
+        }
+
+        let life = RuleSpec::Life(vec![2, 3], vec![3]);
+        let elementary = RuleSpec::Elementary(30);
+        let init = |s: &str| s.parse::<InitSpec>().unwrap();
+        assert!(init("points:c;1,2").check(&life).is_ok());
+        assert!(init("points:c;1").check(&elementary).is_ok());
+        assert!(init("points:1").check(&life).is_err());
+        assert!(init("points:1,2").check(&elementary).is_err());
+        assert!(init("points:c=2").check(&life).is_err());
+        assert!(init("random:0,2").check(&life).is_err());
+        assert!(init("binary:12").check(&elementary).is_err());
+        assert!(init("binary:1").check(&life).is_err());
+        assert!(init("csv:world.csv").check(&elementary).is_err());
+    }
+}
@@ -1 +1,6 @@
+// State of a cell. u32 by default, the small-cells feature makes it u8 to save memory on
+// large grids, then CA can't have more than 256 states.
+#[cfg(not(feature = "small-cells"))]
 pub type Cell = u32;
+#[cfg(feature = "small-cells")]
+pub type Cell = u8;